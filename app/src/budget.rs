@@ -0,0 +1,179 @@
+use crate::{dollar::Dollar, error, split, vope};
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// How long one budget period is
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Period {
+    /// Calendar months, starting on this day of the month (1 to 28)
+    Monthly { start_day: u32 },
+    /// Runs of this many weeks, counted from the anchor date
+    Weeks { weeks: u32, anchor: NaiveDate },
+}
+
+impl Default for Period {
+    fn default() -> Self {
+        Period::Monthly { start_day: 1 }
+    }
+}
+
+impl Period {
+    /// Checks the period can be used
+    ///
+    /// Returns `Err(InvalidPeriod)` if the start day is not 1 to 28, so every
+    /// month has one, or there are no weeks
+    pub fn validate(&self) -> Result<(), error::Error> {
+        let ok = match self {
+            Period::Monthly { start_day } => (1..=28).contains(start_day),
+            Period::Weeks { weeks, .. } => *weeks > 0,
+        };
+
+        if ok {
+            Ok(())
+        } else {
+            Err(error::Error::InvalidPeriod)
+        }
+    }
+
+    /// The first day of the period `date` falls in
+    pub fn start_of(&self, date: NaiveDate) -> NaiveDate {
+        match *self {
+            Period::Monthly { start_day } => {
+                let (y, m) = if date.day() >= start_day {
+                    (date.year(), date.month())
+                } else if date.month() == 1 {
+                    (date.year() - 1, 12)
+                } else {
+                    (date.year(), date.month() - 1)
+                };
+                // start_day is at most 28, so every month has it
+                NaiveDate::from_ymd_opt(y, m, start_day).unwrap()
+            }
+            Period::Weeks { weeks, anchor } => {
+                let len = 7 * weeks as i64;
+                let n = (date - anchor).num_days().div_euclid(len);
+                anchor + Duration::days(n * len)
+            }
+        }
+    }
+
+    /// The first day of the period after the one starting on `start`
+    pub fn next(&self, start: NaiveDate) -> NaiveDate {
+        match *self {
+            Period::Monthly { start_day } => {
+                let (y, m) = if start.month() == 12 {
+                    (start.year() + 1, 1)
+                } else {
+                    (start.year(), start.month() + 1)
+                };
+                NaiveDate::from_ymd_opt(y, m, start_day).unwrap()
+            }
+            Period::Weeks { weeks, .. } => start + Duration::days(7 * weeks as i64),
+        }
+    }
+
+    /// The first day of the period before the one starting on `start`
+    pub fn prev(&self, start: NaiveDate) -> NaiveDate {
+        self.start_of(start - Duration::days(1))
+    }
+}
+
+/// What happens to what is left in a vope when a period ends
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rollover {
+    /// Surpluses and overspending both carry into the next period
+    #[default]
+    Carry,
+    /// Money left over carries, overspending is forgiven
+    CarrySurplus,
+    /// Overspending is taken from the next period, money left over is not kept
+    CarryDeficit,
+    /// Every period starts from nothing
+    Reset,
+}
+
+impl Rollover {
+    fn apply(&self, left: Dollar) -> Dollar {
+        match self {
+            Rollover::Carry => left,
+            Rollover::CarrySurplus => left.max(Dollar::ZERO),
+            Rollover::CarryDeficit => left.min(Dollar::ZERO),
+            Rollover::Reset => Dollar::ZERO,
+        }
+    }
+}
+
+/// One vope's figures for one period
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PeriodSummary {
+    pub start: NaiveDate,
+    /// The first day of the next period
+    pub end: NaiveDate,
+    /// Brought forward from the period before, after the rollover rule
+    pub carried: Dollar,
+    pub budgeted: Dollar,
+    /// Money spent in the period, as a positive amount. Income is not counted.
+    pub spent: Dollar,
+    /// `carried + budgeted - spent`
+    pub available: Dollar,
+}
+
+/// Works out the period containing `date` for one vope.
+///
+/// Every period from the vope's first transaction on is walked through in
+/// turn, so what each one carries follows the vope's rollover rule. Only the
+/// vope's part of a split transaction is counted.
+///
+/// Each period is budgeted what the vope's budget was when it ended, from
+/// `budgets` as `Ledger::budget_history` gives it. Periods before the first
+/// of them get that first budget, and with no history at all the vope's
+/// current budget is used.
+pub fn summarize(
+    v: &vope::Vope,
+    history: &[split::Portion],
+    budgets: &[(NaiveDate, Dollar)],
+    period: &Period,
+    date: NaiveDate,
+) -> PeriodSummary {
+    let target = period.start_of(date);
+
+    let first = history
+        .iter()
+        .map(|p| period.start_of(p.transaction.date))
+        .min()
+        .map_or(target, |s| s.min(target));
+
+    let mut start = first;
+    let mut carried = Dollar::ZERO;
+
+    loop {
+        let end = period.next(start);
+        let budgeted = budgets
+            .iter()
+            .rev()
+            .find(|(set, _)| *set < end)
+            .or(budgets.first())
+            .map_or(v.budget, |(_, b)| *b);
+        let spent: Dollar = history
+            .iter()
+            .filter(|p| p.transaction.date >= start && p.transaction.date < end && p.amount.is_negative())
+            .map(|p| -p.amount)
+            .sum();
+
+        let summary = PeriodSummary {
+            start,
+            end,
+            carried,
+            budgeted,
+            spent,
+            available: carried + budgeted - spent,
+        };
+
+        if start >= target {
+            return summary;
+        }
+
+        carried = v.rollover.apply(summary.available);
+        start = end;
+    }
+}
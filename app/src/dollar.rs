@@ -1,64 +1,265 @@
-use crate::misc;
 use serde;
-use std::{fmt, ops};
+use std::{fmt, iter, num, ops, str};
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, Default, Clone, Copy)]
+/// An exact amount of money, stored as a whole number of cents.
+///
+/// All arithmetic is done on integers, so sums never drift. The plain
+/// operators panic on overflow; use the `checked_*` methods when the
+/// amounts come from the user.
+#[derive(
+    serde::Serialize, serde::Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(from = "DollarRepr")]
 pub struct Dollar {
-    amount: f32,
+    cents: i64,
+}
+
+/// Every shape a Dollar has been saved as.
+///
+/// Older account files stored `{"amount": 12.34}` as a float, newer ones
+/// store `{"cents": 1234}`.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum DollarRepr {
+    Cents { cents: i64 },
+    Legacy { amount: f64 },
+}
+
+impl From<DollarRepr> for Dollar {
+    fn from(value: DollarRepr) -> Self {
+        match value {
+            DollarRepr::Cents { cents } => Dollar::from_cents(cents),
+            DollarRepr::Legacy { amount } => Dollar::from(amount),
+        }
+    }
 }
 
 impl Dollar {
+    pub const ZERO: Dollar = Dollar { cents: 0 };
+
+    pub const fn from_cents(cents: i64) -> Dollar {
+        Dollar { cents }
+    }
+
+    pub const fn cents(&self) -> i64 {
+        self.cents
+    }
+
     pub fn as_f64(&self) -> f64 {
-        self.amount as f64
+        self.cents as f64 / 100.0
     }
 
     pub fn as_f32(&self) -> f32 {
-        self.amount
+        self.as_f64() as f32
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.cents == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.cents < 0
+    }
+
+    pub fn abs(&self) -> Dollar {
+        Dollar::from_cents(self.cents.abs())
+    }
+
+    pub fn checked_add(self, rhs: Dollar) -> Option<Dollar> {
+        self.cents.checked_add(rhs.cents).map(Dollar::from_cents)
+    }
+
+    pub fn checked_sub(self, rhs: Dollar) -> Option<Dollar> {
+        self.cents.checked_sub(rhs.cents).map(Dollar::from_cents)
+    }
+
+    pub fn checked_mul(self, rhs: i64) -> Option<Dollar> {
+        self.cents.checked_mul(rhs).map(Dollar::from_cents)
+    }
+
+    pub fn checked_neg(self) -> Option<Dollar> {
+        self.cents.checked_neg().map(Dollar::from_cents)
+    }
+
+    /// The amount as a plain decimal, such as `-12.34`, for file formats
+    /// that do not want a currency sign
+    pub fn to_decimal_string(&self) -> String {
+        let sign = if self.cents < 0 { "-" } else { "" };
+        let abs = self.cents.unsigned_abs();
+        format!("{}{}.{:02}", sign, abs / 100, abs % 100)
+    }
+
+    /// Splits this amount into one share per weight, proportional to the weights.
+    ///
+    /// Shares are rounded toward zero, then the leftover cents are handed out
+    /// one at a time to the shares with the largest remainders, so the result
+    /// always sums back to exactly `self`. The weights are first turned into
+    /// whole numbers, keeping about 12 significant digits, and the sharing is
+    /// done in integers so it stays exact however large the amount.
+    ///
+    /// Returns `None` if there are no weights, any weight is negative or not
+    /// finite, or they all add up to zero.
+    pub fn allocate(&self, weights: &[f64]) -> Option<Vec<Dollar>> {
+        if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+            return None;
+        }
+
+        let total: f64 = weights.iter().sum();
+        if weights.is_empty() || total <= 0.0 {
+            return None;
+        }
+
+        let magnitude = self.cents.unsigned_abs() as u128;
+        let sign = self.cents.signum() as i128;
+
+        // Whole-number weights, the largest being 2^40. With a magnitude
+        // below 2^64 every product fits in a u128.
+        let largest = weights.iter().cloned().fold(0.0, f64::max);
+        let scale = (1u64 << 40) as f64 / largest;
+        let units: Vec<u128> = weights.iter().map(|w| (w * scale).round() as u128).collect();
+        let total: u128 = units.iter().sum();
+
+        // Whole cents each share gets, and how much it was rounded down by,
+        // in units of 1 / total of a cent
+        let shares_and_remainders: Vec<(u128, u128)> = units
+            .iter()
+            .map(|u| (magnitude * u / total, magnitude * u % total))
+            .collect();
+        let mut shares: Vec<u128> = shares_and_remainders.iter().map(|(s, _)| *s).collect();
+
+        let given: u128 = shares.iter().sum();
+        let mut leftover = magnitude - given;
+
+        // Largest remainder first, ties go to the earliest share
+        let mut order: Vec<usize> = (0..weights.len()).filter(|i| units[*i] > 0).collect();
+        order.sort_by(|a, b| {
+            shares_and_remainders[*b].1.cmp(&shares_and_remainders[*a].1).then(a.cmp(b))
+        });
+
+        for i in order.iter().cycle() {
+            if leftover == 0 {
+                break;
+            }
+            shares[*i] += 1;
+            leftover -= 1;
+        }
+
+        Some(
+            shares
+                .into_iter()
+                .map(|s| Dollar::from_cents((s as i128 * sign) as i64))
+                .collect(),
+        )
+    }
+
+    /// Splits this amount into `parts` shares that differ by at most a cent.
+    pub fn split_evenly(&self, parts: usize) -> Option<Vec<Dollar>> {
+        self.allocate(&vec![1.0; parts])
     }
 }
 
 impl From<f32> for Dollar {
     fn from(value: f32) -> Self {
-        Dollar { amount: value }
+        Dollar::from(value as f64)
+    }
+}
+
+impl From<f64> for Dollar {
+    fn from(value: f64) -> Self {
+        Dollar::from_cents((value * 100.0).round() as i64)
     }
 }
 
+/// Lenient conversion for user input, anything unreadable becomes $0.00
 impl From<&str> for Dollar {
     fn from(value: &str) -> Self {
+        value.parse().unwrap_or_default()
+    }
+}
+
+/// The reasons a string is not a Dollar amount
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseDollarError {
+    Empty,
+    InvalidDigit,
+    Overflow,
+}
+
+impl fmt::Display for ParseDollarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseDollarError::Empty => write!(f, "no amount given"),
+            ParseDollarError::InvalidDigit => write!(f, "invalid digit in amount"),
+            ParseDollarError::Overflow => write!(f, "amount is too large"),
+        }
+    }
+}
+
+impl std::error::Error for ParseDollarError {}
+
+impl From<num::ParseIntError> for ParseDollarError {
+    fn from(_: num::ParseIntError) -> Self {
+        ParseDollarError::Overflow
+    }
+}
+
+/// Parses amounts like `12`, `-12.5`, `$1,234.56` or `$-0.99` exactly, without
+/// going through a float. Digits past the cents are rounded half away from zero.
+impl str::FromStr for Dollar {
+    type Err = ParseDollarError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut rv = String::new();
-        for c in value.chars() {
+        for c in s.chars() {
             if c != '$' && c != ' ' && c != '+' && c != ',' {
                 rv.push(c);
             }
         }
 
-        Dollar::from(rv.parse::<f32>().unwrap_or(0.0))
-    }
-}
+        let (negative, digits) = match rv.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, rv.as_str()),
+        };
 
-impl Eq for Dollar {}
+        let (whole, frac) = digits.split_once('.').unwrap_or((digits, ""));
 
-impl ops::Div for Dollar {
-    type Output = f32;
+        if whole.is_empty() && frac.is_empty() {
+            return Err(ParseDollarError::Empty);
+        }
+        if !whole.chars().chain(frac.chars()).all(|c| c.is_ascii_digit()) {
+            return Err(ParseDollarError::InvalidDigit);
+        }
 
-    fn div(self, rhs: Self) -> Self::Output {
-        self.amount / rhs.amount
+        let whole: i64 = if whole.is_empty() { 0 } else { whole.parse()? };
+
+        // Pad or cut the fraction to exactly two digits
+        let mut frac_digits = frac.bytes().map(|b| (b - b'0') as i64);
+        let tenths = frac_digits.next().unwrap_or(0);
+        let hundredths = frac_digits.next().unwrap_or(0);
+        let round_up = frac_digits.next().unwrap_or(0) >= 5;
+
+        let cents = whole
+            .checked_mul(100)
+            .and_then(|c| c.checked_add(tenths * 10 + hundredths + round_up as i64))
+            .ok_or(ParseDollarError::Overflow)?;
+
+        Ok(Dollar::from_cents(if negative { -cents } else { cents }))
     }
 }
 
-impl ops::Mul<f32> for Dollar {
-    type Output = Self;
+impl ops::Div for Dollar {
+    type Output = f64;
 
-    fn mul(self, rhs: f32) -> Self::Output {
-        Dollar::from(misc::round(self.amount * rhs, 2))
+    fn div(self, rhs: Self) -> Self::Output {
+        self.cents as f64 / rhs.cents as f64
     }
 }
 
-impl ops::Add<f32> for Dollar {
-    type Output = Dollar;
+impl ops::Mul<i64> for Dollar {
+    type Output = Self;
 
-    fn add(self, rhs: f32) -> Dollar {
-        Dollar::from(misc::round(self.amount + rhs, 2))
+    fn mul(self, rhs: i64) -> Self::Output {
+        self.checked_mul(rhs).expect("Dollar multiplication overflowed")
     }
 }
 
@@ -66,7 +267,7 @@ impl ops::Add<Dollar> for Dollar {
     type Output = Dollar;
 
     fn add(self, rhs: Dollar) -> Dollar {
-        Dollar::from(misc::round(self.amount + rhs.amount, 2))
+        self.checked_add(rhs).expect("Dollar addition overflowed")
     }
 }
 
@@ -74,58 +275,44 @@ impl ops::Sub<Dollar> for Dollar {
     type Output = Dollar;
 
     fn sub(self, rhs: Dollar) -> Self::Output {
-        Dollar::from(misc::round(self.amount - rhs.amount, 2))
+        self.checked_sub(rhs).expect("Dollar subtraction overflowed")
     }
 }
 
-impl ops::Sub<f32> for Dollar {
-    type Output = Self;
-
-    fn sub(self, rhs: f32) -> Self::Output {
-        Dollar::from(misc::round(self.amount - rhs, 2))
-    }
-}
+impl ops::Neg for Dollar {
+    type Output = Dollar;
 
-impl ops::AddAssign<f32> for Dollar {
-    fn add_assign(&mut self, rhs: f32) {
-        self.amount = misc::round(self.amount + rhs, 2);
+    fn neg(self) -> Self::Output {
+        self.checked_neg().expect("Dollar negation overflowed")
     }
 }
 
 impl ops::AddAssign<Dollar> for Dollar {
     fn add_assign(&mut self, rhs: Dollar) {
-        self.amount = misc::round(self.amount + rhs.amount, 2);
+        *self = *self + rhs;
     }
 }
 
 impl ops::SubAssign<Dollar> for Dollar {
     fn sub_assign(&mut self, rhs: Dollar) {
-        self.amount = misc::round(self.amount - rhs.amount, 2);
+        *self = *self - rhs;
     }
 }
 
-impl PartialEq for Dollar {
-    fn eq(&self, other: &Self) -> bool {
-        self.amount == other.amount
+impl iter::Sum for Dollar {
+    fn sum<I: Iterator<Item = Dollar>>(iter: I) -> Self {
+        iter.fold(Dollar::ZERO, |acc, d| acc + d)
     }
 }
 
-impl PartialOrd for Dollar {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.amount.partial_cmp(&other.amount)
-    }
-}
-
-impl Ord for Dollar {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.amount.total_cmp(&other.amount)
+impl<'a> iter::Sum<&'a Dollar> for Dollar {
+    fn sum<I: Iterator<Item = &'a Dollar>>(iter: I) -> Self {
+        iter.copied().sum()
     }
 }
 
 impl fmt::Display for Dollar {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "${:.2}", self.amount)
+        write!(f, "${}", self.to_decimal_string())
     }
 }
-
-
@@ -0,0 +1,199 @@
+use crate::{dollar, transaction};
+use std::{error, fmt, io, path};
+
+/// Everything that can go wrong inside the app crate.
+///
+/// Each variant carries enough context for the GUI to tell the user what
+/// happened, and for tests to match on.
+#[derive(Debug)]
+pub enum Error {
+    /// A vope with this name (ignoring case) already exists
+    DuplicateVope(String),
+    /// No vope has this name
+    UnknownVope(String),
+    /// A vope cannot be merged into itself
+    SameVope(String),
+    /// A vope's name cannot be blank
+    BlankVopeName,
+    /// The vope still holds money, so it cannot be removed
+    NonZeroBalance {
+        name: String,
+        balance: dollar::Dollar,
+    },
+    /// No assigned transaction has this ID
+    UnknownTransaction(transaction::TransactionId),
+    /// This transaction has already been assigned
+    DuplicateTransaction(transaction::TransactionId),
+    /// An import profile with this name already exists
+    DuplicateProfile(String),
+    /// No import profile has this name
+    UnknownProfile(String),
+    /// CSV fields can only be separated by a single ASCII character
+    InvalidDelimiter(char),
+    /// A rule with this name already exists
+    DuplicateRule(String),
+    /// No rule has this name
+    UnknownRule(String),
+    /// The rule's description pattern is not a valid regex
+    InvalidRule(String),
+    /// A paycheck was cashed before picking the vope that gets the leftover
+    NoDefaultVope,
+    /// Only money coming in can be cashed as a paycheck
+    NotIncome(transaction::TransactionId),
+    /// A fixed paycheck amount for this vope is below $0
+    NegativeAllocation {
+        name: String,
+        amount: dollar::Dollar,
+    },
+    /// Fixed amounts add up to more than the paycheck
+    OverAllocated {
+        amount: dollar::Dollar,
+        allocated: dollar::Dollar,
+    },
+    /// Budget periods must start on a day every month has, and last at least a week
+    InvalidPeriod,
+    /// A savings goal's target or contribution is not positive
+    InvalidGoal,
+    /// A recurring transaction with this name already exists
+    DuplicateSchedule(String),
+    /// No recurring transaction has this name
+    UnknownSchedule(String),
+    /// A funding account with this name already exists
+    DuplicateFunding(String),
+    /// No funding account has this name
+    UnknownFunding(String),
+    /// A vope group with this name already exists
+    DuplicateGroup(String),
+    /// No vope group has this name
+    UnknownGroup(String),
+    /// The transaction is part of a finished reconciliation
+    Reconciled(transaction::TransactionId),
+    /// The cleared transactions do not add up to the statement balance
+    Unbalanced(dollar::Dollar),
+    /// The weights given to split a transaction are empty, negative or all zero
+    InvalidWeights,
+    /// The amounts a transaction was split into do not add up to its charge
+    SplitMismatch {
+        split: dollar::Dollar,
+        charge: dollar::Dollar,
+    },
+    /// A file could not be understood. Line and column are 1-based, and are
+    /// 0 when unknown
+    Parse { line: usize, column: usize },
+    /// The account file was written by a newer version of Money Man
+    UnsupportedVersion(u32),
+    /// A new account would overwrite the one already at this path
+    AccountExists(path::PathBuf),
+    /// A file could not be read or written
+    Io {
+        path: path::PathBuf,
+        source: io::Error,
+    },
+}
+
+impl Error {
+    /// Wraps an io::Error with the path that caused it
+    pub(crate) fn io(path: &path::Path, source: io::Error) -> Error {
+        Error::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::DuplicateVope(name) => write!(f, "A vope named \"{}\" already exists", name),
+            Error::UnknownVope(name) => write!(f, "There is no vope named \"{}\"", name),
+            Error::SameVope(name) => write!(f, "\"{}\" cannot be merged into itself", name),
+            Error::BlankVopeName => write!(f, "A vope needs a name"),
+            Error::NonZeroBalance { name, balance } => write!(
+                f,
+                "\"{}\" still holds {}, empty it before removing",
+                name, balance
+            ),
+            Error::UnknownTransaction(id) => write!(f, "There is no transaction {}", id),
+            Error::DuplicateTransaction(id) => {
+                write!(f, "Transaction {} has already been assigned", id)
+            }
+            Error::DuplicateProfile(name) => {
+                write!(f, "An import profile named \"{}\" already exists", name)
+            }
+            Error::UnknownProfile(name) => write!(f, "There is no import profile named \"{}\"", name),
+            Error::InvalidDelimiter(c) => {
+                write!(f, "\"{}\" cannot separate CSV fields, use a single ASCII character", c)
+            }
+            Error::DuplicateRule(name) => write!(f, "A rule named \"{}\" already exists", name),
+            Error::UnknownRule(name) => write!(f, "There is no rule named \"{}\"", name),
+            Error::InvalidRule(name) => {
+                write!(f, "The description pattern of rule \"{}\" is not valid", name)
+            }
+            Error::NoDefaultVope => {
+                write!(f, "Pick a default vope to receive what is left of the paycheck")
+            }
+            Error::NotIncome(id) => write!(f, "Transaction {} is not income", id),
+            Error::NegativeAllocation { name, amount } => {
+                write!(f, "{} cannot get {} from a paycheck, amounts must be $0 or more", name, amount)
+            }
+            Error::OverAllocated { amount, allocated } => write!(
+                f,
+                "{} is allocated, but the paycheck is only {}",
+                allocated, amount
+            ),
+            Error::InvalidPeriod => write!(
+                f,
+                "Monthly periods must start on day 1 to 28, and weekly ones last at least a week"
+            ),
+            Error::InvalidGoal => write!(f, "A goal's target or contribution must be more than $0"),
+            Error::DuplicateSchedule(name) => {
+                write!(f, "A recurring transaction named \"{}\" already exists", name)
+            }
+            Error::UnknownSchedule(name) => {
+                write!(f, "There is no recurring transaction named \"{}\"", name)
+            }
+            Error::Reconciled(id) => {
+                write!(f, "Transaction {} is reconciled and cannot be changed", id)
+            }
+            Error::Unbalanced(difference) => {
+                write!(f, "The cleared transactions are {} off the statement", difference)
+            }
+            Error::DuplicateFunding(name) => write!(f, "An account named \"{}\" already exists", name),
+            Error::UnknownFunding(name) => write!(f, "There is no account named \"{}\"", name),
+            Error::DuplicateGroup(name) => write!(f, "A group named \"{}\" already exists", name),
+            Error::UnknownGroup(name) => write!(f, "There is no group named \"{}\"", name),
+            Error::InvalidWeights => write!(f, "Select at least one vope with a positive weight"),
+            Error::SplitMismatch { split, charge } => {
+                write!(f, "The split adds up to {}, but the transaction is {}", split, charge)
+            }
+            Error::Parse { line, column } => {
+                write!(f, "Could not read line {}, column {}", line, column)
+            }
+            Error::UnsupportedVersion(v) => write!(
+                f,
+                "This account file is version {}, update Money Man to open it",
+                v
+            ),
+            Error::AccountExists(path) => write!(f, "There is already an account at {}", path.display()),
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        Error::Parse {
+            line: value.line(),
+            column: value.column(),
+        }
+    }
+}
@@ -0,0 +1,320 @@
+// Writes the whole portfolio out for other accounting tools.
+//
+// The plain-text formats treat each vope as an asset account holding its
+// share of the money, named `Assets:Vopes:<vope>`. Spending from a vope is
+// balanced against `Expenses:<vope>` and income into it against
+// `Income:<vope>`, so every transaction balances and each vope account ends
+// on the vope's balance. Transfers move money between vope accounts, and
+// opening balances come from `Equity:Opening-Balances`. Ignored transactions
+// go through the `Ignored` vope like any other. Two vopes whose names clean
+// up to the same account part, such as `Eating Out` and `Eating-Out`, are
+// told apart by a `-2`, `-3` and so on after the later ones.
+
+use crate::{dollar::Dollar, error, ledger, misc, portfolio, qif, transaction};
+use chrono::NaiveDate;
+use std::{collections, fmt, fmt::Write, path};
+
+/// A file format the portfolio can be exported to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Quicken Interchange Format, see `qif::write_portfolio`
+    Qif,
+    /// A ledger journal, which hledger reads too
+    Ledger,
+    Beancount,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Qif, Format::Ledger, Format::Beancount];
+
+    /// The file extension files of this format usually have
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Qif => "qif",
+            Format::Ledger => "ledger",
+            Format::Beancount => "beancount",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Qif => "QIF",
+            Format::Ledger => "ledger / hledger",
+            Format::Beancount => "beancount",
+        })
+    }
+}
+
+/// The commodity beancount amounts are written in
+const CURRENCY: &str = "USD";
+
+/*
+ * One posting of an exported transaction
+ */
+struct Posting {
+    account: String,
+    amount: Dollar,
+}
+
+/*
+ * One exported transaction, or a transfer or opening balance
+ */
+struct Entry {
+    date: NaiveDate,
+    cleared: bool,
+    desc: String,
+    source: Option<String>,
+    notes: String,
+    tags: Vec<String>,
+    postings: Vec<Posting>,
+}
+
+/// Writes the portfolio in `format`
+pub fn write(port: &portfolio::Portfolio, format: Format) -> String {
+    match format {
+        Format::Qif => qif::write_portfolio(port),
+        Format::Ledger => write_ledger(&entries(port)),
+        Format::Beancount => write_beancount(&entries(port)),
+    }
+}
+
+/// Saves `write` to `path`
+pub fn export(port: &portfolio::Portfolio, format: Format, path: &path::Path) -> Result<(), error::Error> {
+    misc::write_atomic(path, write(port, format).as_bytes()).map_err(|e| error::Error::io(path, e))
+}
+
+/// The account name a vope's money is kept under, before any suffix that
+/// tells it apart from another vope's
+pub fn vope_account(name: &str) -> String {
+    format!("Assets:Vopes:{}", account_part(name))
+}
+
+/*
+ * Makes a name safe to use as one part of an account name in both formats,
+ * which beancount is the stricter about: a capital letter or digit first,
+ * then letters, digits and dashes
+ */
+fn account_part(name: &str) -> String {
+    let mut part: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+
+    match part.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => part[..1].make_ascii_uppercase(),
+        Some(c) if c.is_ascii_digit() => {}
+        _ => part.insert(0, 'V'),
+    }
+    part
+}
+
+/*
+ * The account part each vope is exported under, unique across the export
+ */
+#[derive(Default)]
+struct Parts {
+    by_vope: collections::HashMap<String, String>,
+    taken: collections::HashSet<String>,
+}
+
+impl Parts {
+    /*
+     * The listed vopes claim their parts first, so a suffix goes to the
+     * later one whatever order the entries are in
+     */
+    fn new(port: &portfolio::Portfolio) -> Parts {
+        let mut parts = Parts::default();
+        for v in port.view_vopes().iter().chain(std::iter::once(port.view_ignored())) {
+            parts.part(&v.name);
+        }
+        parts
+    }
+
+    fn part(&mut self, vope: &str) -> String {
+        let key = vope.to_lowercase();
+        if let Some(part) = self.by_vope.get(&key) {
+            return part.clone();
+        }
+
+        let base = account_part(vope);
+        let mut part = base.clone();
+        for n in 2.. {
+            if !self.taken.contains(&part) {
+                break;
+            }
+            part = format!("{}-{}", base, n);
+        }
+
+        self.taken.insert(part.clone());
+        self.by_vope.insert(key, part.clone());
+        part
+    }
+
+    fn account(&mut self, vope: &str) -> String {
+        format!("Assets:Vopes:{}", self.part(vope))
+    }
+}
+
+/*
+ * Every transaction with its splits, then every transfer and opening
+ * balance in the ledger, oldest first
+ */
+fn entries(port: &portfolio::Portfolio) -> Vec<Entry> {
+    let mut list = vec![];
+    let mut parts = Parts::new(port);
+
+    for t in port.transactions() {
+        let mut postings = vec![];
+        for s in port.splits_of(&t.id) {
+            let other = if s.amount.is_negative() { "Expenses" } else { "Income" };
+            postings.push(Posting {
+                account: parts.account(&s.vope),
+                amount: s.amount,
+            });
+            postings.push(Posting {
+                account: format!("{}:{}", other, parts.part(&s.vope)),
+                amount: -s.amount,
+            });
+        }
+
+        list.push(Entry {
+            date: t.date,
+            cleared: t.status != transaction::Status::Uncleared,
+            desc: t.desc.clone(),
+            source: t.source.clone(),
+            notes: t.notes.clone(),
+            tags: t.tags.clone(),
+            postings,
+        });
+    }
+
+    // Transactions are exported under the vopes that hold them now, so
+    // transfers are too
+    for e in port.view_ledger().resolved(true) {
+        let (desc, postings) = match &e.kind {
+            ledger::EventKind::Transfer { from, to, amount } if from != to => (
+                format!("Move from {} to {}", from, to),
+                vec![
+                    Posting {
+                        account: parts.account(from),
+                        amount: -*amount,
+                    },
+                    Posting {
+                        account: parts.account(to),
+                        amount: *amount,
+                    },
+                ],
+            ),
+            ledger::EventKind::OpeningBalance { name, amount } => (
+                format!("Opening balance of {}", name),
+                vec![
+                    Posting {
+                        account: parts.account(name),
+                        amount: *amount,
+                    },
+                    Posting {
+                        account: "Equity:Opening-Balances".to_string(),
+                        amount: -*amount,
+                    },
+                ],
+            ),
+            _ => continue,
+        };
+
+        list.push(Entry {
+            date: e.date,
+            cleared: true,
+            desc,
+            source: None,
+            notes: String::new(),
+            tags: vec![],
+            postings,
+        });
+    }
+
+    // Stable, so entries on one day keep the order they happened in
+    list.sort_by_key(|e| e.date);
+    list
+}
+
+/*
+ * ledger and hledger syntax, with the funding account and the tags as tags
+ * and the notes as a comment
+ */
+fn write_ledger(list: &[Entry]) -> String {
+    let mut s = String::new();
+
+    for e in list.iter() {
+        // Writing to a String cannot fail
+        let flag = if e.cleared { " *" } else { "" };
+        let _ = writeln!(s, "{}{} {}", e.date.format("%Y-%m-%d"), flag, e.desc);
+        if let Some(source) = &e.source {
+            let _ = writeln!(s, "    ; source: {}", source);
+        }
+        if !e.tags.is_empty() {
+            let tags: String = e.tags.iter().map(|t| format!("{}:", tag_name(t))).collect();
+            let _ = writeln!(s, "    ; :{}", tags);
+        }
+        for line in e.notes.lines().filter(|l| !l.trim().is_empty()) {
+            let _ = writeln!(s, "    ; {}", line.trim());
+        }
+        for p in e.postings.iter() {
+            let _ = writeln!(s, "    {}  {}", p.account, p.amount);
+        }
+        s.push('\n');
+    }
+
+    s
+}
+
+/*
+ * beancount syntax. Every account has to be opened before it is used, so
+ * each is opened on the day of its first entry.
+ */
+fn write_beancount(list: &[Entry]) -> String {
+    let mut s = format!("option \"operating_currency\" \"{}\"\n\n", CURRENCY);
+
+    let mut opened: collections::BTreeMap<&str, NaiveDate> = collections::BTreeMap::new();
+    for e in list.iter() {
+        for p in e.postings.iter() {
+            opened.entry(p.account.as_str()).or_insert(e.date);
+        }
+    }
+    for (account, date) in opened.iter() {
+        let _ = writeln!(s, "{} open {} {}", date.format("%Y-%m-%d"), account, CURRENCY);
+    }
+    s.push('\n');
+
+    let quote = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+    for e in list.iter() {
+        let flag = if e.cleared { '*' } else { '!' };
+        let tags: String = e.tags.iter().map(|t| format!(" #{}", tag_name(t))).collect();
+        let _ = writeln!(s, "{} {} \"{}\"{}", e.date.format("%Y-%m-%d"), flag, quote(&e.desc), tags);
+        if let Some(source) = &e.source {
+            let _ = writeln!(s, "  source: \"{}\"", quote(source));
+        }
+        if !e.notes.trim().is_empty() {
+            let _ = writeln!(s, "  notes: \"{}\"", quote(e.notes.trim()));
+        }
+        for p in e.postings.iter() {
+            let _ = writeln!(s, "  {}  {} {}", p.account, p.amount.to_decimal_string(), CURRENCY);
+        }
+        s.push('\n');
+    }
+
+    s
+}
+
+/*
+ * beancount tags can only hold letters, digits and `-_/.`, which also keeps
+ * spaces and colons out of ledger's `:tag:` lists
+ */
+fn tag_name(tag: &str) -> String {
+    tag.trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "-_/.".contains(c) { c } else { '-' })
+        .collect()
+}
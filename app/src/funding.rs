@@ -0,0 +1,63 @@
+use crate::{dollar::Dollar, transaction};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// What sort of account the money is kept in
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Checking,
+    Savings,
+    /// Money owed, so its balance is usually negative
+    CreditCard,
+    Cash,
+    Other,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 5] = [Kind::Checking, Kind::Savings, Kind::CreditCard, Kind::Cash, Kind::Other];
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Kind::Checking => "Checking",
+            Kind::Savings => "Savings",
+            Kind::CreditCard => "Credit card",
+            Kind::Cash => "Cash",
+            Kind::Other => "Other",
+        })
+    }
+}
+
+/// A real account at a bank, where the money in the vopes actually sits.
+///
+/// Imported transactions are tagged with the account they came from, so its
+/// balance is the opening balance plus everything tagged with it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FundingAccount {
+    pub name: String,
+    pub kind: Kind,
+    pub institution: String,
+    /// The bank's number for the account, matched against an OFX ACCTID
+    pub number: Option<String>,
+    /// The balance before the first tagged transaction
+    pub opening_balance: Dollar,
+}
+
+impl FundingAccount {
+    /// True if statements for `number` belong to this account
+    pub fn has_number(&self, number: &str) -> bool {
+        self.number.as_deref().is_some_and(|n| n.trim() == number.trim())
+    }
+
+    /// The opening balance plus every transaction in `list` tagged with this
+    /// account. Each transaction must appear only once.
+    pub fn balance<'a>(&self, list: impl IntoIterator<Item = &'a transaction::Transaction>) -> Dollar {
+        self.opening_balance
+            + list
+                .into_iter()
+                .filter(|t| t.source.as_deref().is_some_and(|s| s.eq_ignore_ascii_case(&self.name)))
+                .map(|t| t.charge)
+                .sum::<Dollar>()
+    }
+}
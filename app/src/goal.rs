@@ -0,0 +1,126 @@
+// Savings goals. A vope with a goal works out how much it should get in each
+// budget period, which the Budget screen shows and a paycheck can be spread
+// by. Everything is worked out per period, from what the vope held when the
+// period started, so a second paycheck in the same period only tops up what
+// the first one left short.
+
+use crate::{budget, dollar::Dollar, error};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// What a vope is saving, or spending, towards
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Goal {
+    /// Hold `target` by `date`, putting the same amount in every period
+    /// until then
+    TargetByDate { target: Dollar, date: NaiveDate },
+    /// Put `amount` in every period, whatever is spent from it
+    Contribution { amount: Dollar },
+    /// Spend what the vope held evenly over the periods until `date`
+    SpendDown { date: NaiveDate },
+}
+
+impl Goal {
+    /// Checks the goal can be used
+    ///
+    /// Returns `Err(InvalidGoal)` if a target or contribution is not positive
+    pub fn validate(&self) -> Result<(), error::Error> {
+        let ok = match self {
+            Goal::TargetByDate { target, .. } => *target > Dollar::ZERO,
+            Goal::Contribution { amount } => *amount > Dollar::ZERO,
+            Goal::SpendDown { .. } => true,
+        };
+
+        if ok {
+            Ok(())
+        } else {
+            Err(error::Error::InvalidGoal)
+        }
+    }
+}
+
+/// How a goal stands in one budget period
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Progress {
+    /// What the vope should get in the period. When spending down, what can
+    /// be spent in it instead.
+    pub per_period: Dollar,
+    /// What the vope still needs this period to stay on track. Never
+    /// negative, and always zero when spending down.
+    pub required: Dollar,
+    /// From 0 to 1: how much of the target is held, how much of this
+    /// period's contribution was put in, or how much of this period's
+    /// spending was spent
+    pub fraction: f32,
+}
+
+/// Works out where a goal stands in the period containing `date`.
+///
+/// `opening` is what the vope held when the period started, `balance` what
+/// it holds now, and `spent` what was spent from it in the period.
+pub fn progress(
+    goal: &Goal,
+    period: &budget::Period,
+    date: NaiveDate,
+    opening: Dollar,
+    balance: Dollar,
+    spent: Dollar,
+) -> Progress {
+    let added = balance - opening + spent;
+
+    match *goal {
+        Goal::TargetByDate { target, date: by } => {
+            let needed = (target - opening).max(Dollar::ZERO);
+            let n = periods_left(period, date, by);
+            let per_period = Dollar::from_cents((needed.cents() + n - 1) / n);
+
+            Progress {
+                per_period,
+                required: (per_period - (balance - opening)).max(Dollar::ZERO),
+                fraction: fraction(balance, target),
+            }
+        }
+        Goal::Contribution { amount } => Progress {
+            per_period: amount,
+            required: (amount - added).max(Dollar::ZERO),
+            fraction: fraction(added, amount),
+        },
+        Goal::SpendDown { date: by } => {
+            let n = periods_left(period, date, by);
+            let per_period = Dollar::from_cents(opening.cents().max(0) / n);
+
+            Progress {
+                per_period,
+                required: Dollar::ZERO,
+                fraction: if per_period.is_zero() { 1.0 } else { fraction(spent, per_period) },
+            }
+        }
+    }
+}
+
+/*
+ * The periods from the one containing `date` to the one containing `by`,
+ * both counted. A date already gone leaves just this one.
+ */
+fn periods_left(period: &budget::Period, date: NaiveDate, by: NaiveDate) -> i64 {
+    let mut start = period.start_of(date);
+    let mut n = 1;
+
+    loop {
+        start = period.next(start);
+        if start > by {
+            return n;
+        }
+        n += 1;
+    }
+}
+
+/*
+ * `part / whole`, kept between 0 and 1
+ */
+fn fraction(part: Dollar, whole: Dollar) -> f32 {
+    if whole <= Dollar::ZERO {
+        return 1.0;
+    }
+    (part / whole).clamp(0.0, 1.0) as f32
+}
@@ -0,0 +1,165 @@
+use crate::{dollar, error, transaction};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path};
+
+/// Where a statement keeps the amount of each transaction
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum AmountColumns {
+    /// One column, signed
+    Single(usize),
+    /// Money spent and money received in separate, unsigned columns
+    Split { debit: usize, credit: usize },
+}
+
+/// Which way round a statement writes its amounts
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignConvention {
+    /// Negative when money is spent
+    Normal,
+    /// Positive when money is spent, as many credit cards do
+    Inverted,
+}
+
+/// Describes how to read one bank's CSV statements.
+///
+/// Columns are counted from 0. The default profile reads the format in the
+/// README: a header line, then date (MM/DD/YYYY), description and amount.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ImportProfile {
+    pub name: String,
+    pub delimiter: char,
+    /// Lines before the first transaction, such as headers
+    pub skip_rows: usize,
+    pub date_column: usize,
+    /// A chrono format string, such as `%m/%d/%Y` or `%Y-%m-%d`
+    pub date_format: String,
+    pub desc_column: usize,
+    pub amount: AmountColumns,
+    pub sign: SignConvention,
+    /// A column holding the bank's own transaction ID, if there is one
+    pub id_column: Option<usize>,
+}
+
+impl Default for ImportProfile {
+    fn default() -> Self {
+        Self {
+            name: "Default".to_string(),
+            delimiter: ',',
+            skip_rows: 1,
+            date_column: 0,
+            date_format: "%m/%d/%Y".to_string(),
+            desc_column: 1,
+            amount: AmountColumns::Single(2),
+            sign: SignConvention::Normal,
+            id_column: None,
+        }
+    }
+}
+
+impl ImportProfile {
+    /// The delimiter as the byte the CSV reader splits on
+    ///
+    /// Returns `Err(InvalidDelimiter)` unless it is a single ASCII character
+    pub fn delimiter_byte(&self) -> Result<u8, error::Error> {
+        if self.delimiter.is_ascii() {
+            Ok(self.delimiter as u8)
+        } else {
+            Err(error::Error::InvalidDelimiter(self.delimiter))
+        }
+    }
+
+    /// Reads every transaction in the CSV file at `path`
+    pub fn parse(&self, path: &path::Path) -> Result<Vec<transaction::Transaction>, error::Error> {
+        let file = fs::File::open(path).map_err(|e| error::Error::io(path, e))?;
+
+        self.parse_reader(file).map_err(|e| match e {
+            // Point io failures at the file rather than the reader
+            error::Error::Io { source, .. } => error::Error::io(path, source),
+            e => e,
+        })
+    }
+
+    /// Reads every transaction from CSV text
+    pub fn parse_reader<R: io::Read>(
+        &self,
+        reader: R,
+    ) -> Result<Vec<transaction::Transaction>, error::Error> {
+        let delimiter = self.delimiter_byte()?;
+        let mut trans = vec![];
+
+        let mut rdr = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .delimiter(delimiter)
+            .from_reader(reader);
+
+        // For line in csv...
+        for result in rdr.records().skip(self.skip_rows) {
+            // Get the record
+            let record = result.map_err(csv_error)?;
+            let line = record.position().map_or(0, |p| p.line() as usize);
+
+            // Skip blank lines, which many banks leave at the end
+            if record.iter().all(|f| f.trim().is_empty()) {
+                continue;
+            }
+
+            let field = |i: usize| {
+                record
+                    .get(i)
+                    .map(str::trim)
+                    .ok_or(error::Error::Parse { line, column: i + 1 })
+            };
+            let amount = |i: usize| -> Result<dollar::Dollar, error::Error> {
+                let raw = field(i)?;
+                if raw.is_empty() {
+                    Ok(dollar::Dollar::ZERO)
+                } else {
+                    raw.parse()
+                        .map_err(|_| error::Error::Parse { line, column: i + 1 })
+                }
+            };
+
+            let date = NaiveDate::parse_from_str(field(self.date_column)?, &self.date_format)
+                .map_err(|_| error::Error::Parse {
+                    line,
+                    column: self.date_column + 1,
+                })?;
+            let desc = field(self.desc_column)?.to_owned();
+
+            let charge = match self.amount {
+                AmountColumns::Single(col) => amount(col)?,
+                AmountColumns::Split { debit, credit } => amount(credit)?.abs() - amount(debit)?.abs(),
+            };
+            let charge = match self.sign {
+                SignConvention::Normal => charge,
+                SignConvention::Inverted => -charge,
+            };
+
+            let mut t = transaction::Transaction::new(date, desc, charge);
+            if let Some(col) = self.id_column {
+                let id = field(col)?;
+                if !id.is_empty() {
+                    t = t.with_bank_id(id);
+                }
+            }
+
+            trans.push(t);
+        }
+
+        transaction::number_occurrences(&mut trans);
+
+        Ok(trans)
+    }
+}
+
+/// Turns a csv failure into either an Io or a Parse error
+fn csv_error(e: csv::Error) -> error::Error {
+    let line = e.position().map_or(0, |p| p.line() as usize);
+
+    match e.into_kind() {
+        csv::ErrorKind::Io(io) => error::Error::io(path::Path::new(""), io),
+        _ => error::Error::Parse { line, column: 0 },
+    }
+}
@@ -0,0 +1,218 @@
+use crate::{dollar, error, paycheck, portfolio, transaction};
+use std::fmt;
+
+/// How many changes can be undone
+pub const MAX_UNDO: usize = 100;
+
+/// A change to the portfolio that can be undone.
+///
+/// A command may edit vopes and groups freely, but must only append to the
+/// transactions and splits, as undo cuts those back to their old length
+/// rather than copying them. Its ledger events are kept, and undo adds
+/// events that reverse them.
+#[derive(Clone, Debug)]
+pub enum Command {
+    AddVope {
+        name: String,
+        budget: dollar::Dollar,
+    },
+    RemoveVope {
+        name: String,
+    },
+    Transfer {
+        from: String,
+        to: String,
+        amount: dollar::Dollar,
+    },
+    Assign {
+        names: Vec<(String, f32)>,
+        transaction: transaction::Transaction,
+        even_weight: bool,
+    },
+    /// Assigns the exact amount each vope gets, see `Portfolio::assign_shares`
+    AssignShares {
+        shares: Vec<(String, dollar::Dollar)>,
+        transaction: transaction::Transaction,
+    },
+    CashPaycheck {
+        transaction: transaction::Transaction,
+        allocation: paycheck::Allocation,
+    },
+    AddGroup {
+        name: String,
+    },
+    RemoveGroup {
+        name: String,
+    },
+    MoveGroup {
+        name: String,
+        index: usize,
+    },
+    /// Puts a vope in a group, or none, see `Portfolio::move_vope`
+    MoveVope {
+        name: String,
+        group: Option<String>,
+        index: usize,
+    },
+    SortVopes,
+}
+
+impl Command {
+    /// Makes the change, through the same `Portfolio` method as always
+    pub fn apply(&self, port: &mut portfolio::Portfolio) -> Result<(), error::Error> {
+        match self {
+            Command::AddVope { name, budget } => port.add_vope(name, *budget),
+            Command::RemoveVope { name } => port.remove_vope(name),
+            Command::Transfer { from, to, amount } => port.transfer_holdings(from, to, *amount),
+            Command::Assign {
+                names,
+                transaction,
+                even_weight,
+            } => {
+                let names: Vec<(&str, f32)> = names.iter().map(|(n, w)| (n.as_str(), *w)).collect();
+                port.assign_transaction(&names, transaction, *even_weight)
+            }
+            Command::AssignShares { shares, transaction } => {
+                let shares: Vec<(&str, dollar::Dollar)> = shares.iter().map(|(n, a)| (n.as_str(), *a)).collect();
+                port.assign_shares(&shares, transaction)
+            }
+            Command::CashPaycheck {
+                transaction,
+                allocation,
+            } => port.cash_paycheck(transaction, allocation).map(|_| ()),
+            Command::AddGroup { name } => port.add_group(name),
+            Command::RemoveGroup { name } => port.remove_group(name),
+            Command::MoveGroup { name, index } => port.move_group(name, *index),
+            Command::MoveVope { name, group, index } => port.move_vope(name, group.as_deref(), *index),
+            Command::SortVopes => {
+                port.sort_vope();
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Describes the change for an Undo or Redo label
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::AddVope { name, .. } => write!(f, "add {}", name),
+            Command::RemoveVope { name } => write!(f, "remove {}", name),
+            Command::Transfer { from, to, amount } => write!(f, "move {} from {} to {}", amount, from, to),
+            Command::Assign { transaction, .. } | Command::AssignShares { transaction, .. } => {
+                write!(f, "assign {}", transaction.desc)
+            }
+            Command::CashPaycheck { transaction, .. } => write!(f, "cash {}", transaction.desc),
+            Command::AddGroup { name } => write!(f, "add group {}", name),
+            Command::RemoveGroup { name } => write!(f, "remove group {}", name),
+            Command::MoveGroup { name, .. } => write!(f, "move group {}", name),
+            Command::MoveVope { name, .. } => write!(f, "move {}", name),
+            Command::SortVopes => write!(f, "sort vopes"),
+        }
+    }
+}
+
+/*
+ * A change that was made, and the portfolio as it was before it
+ */
+#[derive(Clone, Debug)]
+struct Entry {
+    command: Command,
+    before: portfolio::Checkpoint,
+}
+
+/// The changes that can be undone and redone.
+///
+/// Undo puts back the portfolio from before the change, so it always lands
+/// exactly where it was. Redo makes the change again.
+///
+/// Each entry copies the vopes, groups and reconciliations, but not the
+/// transactions, splits or ledger, so a full journal costs about `MAX_UNDO`
+/// copies of the vope list however long the account's history is. Undo and
+/// redo both leave their mark in the ledger.
+#[derive(Clone, Debug, Default)]
+pub struct Journal {
+    undo: Vec<Entry>,
+    redo: Vec<Command>,
+}
+
+impl Journal {
+    /// Makes the change and remembers how to undo it. Anything that was
+    /// undone can no longer be redone.
+    pub fn execute(
+        &mut self,
+        port: &mut portfolio::Portfolio,
+        command: Command,
+    ) -> Result<(), error::Error> {
+        let mut before = port.checkpoint();
+        if let Err(e) = command.apply(port) {
+            port.restore(before);
+            return Err(e);
+        }
+        port.seal(&mut before);
+
+        self.undo.push(Entry { command, before });
+        if self.undo.len() > MAX_UNDO {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+
+        Ok(())
+    }
+
+    /// Takes back the last change, and returns it. Which groups are folded
+    /// is how the user is looking at the vopes, so it stays as it is.
+    pub fn undo(&mut self, port: &mut portfolio::Portfolio) -> Option<Command> {
+        let entry = self.undo.pop()?;
+        let folded = port.view_groups().to_vec();
+        port.restore(entry.before);
+        for g in folded {
+            // Groups the undo takes away stay gone
+            let _ = port.set_collapsed(&g.name, g.collapsed);
+        }
+        self.redo.push(entry.command.clone());
+        Some(entry.command)
+    }
+
+    /// Makes the last undone change again, and returns it
+    pub fn redo(&mut self, port: &mut portfolio::Portfolio) -> Result<Option<Command>, error::Error> {
+        let command = match self.redo.pop() {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+
+        let mut before = port.checkpoint();
+        match command.apply(port) {
+            Ok(_) => {
+                port.seal(&mut before);
+                self.undo.push(Entry {
+                    command: command.clone(),
+                    before,
+                });
+                Ok(Some(command))
+            }
+            Err(e) => {
+                // Leave everything as it was, to try again
+                port.restore(before);
+                self.redo.push(command);
+                Err(e)
+            }
+        }
+    }
+
+    /// The change `undo` would take back
+    pub fn next_undo(&self) -> Option<&Command> {
+        self.undo.last().map(|e| &e.command)
+    }
+
+    /// The change `redo` would make
+    pub fn next_redo(&self) -> Option<&Command> {
+        self.redo.last()
+    }
+
+    /// Forgets every change
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
@@ -0,0 +1,319 @@
+use crate::{dollar::Dollar, transaction};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// Something that happened to the portfolio
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum EventKind {
+    CreateVope {
+        name: String,
+        budget: Dollar,
+    },
+    DeleteVope {
+        name: String,
+    },
+    SetBudget {
+        name: String,
+        budget: Dollar,
+    },
+    /// A transaction was split across these vopes, by these amounts
+    Assign {
+        transaction: transaction::TransactionId,
+        shares: Vec<(String, Dollar)>,
+    },
+    /// An assignment was undone, and each vope gave back its share
+    Unassign {
+        transaction: transaction::TransactionId,
+        shares: Vec<(String, Dollar)>,
+    },
+    Transfer {
+        from: String,
+        to: String,
+        amount: Dollar,
+    },
+    /// Money a vope held that no other event explains, written when an
+    /// account from before the ledger was upgraded
+    OpeningBalance {
+        name: String,
+        amount: Dollar,
+    },
+    /// A vope was renamed. Earlier events keep the name it had then.
+    Rename {
+        from: String,
+        to: String,
+    },
+    /// One vope was folded into another, which took the `amount` it held
+    Merge {
+        from: String,
+        into: String,
+        amount: Dollar,
+    },
+    /// A scheduled occurrence was swapped for the bank's line. Earlier
+    /// events keep the occurrence's ID.
+    Replace {
+        from: transaction::TransactionId,
+        to: transaction::TransactionId,
+    },
+}
+
+impl EventKind {
+    /// How much each vope's balance changes because of this event
+    pub fn effect(&self) -> Vec<(&str, Dollar)> {
+        match self {
+            EventKind::Assign { shares, .. } => shares.iter().map(|(n, a)| (n.as_str(), *a)).collect(),
+            EventKind::Unassign { shares, .. } => shares.iter().map(|(n, a)| (n.as_str(), -*a)).collect(),
+            EventKind::Transfer { from, to, amount } => {
+                vec![(from.as_str(), -*amount), (to.as_str(), *amount)]
+            }
+            EventKind::OpeningBalance { name, amount } => vec![(name.as_str(), *amount)],
+            EventKind::Merge { from, into, amount } => {
+                vec![(from.as_str(), -*amount), (into.as_str(), *amount)]
+            }
+            EventKind::CreateVope { .. }
+            | EventKind::DeleteVope { .. }
+            | EventKind::SetBudget { .. }
+            | EventKind::Rename { .. }
+            | EventKind::Replace { .. } => vec![],
+        }
+    }
+
+    /*
+     * Every vope name in the event
+     */
+    fn names(&self) -> Vec<&String> {
+        match self {
+            EventKind::Assign { shares, .. } | EventKind::Unassign { shares, .. } => {
+                shares.iter().map(|(n, _)| n).collect()
+            }
+            EventKind::Transfer { from, to, .. } => vec![from, to],
+            EventKind::OpeningBalance { name, .. }
+            | EventKind::CreateVope { name, .. }
+            | EventKind::DeleteVope { name }
+            | EventKind::SetBudget { name, .. } => vec![name],
+            EventKind::Rename { from, to } => vec![from, to],
+            EventKind::Merge { from, into, .. } => vec![from, into],
+            EventKind::Replace { .. } => vec![],
+        }
+    }
+
+    /*
+     * `names`, to be changed
+     */
+    fn names_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            EventKind::Assign { shares, .. } | EventKind::Unassign { shares, .. } => {
+                shares.iter_mut().map(|(n, _)| n).collect()
+            }
+            EventKind::Transfer { from, to, .. } => vec![from, to],
+            EventKind::OpeningBalance { name, .. }
+            | EventKind::CreateVope { name, .. }
+            | EventKind::DeleteVope { name }
+            | EventKind::SetBudget { name, .. } => vec![name],
+            EventKind::Rename { from, to } => vec![from, to],
+            EventKind::Merge { from, into, .. } => vec![from, into],
+            EventKind::Replace { .. } => vec![],
+        }
+    }
+}
+
+/// One entry in the ledger
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Event {
+    /// The transaction's date for assignments, otherwise the day it happened
+    pub date: NaiveDate,
+    pub kind: EventKind,
+}
+
+/// A vope as the ledger sees it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Balance {
+    pub name: String,
+    pub budget: Dollar,
+    pub amount: Dollar,
+}
+
+/// Where a vope's stored figures disagree with the ledger
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    pub name: String,
+    /// `None` if the portfolio has no such vope
+    pub stored: Option<Dollar>,
+    /// `None` if the ledger has no such vope
+    pub derived: Option<Dollar>,
+}
+
+/*
+ * One vope as the ledger is played back, under the last name it had
+ */
+struct Slot {
+    name: String,
+    budget: Dollar,
+    amount: Dollar,
+    /// Whether the vope exists as of the date being played back to
+    present: bool,
+    merged_into: Option<usize>,
+}
+
+/// Every change to the portfolio, in the order it was made.
+///
+/// Events are only ever added. Balances are what the events add up to, so
+/// they can be worked out as of any date. A renamed vope is followed
+/// through its `Rename` events, so its balances are always listed under
+/// the name it has now.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Ledger {
+    events: Vec<Event>,
+}
+
+impl Ledger {
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    pub(crate) fn push(&mut self, date: NaiveDate, kind: EventKind) {
+        self.events.push(Event { date, kind });
+    }
+
+    /// Plays back every event dated on or before `date`, or every event if
+    /// there is no date. Vopes are in the order they were created, under the
+    /// names they have now.
+    pub fn balances(&self, date: Option<NaiveDate>) -> Vec<Balance> {
+        let slots = self.play(|e, ids, slots| {
+            if date.is_some_and(|d| e.date > d) {
+                return;
+            }
+
+            match &e.kind {
+                EventKind::CreateVope { budget, .. } | EventKind::SetBudget { budget, .. } => {
+                    slots[ids[0]].budget = *budget;
+                    slots[ids[0]].present = true;
+                }
+                EventKind::DeleteVope { .. } => slots[ids[0]].present = false,
+                EventKind::Rename { .. } => {}
+                other => {
+                    // A vope's names line up with what the event does to them
+                    for ((_, amount), &i) in other.effect().iter().zip(ids) {
+                        slots[i].amount += *amount;
+                        slots[i].present = true;
+                    }
+                    if let EventKind::Merge { .. } = other {
+                        slots[ids[0]].present = false;
+                    }
+                }
+            }
+        });
+
+        slots
+            .into_iter()
+            .filter(|s| s.present)
+            .map(|s| Balance {
+                name: s.name,
+                budget: s.budget,
+                amount: s.amount,
+            })
+            .collect()
+    }
+
+    /// Each budget the vope now called `name` has been given, by its
+    /// creation or a later change, with the day it was set. In the order
+    /// they were set.
+    pub fn budget_history(&self, name: &str) -> Vec<(NaiveDate, Dollar)> {
+        self.resolved(false)
+            .into_iter()
+            .filter_map(|e| match e.kind {
+                EventKind::CreateVope { name: n, budget } | EventKind::SetBudget { name: n, budget }
+                    if n.eq_ignore_ascii_case(name) =>
+                {
+                    Some((e.date, budget))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Every event with each vope under the name it has now. If
+    /// `through_merges` is set, a merged vope is named as the vope it went
+    /// into instead.
+    pub fn resolved(&self, through_merges: bool) -> Vec<Event> {
+        let mut ids_per_event: Vec<Vec<usize>> = vec![];
+        let slots = self.play(|_, ids, _| ids_per_event.push(ids.to_vec()));
+
+        let last_name = |mut i: usize| {
+            while let Some(j) = slots[i].merged_into.filter(|_| through_merges) {
+                i = j;
+            }
+            slots[i].name.clone()
+        };
+
+        self.events
+            .iter()
+            .zip(ids_per_event)
+            .map(|(e, ids)| {
+                let mut e = e.clone();
+                for (name, i) in e.kind.names_mut().into_iter().zip(ids) {
+                    *name = last_name(i);
+                }
+                e
+            })
+            .collect()
+    }
+
+    /*
+     * Follows each vope through every event, whatever its date. `each` is
+     * given every event with the slot of each of its `names`, after any
+     * rename or merge in it is followed.
+     */
+    fn play(&self, mut each: impl FnMut(&Event, &[usize], &mut Vec<Slot>)) -> Vec<Slot> {
+        let mut slots: Vec<Slot> = vec![];
+        // The slots of the vopes that still exist, by their current name
+        let mut live: Vec<usize> = vec![];
+
+        let find = |slots: &mut Vec<Slot>, live: &mut Vec<usize>, name: &str| -> usize {
+            match live.iter().find(|i| slots[**i].name.eq_ignore_ascii_case(name)) {
+                Some(i) => *i,
+                None => {
+                    // Money can arrive before the vope's own event when
+                    // older transactions are assigned later
+                    slots.push(Slot {
+                        name: name.to_string(),
+                        budget: Dollar::ZERO,
+                        amount: Dollar::ZERO,
+                        present: false,
+                        merged_into: None,
+                    });
+                    live.push(slots.len() - 1);
+                    slots.len() - 1
+                }
+            }
+        };
+
+        for e in self.events.iter() {
+            let ids: Vec<usize> = match &e.kind {
+                EventKind::Rename { from, to } => {
+                    let i = find(&mut slots, &mut live, from);
+                    slots[i].name = to.clone();
+                    vec![i, i]
+                }
+                kind => kind.names().into_iter().map(|n| find(&mut slots, &mut live, n)).collect(),
+            };
+
+            match &e.kind {
+                EventKind::DeleteVope { .. } => live.retain(|i| *i != ids[0]),
+                EventKind::Merge { .. } => {
+                    slots[ids[0]].merged_into = Some(ids[1]);
+                    live.retain(|i| *i != ids[0]);
+                }
+                _ => {}
+            }
+
+            each(e, &ids, &mut slots);
+        }
+
+        slots
+    }
+}
+
+/// Today, for events that have no date of their own
+pub(crate) fn today() -> NaiveDate {
+    chrono::Local::now().date_naive()
+}
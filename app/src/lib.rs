@@ -1,21 +1,37 @@
 // Library Imports
 use directories::ProjectDirs;
-use log;
-use serde;
-use serde_json;
-use std::{
-    error, fs,
-    path::{self, PathBuf},
-};
+use std::{fs, path};
 
 // Define and re-export crate modules
+pub mod budget;
 pub mod dollar;
+pub mod error;
+pub mod export;
+pub mod funding;
+pub mod goal;
+pub mod import;
+pub mod journal;
+pub mod ledger;
+pub mod migrate;
 pub mod misc;
+pub mod ofx;
+pub mod paycheck;
 pub mod portfolio;
+pub mod qif;
+pub mod reconcile;
+pub mod recurring;
+pub mod report;
+pub mod rules;
+pub mod split;
+pub mod suggest;
+// The baseline smoke test matches on the result rather than calling is_ok
+#[allow(clippy::redundant_pattern_matching)]
 pub mod tests;
 pub mod transaction;
 pub mod vope;
 
+pub use error::Error;
+
 /**
  * An account contains all information about the user.
  *
@@ -25,30 +41,56 @@ pub mod vope;
  */
 #[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct Account {
+    version: u32,
     port: portfolio::Portfolio,
     name: String,
     date: String,
     path: path::PathBuf,
+    profiles: Vec<import::ImportProfile>,
+    rules: Vec<rules::Rule>,
+    recurring: Vec<recurring::Recurring>,
+    funding: Vec<funding::FundingAccount>,
+    #[serde(skip)]
+    journal: journal::Journal,
 }
 
 impl Account {
     /**
      * The new function is the "constructor" for an Account.
+     *
+     * The account is created at the default location, see `default_path`
      */
-    pub fn new() -> Result<Account, Box<dyn error::Error>> {
-        // Default account location is
-        // %USERPROFILE%\AppData\Roaming\ButzIndustries\MoneyMan\data\acc.json
-        let binding = ProjectDirs::from("io", "ButzIndustries", "MoneyMan").unwrap();
-        let path = path::Path::new(binding.data_dir()).join("acc.json");
-        fs::create_dir_all(path.parent().unwrap())?;
+    pub fn new() -> Result<Account, Error> {
+        Account::new_at(Account::default_path())
+    }
+
+    /**
+     * Creates a new, empty account and saves it at the given path.
+     *
+     * Returns `Err(AccountExists)` rather than overwrite a file already
+     * there, see `replace_at`.
+     */
+    pub fn new_at(acc_path: path::PathBuf) -> Result<Account, Error> {
+        if acc_path.exists() {
+            return Err(Error::AccountExists(acc_path));
+        }
+        if let Some(dir) = acc_path.parent() {
+            fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+        }
 
-        log::info!("Creating new account at {:?}", path);
+        log::info!("Creating new account at {:?}", acc_path);
 
         let mut acc = Account {
+            version: migrate::CURRENT_VERSION,
             name: "Unknown".to_string(),
             date: "Today".to_string(),
-            path: path.to_path_buf(),
+            path: acc_path,
             port: portfolio::Portfolio::new(),
+            profiles: vec![import::ImportProfile::default()],
+            rules: vec![],
+            recurring: vec![],
+            funding: vec![],
+            journal: journal::Journal::default(),
         };
 
         acc.save()?;
@@ -56,45 +98,115 @@ impl Account {
         Ok(acc)
     }
 
-    pub fn open(acc_path: path::PathBuf) -> Result<Account, Box<dyn error::Error>> {
-        // let file_read = fs::read_to_string(acc_path);
-        let p = path::PathBuf::from("/home/butz/OneDrive/Documents/Finances/acc.json");
-        let file_read = fs::read_to_string(p);
-
-        match file_read {
-            Ok(raw_json) => {
-                let res_acc: Result<Account, serde_json::Error> = serde_json::from_str(&raw_json);
-
-                match res_acc {
-                    Ok(mut acc) => {
-                        acc.save()?;
-                        Ok(acc)
-                    }
-                    Err(e) => {
-                        log::error!("Failed to deserialzie Account: {}", &e);
-                        Err(Box::new(e))
-                    }
+    /// Creates a new, empty account at the given path like `new_at`. A file
+    /// already there is kept at `replaced_path`, replacing any older copy.
+    pub fn replace_at(acc_path: path::PathBuf) -> Result<Account, Error> {
+        if acc_path.exists() {
+            let backup = Account::replaced_path(&acc_path);
+            fs::rename(&acc_path, &backup).map_err(|e| Error::io(&backup, e))?;
+            log::info!("Moved the old account to {:?}", backup);
+        }
+
+        Account::new_at(acc_path)
+    }
+
+    /// Where `replace_at` keeps the account it replaced: the same name with
+    /// `.bak` added
+    pub fn replaced_path(acc_path: &path::Path) -> path::PathBuf {
+        let mut name = acc_path.file_name().unwrap_or_default().to_os_string();
+        name.push(".bak");
+        acc_path.with_file_name(name)
+    }
+
+    /// Default account location is
+    /// %USERPROFILE%\AppData\Roaming\ButzIndustries\MoneyMan\data\acc.json
+    pub fn default_path() -> path::PathBuf {
+        let binding = ProjectDirs::from("io", "ButzIndustries", "MoneyMan").unwrap();
+        path::Path::new(binding.data_dir()).join("acc.json")
+    }
+
+    /// Reads the account stored at `acc_path`. Later saves go back to the same file.
+    ///
+    /// Files written by older versions are upgraded first. The original file is
+    /// kept next to it as `<name>.v<version>.bak` before anything is rewritten.
+    pub fn open(acc_path: path::PathBuf) -> Result<Account, Error> {
+        let raw_json = fs::read_to_string(&acc_path).map_err(|e| {
+            log::error!("Failed to read account file: {}", &e);
+            Error::io(&acc_path, e)
+        })?;
+
+        let mut js: serde_json::Value = serde_json::from_str(&raw_json)?;
+        let version = migrate::file_version(&js);
+
+        let res_acc = if version == migrate::CURRENT_VERSION {
+            // Parse the text directly so errors keep their line and column
+            serde_json::from_str::<Account>(&raw_json)
+        } else {
+            let backup = Account::backup_path(&acc_path, version);
+            misc::write_atomic(&backup, raw_json.as_bytes()).map_err(|e| Error::io(&backup, e))?;
+            log::info!("Backed up version {} account to {:?}", version, backup);
+
+            migrate::migrate(&mut js)?;
+            serde_json::from_value::<Account>(js)
+        };
+
+        match res_acc {
+            Ok(mut acc) => {
+                // The file may have been moved since it was last saved
+                acc.path = acc_path;
+
+                for m in acc.port.verify() {
+                    log::warn!("{} does not match the ledger: {:?}", m.name, m);
                 }
+
+                let posted = acc.post_due(chrono::Local::now().date_naive());
+                if !posted.is_empty() {
+                    log::info!("Posted {} scheduled transactions", posted.len());
+                }
+
+                acc.save()?;
+                Ok(acc)
             }
             Err(e) => {
-                log::error!("Failed to parse account file: {}", &e);
-                Err(Box::new(e))
+                log::error!("Failed to deserialzie Account: {}", &e);
+                Err(Error::from(e))
             }
         }
     }
 
-    pub fn save(&mut self) -> Result<(), Box<dyn error::Error>> {
+    /// Where the pre-migration copy of a version `version` file is kept
+    pub fn backup_path(acc_path: &path::Path, version: u32) -> path::PathBuf {
+        let mut name = acc_path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".v{}.bak", version));
+        acc_path.with_file_name(name)
+    }
+
+    /// Saves the account back to the file it was opened from
+    pub fn save(&mut self) -> Result<(), Error> {
         self.save_as(&self.path.clone())
     }
 
-    pub fn save_as(&mut self, acc_path: &path::Path) -> Result<(), Box<dyn error::Error>> {
+    /// Saves the account to `acc_path`, which becomes the file later saves go to.
+    ///
+    /// The file is replaced atomically, so a crash part way through leaves the
+    /// previous save intact.
+    pub fn save_as(&mut self, acc_path: &path::Path) -> Result<(), Error> {
         self.port.calc_holdings();
-        let js = serde_json::to_string(&self)?;
-        let p = path::PathBuf::from("/home/butz/OneDrive/Documents/Finances/acc.json");
 
-        let _wr = fs::write(p, js);
+        // Only move to the new path once it has actually been written
+        let old_path = std::mem::replace(&mut self.path, acc_path.to_path_buf());
+        let res = serde_json::to_string(&self)
+            .map_err(Error::from)
+            .and_then(|js| {
+                misc::write_atomic(acc_path, js.as_bytes()).map_err(|e| Error::io(acc_path, e))
+            });
 
-        Ok(())
+        if let Err(e) = &res {
+            log::error!("Failed to save account to {:?}: {}", acc_path, e);
+            self.path = old_path;
+        }
+
+        res
     }
 
     // Getters
@@ -106,11 +218,301 @@ impl Account {
         &self.date
     }
 
+    pub fn get_path(&self) -> &path::Path {
+        &self.path
+    }
+
     pub fn get_portfolio(&self) -> &portfolio::Portfolio {
         &self.port
     }
 
+    /// Changes made through here cannot be undone, and clear the undo
+    /// history, since undoing past them would lose them. Use `execute` for
+    /// changes the user should be able to take back.
     pub fn get_portfolio_mut(&mut self) -> &mut portfolio::Portfolio {
+        self.journal.clear();
         &mut self.port
     }
+
+    /// Folds a group down to its subtotal, or opens it again. This only
+    /// changes how the vopes are shown, so the undo history is kept.
+    ///
+    /// Returns `Err(UnknownGroup)` if there is no group with that name
+    pub fn set_collapsed(&mut self, group: &str, collapsed: bool) -> Result<(), Error> {
+        self.port.set_collapsed(group, collapsed)
+    }
+
+    /// Makes a change to the portfolio that can be undone
+    pub fn execute(&mut self, command: journal::Command) -> Result<(), Error> {
+        self.journal.execute(&mut self.port, command)
+    }
+
+    /// Takes back the last change made with `execute`, and returns it
+    pub fn undo(&mut self) -> Option<journal::Command> {
+        self.journal.undo(&mut self.port)
+    }
+
+    /// Makes the last undone change again, and returns it
+    pub fn redo(&mut self) -> Result<Option<journal::Command>, Error> {
+        self.journal.redo(&mut self.port)
+    }
+
+    pub fn get_journal(&self) -> &journal::Journal {
+        &self.journal
+    }
+
+    pub fn get_profiles(&self) -> &[import::ImportProfile] {
+        &self.profiles
+    }
+
+    /// Finds an import profile by name, ignoring case
+    pub fn get_profile(&self, name: &str) -> Result<&import::ImportProfile, Error> {
+        self.profiles
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| Error::UnknownProfile(name.to_string()))
+    }
+
+    /// Adds a new import profile, or replaces the one with the same name if
+    /// `replace` is set
+    ///
+    /// Returns `Err(DuplicateProfile)` if the name is taken and `replace` is not set,
+    /// or `Err(InvalidDelimiter)` if the delimiter is not ASCII
+    pub fn add_profile(&mut self, profile: import::ImportProfile, replace: bool) -> Result<(), Error> {
+        profile.delimiter_byte()?;
+
+        match self
+            .profiles
+            .iter()
+            .position(|p| p.name.eq_ignore_ascii_case(&profile.name))
+        {
+            Some(i) if replace => self.profiles[i] = profile,
+            Some(_) => return Err(Error::DuplicateProfile(profile.name)),
+            None => self.profiles.push(profile),
+        }
+
+        Ok(())
+    }
+
+    /// The auto-assign rules, in the order they are tried
+    pub fn get_rules(&self) -> &[rules::Rule] {
+        &self.rules
+    }
+
+    /// Adds a rule to the end of the list, or replaces the one with the same
+    /// name in place if `replace` is set
+    ///
+    /// Returns `Err(DuplicateRule)` if the name is taken and `replace` is not set,
+    /// and `Err(InvalidRule)` if its pattern is not a valid regex
+    pub fn add_rule(&mut self, rule: rules::Rule, replace: bool) -> Result<(), Error> {
+        rule.validate()?;
+
+        match self.rules.iter().position(|r| r.name.eq_ignore_ascii_case(&rule.name)) {
+            Some(i) if replace => self.rules[i] = rule,
+            Some(_) => return Err(Error::DuplicateRule(rule.name)),
+            None => self.rules.push(rule),
+        }
+
+        Ok(())
+    }
+
+    /// Removes the named rule
+    pub fn remove_rule(&mut self, name: &str) -> Result<(), Error> {
+        let len = self.rules.len();
+        self.rules.retain(|r| !r.name.eq_ignore_ascii_case(name));
+
+        if self.rules.len() == len {
+            Err(Error::UnknownRule(name.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Moves the named rule to `index`, earlier rules are tried first
+    pub fn move_rule(&mut self, name: &str, index: usize) -> Result<(), Error> {
+        let pos = self
+            .rules
+            .iter()
+            .position(|r| r.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| Error::UnknownRule(name.to_string()))?;
+
+        let rule = self.rules.remove(pos);
+        self.rules.insert(index.min(self.rules.len()), rule);
+
+        Ok(())
+    }
+
+    /// Files every transaction in `list` that a rule matches, see
+    /// `rules::auto_assign`. Each one can be undone.
+    pub fn auto_assign(
+        &mut self,
+        list: &mut Vec<transaction::Transaction>,
+    ) -> Result<Vec<rules::AutoAssigned>, Error> {
+        rules::auto_assign(&mut self.port, &mut self.journal, &self.rules, list)
+    }
+
+    pub fn get_recurring(&self) -> &[recurring::Recurring] {
+        &self.recurring
+    }
+
+    /// Adds a recurring transaction, or replaces the one with the same name
+    /// if `replace` is set. Nothing is posted until `post_due` is called.
+    ///
+    /// Returns `Err(DuplicateSchedule)` if the name is taken and `replace` is not set
+    pub fn add_recurring(&mut self, r: recurring::Recurring, replace: bool) -> Result<(), Error> {
+        match self.recurring.iter().position(|x| x.name.eq_ignore_ascii_case(&r.name)) {
+            Some(i) if replace => self.recurring[i] = r,
+            Some(_) => return Err(Error::DuplicateSchedule(r.name)),
+            None => self.recurring.push(r),
+        }
+
+        Ok(())
+    }
+
+    /// Removes the named recurring transaction. Occurrences already posted stay.
+    pub fn remove_recurring(&mut self, name: &str) -> Result<(), Error> {
+        let len = self.recurring.len();
+        self.recurring.retain(|r| !r.name.eq_ignore_ascii_case(name));
+
+        if self.recurring.len() == len {
+            Err(Error::UnknownSchedule(name.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Assigns every recurring transaction that has come due by `today`, see
+    /// `recurring::post_due`. This is done each time the account is opened.
+    pub fn post_due(&mut self, today: chrono::NaiveDate) -> Vec<transaction::Transaction> {
+        let posted = recurring::post_due(&mut self.port, &mut self.recurring, today);
+        if !posted.is_empty() {
+            self.journal.clear();
+        }
+        posted
+    }
+
+    /// Replaces posted occurrences with the matching lines of `list`, see
+    /// `recurring::match_imported`
+    pub fn match_scheduled(
+        &mut self,
+        list: &mut Vec<transaction::Transaction>,
+    ) -> Vec<(transaction::TransactionId, transaction::Transaction)> {
+        recurring::match_imported(self.get_portfolio_mut(), list)
+    }
+
+    /// Renames a vope, see `Portfolio::rename_vope`. Rules and recurring
+    /// transactions that file into it follow the new name. The undo history
+    /// is only forgotten if the rename goes through.
+    pub fn rename_vope(&mut self, name: &str, new_name: &str) -> Result<(), Error> {
+        self.port.rename_vope(name, new_name)?;
+        self.journal.clear();
+        self.retarget(name, new_name.trim());
+        Ok(())
+    }
+
+    /// Folds one vope into another, see `Portfolio::merge_vope`. Rules and
+    /// recurring transactions that filed into `from` file into `into`. The
+    /// undo history is only forgotten if the merge goes through.
+    pub fn merge_vope(&mut self, from: &str, into: &str) -> Result<(), Error> {
+        self.port.merge_vope(from, into)?;
+        self.journal.clear();
+        self.retarget(from, into);
+        Ok(())
+    }
+
+    /// The real accounts the money is kept in
+    pub fn get_funding(&self) -> &[funding::FundingAccount] {
+        &self.funding
+    }
+
+    /// Finds the funding account statements for the bank's account `number` belong to
+    pub fn funding_for_number(&self, number: &str) -> Option<&funding::FundingAccount> {
+        self.funding.iter().find(|f| f.has_number(number))
+    }
+
+    /// Adds a funding account, or replaces the one with the same name if
+    /// `replace` is set
+    ///
+    /// Returns `Err(DuplicateFunding)` if the name is taken and `replace` is not set
+    pub fn add_funding(&mut self, f: funding::FundingAccount, replace: bool) -> Result<(), Error> {
+        match self.funding.iter().position(|x| x.name.eq_ignore_ascii_case(&f.name)) {
+            Some(i) if replace => self.funding[i] = f,
+            Some(_) => return Err(Error::DuplicateFunding(f.name)),
+            None => self.funding.push(f),
+        }
+
+        Ok(())
+    }
+
+    /// Removes the named funding account. Transactions keep their tag, and
+    /// count again if an account with that name is added back.
+    pub fn remove_funding(&mut self, name: &str) -> Result<(), Error> {
+        let len = self.funding.len();
+        self.funding.retain(|f| !f.name.eq_ignore_ascii_case(name));
+
+        if self.funding.len() == len {
+            Err(Error::UnknownFunding(name.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Each funding account with its balance, see `funding::FundingAccount::balance`
+    pub fn funding_balances(&self) -> Vec<(&funding::FundingAccount, dollar::Dollar)> {
+        let list = self.port.transactions();
+        self.funding.iter().map(|f| (f, f.balance(list))).collect()
+    }
+
+    /// What the named funding account held before any transaction, or all of
+    /// them together if there is no name
+    pub fn opening_balance(&self, account: Option<&str>) -> Result<dollar::Dollar, Error> {
+        match account {
+            Some(name) => self
+                .funding
+                .iter()
+                .find(|f| f.name.eq_ignore_ascii_case(name))
+                .map(|f| f.opening_balance)
+                .ok_or_else(|| Error::UnknownFunding(name.to_string())),
+            None => Ok(self.funding.iter().map(|f| f.opening_balance).sum()),
+        }
+    }
+
+    /// Compares the cleared transactions with a statement, see `reconcile::summarize`
+    pub fn reconcile_summary(&self, rec: &reconcile::Reconciliation) -> Result<reconcile::Summary, Error> {
+        let opening = self.opening_balance(rec.account.as_deref())?;
+        Ok(self.port.reconcile_summary(rec, opening))
+    }
+
+    /// Locks the cleared transactions on a statement that balances, see
+    /// `portfolio::Portfolio::finish_reconciliation`
+    pub fn finish_reconciliation(&mut self, rec: reconcile::Reconciliation) -> Result<usize, Error> {
+        let opening = self.opening_balance(rec.account.as_deref())?;
+        self.get_portfolio_mut().finish_reconciliation(rec, opening)
+    }
+
+    /// Removes the named import profile
+    pub fn remove_profile(&mut self, name: &str) -> Result<(), Error> {
+        let len = self.profiles.len();
+        self.profiles.retain(|p| !p.name.eq_ignore_ascii_case(name));
+
+        if self.profiles.len() == len {
+            Err(Error::UnknownProfile(name.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /*
+     * Points every rule and recurring transaction filing into `from` at `to`
+     */
+    fn retarget(&mut self, from: &str, to: &str) {
+        let targets = self
+            .rules
+            .iter_mut()
+            .map(|r| &mut r.target)
+            .chain(self.recurring.iter_mut().map(|r| &mut r.target));
+        for t in targets {
+            t.rename(from, to);
+        }
+    }
 }
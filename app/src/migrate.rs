@@ -0,0 +1,549 @@
+// Upgrades account files written by older versions of Money Man.
+//
+// Files from before the `version` field existed count as version 0. Each
+// step rewrites the raw JSON of one version into the next, so a file of any
+// age is walked forward until it matches CURRENT_VERSION. To change the file
+// format, bump CURRENT_VERSION and append a step to STEPS.
+//
+// A step writes the JSON its version had as literal values, never by
+// serializing the current types, so it keeps writing the same thing after
+// those types change.
+
+use crate::{dollar::Dollar, error, ledger, transaction};
+use std::collections::HashMap;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+/// The version written by this build
+pub const CURRENT_VERSION: u32 = 15;
+
+/// Upgrades a file from version `n` to `n + 1`, where `n` is its index in `STEPS`
+type Step = fn(&mut Map<String, Value>) -> Result<(), error::Error>;
+
+const STEPS: [Step; CURRENT_VERSION as usize] = [v0_to_v1, v1_to_v2, v2_to_v3, v3_to_v4, v4_to_v5, v5_to_v6, v6_to_v7, v7_to_v8, v8_to_v9, v9_to_v10, v10_to_v11, v11_to_v12, v12_to_v13, v13_to_v14, v14_to_v15];
+
+/// Reads the version of a raw account file, files without one are version 0
+pub fn file_version(js: &Value) -> u32 {
+    js.get("version")
+        .and_then(Value::as_u64)
+        .map_or(0, |v| v as u32)
+}
+
+/// Brings a raw account file up to `CURRENT_VERSION`.
+///
+/// Returns the version the file started at.
+///
+/// Returns `Err(UnsupportedVersion)` if the file was written by a newer build,
+/// and `Err(Parse)` if it is not a JSON object.
+pub fn migrate(js: &mut Value) -> Result<u32, error::Error> {
+    let from = file_version(js);
+
+    if from > CURRENT_VERSION {
+        return Err(error::Error::UnsupportedVersion(from));
+    }
+
+    let obj = js
+        .as_object_mut()
+        .ok_or(error::Error::Parse { line: 1, column: 1 })?;
+
+    for (version, step) in STEPS.iter().enumerate().skip(from as usize) {
+        log::info!("Migrating account from version {} to {}", version, version + 1);
+        step(obj)?;
+        obj.insert("version".to_string(), Value::from(version as u32 + 1));
+    }
+
+    Ok(from)
+}
+
+/*
+ * Version 0 stored every Dollar as a float `{"amount": 12.34}`, which is now
+ * stored exactly as `{"cents": 1234}`.
+ */
+fn v0_to_v1(obj: &mut Map<String, Value>) -> Result<(), error::Error> {
+    fn convert(js: &mut Value) {
+        match js {
+            Value::Object(map) => {
+                let legacy = map.len() == 1 && map.get("amount").is_some_and(Value::is_number);
+
+                if legacy {
+                    let amount = map["amount"].as_f64().unwrap_or_default();
+                    let cents = (amount * 100.0).round() as i64;
+                    map.clear();
+                    map.insert("cents".to_string(), Value::from(cents));
+                } else {
+                    map.values_mut().for_each(convert);
+                }
+            }
+            Value::Array(list) => list.iter_mut().for_each(convert),
+            _ => {}
+        }
+    }
+
+    obj.values_mut().for_each(convert);
+    Ok(())
+}
+
+/*
+ * Version 1 saved each transaction as a `date | desc | $charge` string, with
+ * no ID. Each becomes an object with a content ID, numbering identical lines
+ * within a vope so repeated purchases stay separate. A transaction split
+ * across vopes gets the same ID in each of them.
+ */
+fn v1_to_v2(obj: &mut Map<String, Value>) -> Result<(), error::Error> {
+    let port = obj
+        .get_mut("port")
+        .and_then(Value::as_object_mut)
+        .ok_or(error::Error::Parse { line: 0, column: 0 })?;
+
+    // Every vope with a history, the ignored vope included
+    let mut vopes = vec![];
+    for (key, val) in port.iter_mut() {
+        match key.as_str() {
+            "envelopes" => vopes.extend(val.as_array_mut().into_iter().flatten()),
+            "ignored" => vopes.push(val),
+            _ => {}
+        }
+    }
+
+    for v in vopes {
+        let list = match v.get_mut("transactions").and_then(Value::as_array_mut) {
+            Some(list) => list,
+            None => continue,
+        };
+
+        let mut parsed = list
+            .iter()
+            .map(|t| {
+                t.as_str()
+                    .and_then(transaction::Transaction::from_legacy)
+                    .ok_or(error::Error::Parse { line: 0, column: 0 })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        transaction::number_occurrences(&mut parsed);
+        *list = parsed
+            .iter()
+            .map(|t| {
+                json!({
+                    "id": t.id.as_str(),
+                    "date": t.date,
+                    "desc": t.desc,
+                    "charge": cents(t.charge),
+                })
+            })
+            .collect();
+    }
+
+    Ok(())
+}
+
+/*
+ * Version 3 keeps a list of CSV import profiles with the account, starting
+ * with the default format every older file was imported with.
+ */
+fn v2_to_v3(obj: &mut Map<String, Value>) -> Result<(), error::Error> {
+    let default = json!({
+        "name": "Default",
+        "delimiter": ",",
+        "skip_rows": 1,
+        "date_column": 0,
+        "date_format": "%m/%d/%Y",
+        "desc_column": 1,
+        "amount": {"Single": 2},
+        "sign": "Normal",
+        "id_column": null,
+    });
+    obj.insert("profiles".to_string(), Value::Array(vec![default]));
+    Ok(())
+}
+
+/*
+ * Version 4 keeps the auto-assign rules with the account
+ */
+fn v3_to_v4(obj: &mut Map<String, Value>) -> Result<(), error::Error> {
+    obj.insert("rules".to_string(), Value::Array(vec![]));
+    Ok(())
+}
+
+/*
+ * Version 5 names the vope that gets what is left of each paycheck. Older
+ * files have none until the user picks one.
+ */
+fn v4_to_v5(obj: &mut Map<String, Value>) -> Result<(), error::Error> {
+    let port = obj
+        .get_mut("port")
+        .and_then(Value::as_object_mut)
+        .ok_or(error::Error::Parse { line: 0, column: 0 })?;
+
+    port.insert("default_vope".to_string(), Value::Null);
+    Ok(())
+}
+
+/*
+ * Version 6 splits the budget into periods. Older files get monthly periods,
+ * and every vope carries its balance forward as the running total always did.
+ */
+fn v5_to_v6(obj: &mut Map<String, Value>) -> Result<(), error::Error> {
+    let port = obj
+        .get_mut("port")
+        .and_then(Value::as_object_mut)
+        .ok_or(error::Error::Parse { line: 0, column: 0 })?;
+
+    port.insert("period".to_string(), json!({"Monthly": {"start_day": 1}}));
+
+    let rollover = json!("Carry");
+    for (key, val) in port.iter_mut() {
+        let vopes: Vec<&mut Value> = match key.as_str() {
+            "envelopes" => val.as_array_mut().into_iter().flatten().collect(),
+            "ignored" => vec![val],
+            _ => continue,
+        };
+
+        for v in vopes.into_iter().filter_map(Value::as_object_mut) {
+            v.insert("rollover".to_string(), rollover.clone());
+        }
+    }
+
+    Ok(())
+}
+
+/*
+ * Version 7 keeps recurring transaction templates with the account
+ */
+fn v6_to_v7(obj: &mut Map<String, Value>) -> Result<(), error::Error> {
+    obj.insert("recurring".to_string(), Value::Array(vec![]));
+    Ok(())
+}
+
+/*
+ * Version 8 keeps a ledger that balances are derived from. Older files get
+ * one written from what they have: each vope is created on the date of the
+ * first transaction, and each transaction that went to a single vope is
+ * assigned on its own date. What that does not explain, such as transfers
+ * and shares of split transactions, becomes an opening balance today.
+ */
+fn v7_to_v8(obj: &mut Map<String, Value>) -> Result<(), error::Error> {
+    let port = obj
+        .get_mut("port")
+        .and_then(Value::as_object_mut)
+        .ok_or(error::Error::Parse { line: 0, column: 0 })?;
+
+    let read = |v: Option<&Value>| -> Result<OldVope, error::Error> {
+        let v = v.ok_or(error::Error::Parse { line: 0, column: 0 })?;
+        Ok(serde_json::from_value(v.clone())?)
+    };
+
+    let envelopes: Vec<OldVope> = port
+        .get("envelopes")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|v| read(Some(v)))
+        .collect::<Result<_, _>>()?;
+    let ignored = read(port.get("ignored"))?;
+    let all: Vec<&OldVope> = envelopes.iter().chain(std::iter::once(&ignored)).collect();
+
+    let today = ledger::today();
+    let first = all
+        .iter()
+        .flat_map(|v| v.transactions.iter().map(|t| t.date))
+        .min()
+        .unwrap_or(today);
+
+    // A transaction in more than one vope was split, and its shares are unknown
+    let mut seen: HashMap<&transaction::TransactionId, usize> = HashMap::new();
+    for t in all.iter().flat_map(|v| v.transactions.iter()) {
+        *seen.entry(&t.id).or_insert(0) += 1;
+    }
+
+    let mut log = vec![];
+    for v in envelopes.iter() {
+        log.push(json!({
+            "date": first,
+            "kind": {"CreateVope": {"name": v.name, "budget": cents(v.budget)}},
+        }));
+    }
+
+    let mut assigned: Vec<(&OldVope, &OldTransaction)> = all
+        .iter()
+        .flat_map(|v| v.transactions.iter().map(move |t| (*v, t)))
+        .filter(|(_, t)| seen[&t.id] == 1)
+        .collect();
+    assigned.sort_by_key(|(_, t)| t.date);
+
+    for (v, t) in assigned.iter() {
+        log.push(json!({
+            "date": t.date,
+            "kind": {"Assign": {"transaction": t.id.as_str(), "shares": [[v.name, cents(t.charge)]]}},
+        }));
+    }
+
+    for v in all.iter() {
+        let explained: Dollar = assigned
+            .iter()
+            .filter(|(a, _)| a.name == v.name)
+            .map(|(_, t)| t.charge)
+            .sum();
+
+        let rest = v.actual_amount - explained;
+        if !rest.is_zero() {
+            log.push(json!({
+                "date": today,
+                "kind": {"OpeningBalance": {"name": v.name, "amount": cents(rest)}},
+            }));
+        }
+    }
+
+    port.insert("ledger".to_string(), json!({"events": log}));
+    Ok(())
+}
+
+/*
+ * Version 9 added funding accounts, and tags each transaction with the one
+ * it came from. Older transactions came from no account in particular.
+ */
+fn v8_to_v9(obj: &mut Map<String, Value>) -> Result<(), error::Error> {
+    obj.insert("funding".to_string(), Value::Array(vec![]));
+
+    let port = obj
+        .get_mut("port")
+        .and_then(Value::as_object_mut)
+        .ok_or(error::Error::Parse { line: 0, column: 0 })?;
+
+    let tag = |v: &mut Value| {
+        let list = v.get_mut("transactions").and_then(Value::as_array_mut);
+        for t in list.into_iter().flatten().filter_map(Value::as_object_mut) {
+            t.insert("source".to_string(), Value::Null);
+        }
+    };
+
+    if let Some(list) = port.get_mut("envelopes").and_then(Value::as_array_mut) {
+        list.iter_mut().for_each(tag);
+    }
+    if let Some(v) = port.get_mut("ignored") {
+        tag(v);
+    }
+
+    Ok(())
+}
+
+/*
+ * Version 10 keeps each transaction once, with split records saying how
+ * much each vope got, where older files copied the whole transaction into
+ * every vope. The ledger knows the shares of everything assigned since
+ * version 8. Older splits are shared evenly, as their real shares are lost.
+ */
+fn v9_to_v10(obj: &mut Map<String, Value>) -> Result<(), error::Error> {
+    let port = obj
+        .get_mut("port")
+        .and_then(Value::as_object_mut)
+        .ok_or(error::Error::Parse { line: 0, column: 0 })?;
+
+    // The shares of each transaction, the first time the ledger assigned it
+    let mut assigned: HashMap<transaction::TransactionId, Vec<(String, Dollar)>> = HashMap::new();
+    let events = port
+        .get("ledger")
+        .and_then(|l| l.get("events"))
+        .and_then(Value::as_array);
+    for kind in events.into_iter().flatten().filter_map(|e| e.get("kind")) {
+        if let Some(a) = kind.get("Assign") {
+            let a: OldAssign = serde_json::from_value(a.clone())?;
+            assigned.entry(a.transaction).or_insert(a.shares);
+        }
+    }
+
+    // Each transaction, and the vopes it was copied into
+    let mut list: Vec<(Value, OldTransaction, Vec<String>)> = vec![];
+    let mut index: HashMap<transaction::TransactionId, usize> = HashMap::new();
+    for (key, val) in port.iter_mut() {
+        let vopes: Vec<&mut Value> = match key.as_str() {
+            "envelopes" => val.as_array_mut().into_iter().flatten().collect(),
+            "ignored" => vec![val],
+            _ => continue,
+        };
+
+        for v in vopes.into_iter().filter_map(Value::as_object_mut) {
+            let name = v.get("name").and_then(Value::as_str).unwrap_or_default().to_string();
+            let history = match v.remove("transactions") {
+                Some(Value::Array(history)) => history,
+                _ => continue,
+            };
+
+            for raw in history {
+                let t: OldTransaction = serde_json::from_value(raw.clone())?;
+                match index.get(&t.id) {
+                    Some(i) => list[*i].2.push(name.clone()),
+                    None => {
+                        index.insert(t.id.clone(), list.len());
+                        list.push((raw, t, vec![name.clone()]));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut splits = vec![];
+    for (_, t, names) in list.iter() {
+        let shares = match assigned.get(&t.id) {
+            Some(shares) => shares.clone(),
+            None => {
+                let even = t
+                    .charge
+                    .split_evenly(names.len())
+                    .ok_or(error::Error::Parse { line: 0, column: 0 })?;
+                names.iter().cloned().zip(even).collect()
+            }
+        };
+
+        for (vope, amount) in shares {
+            splits.push(json!({
+                "transaction": t.id.as_str(),
+                "vope": vope,
+                "amount": cents(amount),
+            }));
+        }
+    }
+
+    let transactions: Vec<Value> = list.into_iter().map(|(raw, _, _)| raw).collect();
+    port.insert("transactions".to_string(), Value::Array(transactions));
+    port.insert("splits".to_string(), Value::Array(splits));
+    Ok(())
+}
+
+/*
+ * Version 11 reconciles transactions against bank statements. Nothing in an
+ * older file has been checked yet.
+ */
+fn v10_to_v11(obj: &mut Map<String, Value>) -> Result<(), error::Error> {
+    let port = obj
+        .get_mut("port")
+        .and_then(Value::as_object_mut)
+        .ok_or(error::Error::Parse { line: 0, column: 0 })?;
+
+    let status = json!("Uncleared");
+    let list = port.get_mut("transactions").and_then(Value::as_array_mut);
+    for t in list.into_iter().flatten().filter_map(Value::as_object_mut) {
+        t.insert("status".to_string(), status.clone());
+    }
+
+    port.insert("reconciliations".to_string(), Value::Array(vec![]));
+    Ok(())
+}
+
+/*
+ * Version 12 lists vopes under named groups. Every vope starts out in no
+ * group, and there are no groups yet.
+ */
+fn v11_to_v12(obj: &mut Map<String, Value>) -> Result<(), error::Error> {
+    let port = obj
+        .get_mut("port")
+        .and_then(Value::as_object_mut)
+        .ok_or(error::Error::Parse { line: 0, column: 0 })?;
+
+    let list = port.get_mut("envelopes").and_then(Value::as_array_mut);
+    for v in list.into_iter().flatten().filter_map(Value::as_object_mut) {
+        v.insert("group".to_string(), Value::Null);
+    }
+    if let Some(v) = port.get_mut("ignored").and_then(Value::as_object_mut) {
+        v.insert("group".to_string(), Value::Null);
+    }
+
+    port.insert("groups".to_string(), Value::Array(vec![]));
+    Ok(())
+}
+
+/*
+ * Version 13 lets a vope have a savings goal, which none have yet
+ */
+fn v12_to_v13(obj: &mut Map<String, Value>) -> Result<(), error::Error> {
+    let port = obj
+        .get_mut("port")
+        .and_then(Value::as_object_mut)
+        .ok_or(error::Error::Parse { line: 0, column: 0 })?;
+
+    let list = port.get_mut("envelopes").and_then(Value::as_array_mut);
+    for v in list.into_iter().flatten().filter_map(Value::as_object_mut) {
+        v.insert("goal".to_string(), Value::Null);
+    }
+    if let Some(v) = port.get_mut("ignored").and_then(Value::as_object_mut) {
+        v.insert("goal".to_string(), Value::Null);
+    }
+
+    Ok(())
+}
+
+/*
+ * Version 14 can archive vopes, and none are yet
+ */
+fn v13_to_v14(obj: &mut Map<String, Value>) -> Result<(), error::Error> {
+    let port = obj
+        .get_mut("port")
+        .and_then(Value::as_object_mut)
+        .ok_or(error::Error::Parse { line: 0, column: 0 })?;
+
+    let list = port.get_mut("envelopes").and_then(Value::as_array_mut);
+    for v in list.into_iter().flatten().filter_map(Value::as_object_mut) {
+        v.insert("archived".to_string(), Value::Bool(false));
+    }
+    if let Some(v) = port.get_mut("ignored").and_then(Value::as_object_mut) {
+        v.insert("archived".to_string(), Value::Bool(false));
+    }
+
+    Ok(())
+}
+
+/*
+ * Version 15 gives transactions a payee, memo, notes and tags. Older files
+ * had nowhere to keep them.
+ */
+fn v14_to_v15(obj: &mut Map<String, Value>) -> Result<(), error::Error> {
+    let port = obj
+        .get_mut("port")
+        .and_then(Value::as_object_mut)
+        .ok_or(error::Error::Parse { line: 0, column: 0 })?;
+
+    let list = port.get_mut("transactions").and_then(Value::as_array_mut);
+    for t in list.into_iter().flatten().filter_map(Value::as_object_mut) {
+        t.insert("payee".to_string(), Value::Null);
+        t.insert("memo".to_string(), Value::Null);
+        t.insert("notes".to_string(), Value::String(String::new()));
+        t.insert("tags".to_string(), Value::Array(vec![]));
+    }
+
+    Ok(())
+}
+
+/*
+ * A vope as files before version 10 stored it, with only what the steps
+ * above need
+ */
+#[derive(Deserialize)]
+struct OldVope {
+    name: String,
+    budget: Dollar,
+    actual_amount: Dollar,
+    transactions: Vec<OldTransaction>,
+}
+
+#[derive(Deserialize)]
+struct OldTransaction {
+    id: transaction::TransactionId,
+    date: chrono::NaiveDate,
+    charge: Dollar,
+}
+
+/*
+ * An Assign event as the ledger stored it from version 8
+ */
+#[derive(Deserialize)]
+struct OldAssign {
+    transaction: transaction::TransactionId,
+    shares: Vec<(String, Dollar)>,
+}
+
+/*
+ * A Dollar as files have stored it since version 1
+ */
+fn cents(amount: Dollar) -> Value {
+    json!({"cents": amount.cents()})
+}
@@ -1,4 +1,9 @@
-
+use std::{
+    ffi::OsString,
+    fs,
+    io::{self, Write},
+    path::Path,
+};
 
 pub fn money_to_float(s: &str) -> Result<f32, std::num::ParseFloatError> {
     let mut rv = String::new();
@@ -15,3 +20,31 @@ pub fn round(num: f32, decimals: u32) -> f32 {
     let precison = 10i32.pow(decimals) as f32;
     (num * precison).round() / precison
 }
+
+/// Replaces the file at `path` with `contents` without ever leaving it half written.
+///
+/// The data goes to a temporary file in the same directory first, which is
+/// flushed to disk and then renamed over the original.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+
+    let mut tmp_name = OsString::from(".");
+    tmp_name.push(file_name);
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let res = (|| {
+        let mut f = fs::File::create(&tmp_path)?;
+        f.write_all(contents)?;
+        f.sync_all()?;
+        fs::rename(&tmp_path, path)
+    })();
+
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+
+    res
+}
@@ -0,0 +1,271 @@
+use crate::{dollar, error, transaction};
+use chrono::NaiveDate;
+use std::{borrow::Cow, fs, path};
+
+/// Everything read from one OFX or QFX statement
+#[derive(Clone, Debug, Default)]
+pub struct Statement {
+    /// The bank's number for the account, from ACCTID
+    pub account_id: Option<String>,
+    pub transactions: Vec<transaction::Transaction>,
+    /// The balance the bank reported, from LEDGERBAL
+    pub ledger_balance: Option<Balance>,
+}
+
+/// A balance as of a date
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Balance {
+    pub amount: dollar::Dollar,
+    pub date: NaiveDate,
+}
+
+/// Reads the OFX or QFX file at `path`
+pub fn parse(path: &path::Path) -> Result<Statement, error::Error> {
+    let raw = fs::read(path).map_err(|e| error::Error::io(path, e))?;
+
+    // Older statements are often Windows-1252, anything unreadable is replaced
+    parse_str(&String::from_utf8_lossy(&raw))
+}
+
+/// Reads an OFX statement.
+///
+/// Handles both the SGML form of OFX 1.x, where values have no closing tag,
+/// and the XML form of OFX 2.x. Each STMTTRN becomes a transaction carrying
+/// its FITID, so importing the same statement twice finds the duplicates.
+pub fn parse_str(s: &str) -> Result<Statement, error::Error> {
+    let mut statement = Statement::default();
+
+    // Values seen so far in the STMTTRN or LEDGERBAL being read
+    let mut trn: Option<Fields> = None;
+    let mut bal: Option<Fields> = None;
+
+    for tag in Tags::new(s) {
+        let tag = tag?;
+
+        match (tag.name.as_str(), tag.closing) {
+            ("STMTTRN", false) => trn = Some(Fields::new(tag.line)),
+            ("STMTTRN", true) => {
+                if let Some(f) = trn.take() {
+                    let t = f.transaction(statement.account_id.as_deref())?;
+                    statement.transactions.push(t);
+                }
+            }
+            ("LEDGERBAL", false) => bal = Some(Fields::new(tag.line)),
+            ("LEDGERBAL", true) => {
+                if let Some(f) = bal.take() {
+                    statement.ledger_balance = Some(Balance {
+                        amount: f.amount("BALAMT")?,
+                        date: f.date("DTASOF")?,
+                    });
+                }
+            }
+            ("ACCTID", false) if !tag.text.is_empty() => {
+                statement.account_id = Some(tag.text.into_owned())
+            }
+            (_, false) if !tag.text.is_empty() => {
+                if let Some(f) = trn.as_mut().or(bal.as_mut()) {
+                    f.values.push((tag.name, tag.text.into_owned(), tag.line));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    transaction::number_occurrences(&mut statement.transactions);
+
+    Ok(statement)
+}
+
+/*
+ * The leaf values inside one aggregate, such as a STMTTRN
+ */
+struct Fields {
+    line: usize,
+    values: Vec<(String, String, usize)>,
+}
+
+impl Fields {
+    fn new(line: usize) -> Fields {
+        Fields {
+            line,
+            values: vec![],
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<(&str, usize)> {
+        self.values
+            .iter()
+            .find(|(n, _, _)| n == name)
+            .map(|(_, v, line)| (v.as_str(), *line))
+    }
+
+    fn require(&self, name: &str) -> Result<(&str, usize), error::Error> {
+        self.get(name).ok_or(error::Error::Parse {
+            line: self.line,
+            column: 0,
+        })
+    }
+
+    // Dates look like 20230701 or 20230701120000.000[-5:EST]
+    fn date(&self, name: &str) -> Result<NaiveDate, error::Error> {
+        let (raw, line) = self.require(name)?;
+
+        raw.get(..8)
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y%m%d").ok())
+            .ok_or(error::Error::Parse { line, column: 0 })
+    }
+
+    // Some banks write a decimal comma
+    fn amount(&self, name: &str) -> Result<dollar::Dollar, error::Error> {
+        let (raw, line) = self.require(name)?;
+
+        let raw = if raw.contains('.') {
+            raw.to_string()
+        } else {
+            raw.replace(',', ".")
+        };
+
+        raw.parse()
+            .map_err(|_| error::Error::Parse { line, column: 0 })
+    }
+
+    fn transaction(&self, account: Option<&str>) -> Result<transaction::Transaction, error::Error> {
+        let date = self.date("DTPOSTED")?;
+        let charge = self.amount("TRNAMT")?;
+
+        // NAME is the payee, MEMO is usually the bank's extra detail
+        let desc = ["NAME", "PAYEE", "MEMO"]
+            .iter()
+            .find_map(|n| self.get(n))
+            .map_or("Unknown", |(v, _)| v);
+
+        let payee = ["NAME", "PAYEE"].iter().find_map(|n| self.get(n)).map(|(v, _)| v);
+        let memo = self.get("MEMO").map(|(v, _)| v);
+        let t = transaction::Transaction::new(date, desc.to_string(), charge).with_payee_memo(payee, memo);
+
+        // FITIDs are only unique within one account
+        Ok(match (self.get("FITID"), account) {
+            (Some((fitid, _)), Some(acct)) => t.with_bank_id(&format!("{}/{}", acct, fitid)),
+            (Some((fitid, _)), None) => t.with_bank_id(fitid),
+            (None, _) => t,
+        })
+    }
+}
+
+/*
+ * One tag, and the text that follows it up to the next tag
+ */
+struct Tag<'a> {
+    name: String,
+    closing: bool,
+    /// With entities such as `&amp;` decoded
+    text: Cow<'a, str>,
+    line: usize,
+}
+
+/*
+ * Walks the tags of an OFX document, skipping the header before <OFX>
+ */
+struct Tags<'a> {
+    s: &'a str,
+    pos: usize,
+    line: usize,
+}
+
+impl<'a> Tags<'a> {
+    fn new(s: &'a str) -> Tags<'a> {
+        let start = s.find("<OFX>").unwrap_or(0);
+        Tags {
+            s,
+            pos: start,
+            line: 1 + s[..start].matches('\n').count(),
+        }
+    }
+}
+
+impl<'a> Iterator for Tags<'a> {
+    type Item = Result<Tag<'a>, error::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.s[self.pos..];
+        let open = rest.find('<')?;
+
+        self.line += rest[..open].matches('\n').count();
+        let rest = &rest[open + 1..];
+
+        let close = match rest.find('>') {
+            Some(c) => c,
+            None => {
+                return Some(Err(error::Error::Parse {
+                    line: self.line,
+                    column: 0,
+                }))
+            }
+        };
+
+        let raw_name = rest[..close].trim();
+        let after = &rest[close + 1..];
+        let text_len = after.find('<').unwrap_or(after.len());
+
+        let tag = Tag {
+            name: raw_name.trim_start_matches('/').to_ascii_uppercase(),
+            closing: raw_name.starts_with('/'),
+            text: decode_entities(after[..text_len].trim()),
+            line: self.line,
+        };
+
+        self.line += rest[..close].matches('\n').count();
+        self.pos += open + 1 + close + 1;
+
+        Some(Ok(tag))
+    }
+}
+
+/*
+ * Replaces the named XML entities and numeric character references in
+ * `text`. Anything that is not a known entity is left as it is.
+ */
+fn decode_entities(text: &str) -> Cow<'_, str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let decoded = rest.find(';').and_then(|semi| {
+            let c = match &rest[1..semi] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                num => {
+                    let code = match num.strip_prefix('#')? {
+                        hex if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16).ok()?,
+                        dec => dec.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, semi + 1))
+        });
+
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+
+    Cow::Owned(out)
+}
@@ -0,0 +1,121 @@
+use crate::{dollar::Dollar, error, portfolio, vope};
+use chrono::NaiveDate;
+
+/// How a paycheck is spread across the vopes
+#[derive(Clone, Debug, PartialEq)]
+pub enum Allocation {
+    /// Every vope gets the share of the paycheck its budget is of the total
+    /// budget, rounded down to the cent. Archived vopes are skipped.
+    BudgetShare,
+    /// These vopes get exactly these amounts
+    Fixed(Vec<(String, Dollar)>),
+    /// These vopes are topped up to their budget one at a time, in order,
+    /// until the paycheck runs out. A vope already holding its budget or
+    /// more gets nothing.
+    Priority(Vec<String>),
+    /// Every vope with a savings goal gets what it still needs in the period
+    /// containing `date`, in the order the vopes are listed, until the
+    /// paycheck runs out. Archived vopes are skipped. See
+    /// `Portfolio::goal_progress`.
+    Goals { date: NaiveDate },
+}
+
+/// Works out how much of `amount` each vope gets, without moving anything.
+///
+/// Whatever the allocation leaves over, rounding included, goes to the
+/// default vope, so the shares always add up to `amount`. Vopes that get
+/// nothing are left out.
+///
+/// Returns `Err(NoDefaultVope)` if no default vope is set, `Err(UnknownVope)`
+/// if the allocation names a vope that does not exist,
+/// `Err(NegativeAllocation)` if a fixed amount is below $0, and
+/// `Err(OverAllocated)` if fixed amounts add up to more than the paycheck.
+pub(crate) fn plan(
+    port: &portfolio::Portfolio,
+    amount: Dollar,
+    how: &Allocation,
+) -> Result<Vec<(String, Dollar)>, error::Error> {
+    let default = port.view_default_vope().ok_or(error::Error::NoDefaultVope)?;
+
+    // Checks the name and uses the vope's own spelling of it
+    let name_of = |n: &str| {
+        port.view_vopes()
+            .iter()
+            .find(|v| v.name.eq_ignore_ascii_case(n))
+            .map(|v| v.name.clone())
+            .ok_or_else(|| error::Error::UnknownVope(n.to_string()))
+    };
+
+    let mut shares: Vec<(String, Dollar)> = vec![];
+
+    match how {
+        Allocation::BudgetShare => {
+            let budget = |v: &vope::Vope| v.budget.cents().max(0) as i128;
+            let listed = || port.view_vopes().iter().filter(|v| !v.archived);
+            let total: i128 = listed().map(budget).sum();
+
+            // With nothing budgeted the whole paycheck is left over
+            if total > 0 {
+                for v in listed() {
+                    let share = amount.cents() as i128 * budget(v) / total;
+                    shares.push((v.name.clone(), Dollar::from_cents(share as i64)));
+                }
+            }
+        }
+        Allocation::Fixed(list) => {
+            for (n, a) in list {
+                if a.is_negative() {
+                    return Err(error::Error::NegativeAllocation {
+                        name: n.clone(),
+                        amount: *a,
+                    });
+                }
+                shares.push((name_of(n)?, *a));
+            }
+
+            let allocated: Dollar = shares.iter().map(|(_, a)| *a).sum();
+            if allocated > amount {
+                return Err(error::Error::OverAllocated { amount, allocated });
+            }
+        }
+        Allocation::Priority(list) => {
+            let mut left = amount;
+            for n in list {
+                let name = name_of(n)?;
+                let missing = port
+                    .view_vopes()
+                    .iter()
+                    .find(|v| v.name == name)
+                    .map_or(Dollar::ZERO, |v| (v.budget - v.actual_amount).max(Dollar::ZERO));
+
+                let share = missing.min(left);
+                left -= share;
+                shares.push((name, share));
+            }
+        }
+        Allocation::Goals { date } => {
+            let mut left = amount;
+            for v in port.view_vopes().iter().filter(|v| !v.archived) {
+                let required = match port.goal_progress(&v.name, *date)? {
+                    Some(p) => p.required,
+                    None => continue,
+                };
+
+                let share = required.min(left);
+                left -= share;
+                shares.push((v.name.clone(), share));
+            }
+        }
+    }
+
+    let allocated: Dollar = shares.iter().map(|(_, a)| *a).sum();
+    let remainder = amount - allocated;
+
+    match shares.iter_mut().find(|(n, _)| n.eq_ignore_ascii_case(default)) {
+        Some((_, share)) => *share += remainder,
+        None => shares.push((default.to_string(), remainder)),
+    }
+
+    shares.retain(|(_, a)| !a.is_zero());
+    Ok(shares)
+}
@@ -1,16 +1,54 @@
-use crate::{dollar, transaction, vope};
+use crate::{budget, dollar, error, goal, ledger, paycheck, reconcile, split, transaction, vope};
 use serde;
-use std::{error, io::ErrorKind};
+use std::{collections, fmt};
+
+// The name of the vope ignored transactions are filed under
+const IGNORED: &str = "Ignored";
 
 /**
  * A portfolio is a collection of Vopes
+ *
+ * Every change is written to the ledger first. Vope balances are kept as a
+ * running total of the ledger, which `verify` and `rebuild` can check.
  */
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub struct Portfolio {
-    envelopes: Vec<vope::Vope>, // Our vopes
+    envelopes: Vec<vope::Vope>, // Our vopes, in the order they are listed
+    groups: Vec<vope::Group>,   // Headings the vopes are listed under, in order
     ignored: vope::Vope,
+    transactions: Vec<transaction::Transaction>, // Every assigned transaction, once
+    splits: Vec<split::Split>,                   // How each one was shared between the vopes
     budgeted: dollar::Dollar, // Amount of paycheck budgeted
     holdings: dollar::Dollar, // Total money in account
+    default_vope: Option<String>, // Gets whatever a paycheck allocation leaves over
+    period: budget::Period,       // How long each budget period is
+    ledger: ledger::Ledger,       // Everything that has happened, balances follow from it
+    reconciliations: Vec<reconcile::Reconciliation>, // Statements the transactions were checked against
+}
+
+/**
+ * A portfolio as it was before an undoable change.
+ *
+ * Everything a journal command can edit is copied. The transactions and
+ * splits grow with the account, and commands only ever add to them, so only
+ * their lengths are kept and `restore` cuts them back. The ledger is never
+ * cut: `restore` adds events that take back what was done since.
+ */
+#[derive(Debug, Clone)]
+pub(crate) struct Checkpoint {
+    envelopes: Vec<vope::Vope>,
+    groups: Vec<vope::Group>,
+    ignored: vope::Vope,
+    budgeted: dollar::Dollar,
+    holdings: dollar::Dollar,
+    default_vope: Option<String>,
+    period: budget::Period,
+    reconciliations: Vec<reconcile::Reconciliation>,
+    transactions: usize,
+    splits: usize,
+    events: usize,
+    /// Where the change's own events end, once it is made
+    done: Option<usize>,
 }
 
 impl Portfolio {
@@ -18,9 +56,16 @@ impl Portfolio {
     pub(crate) fn new() -> Portfolio {
         Self {
             envelopes: vec![],
-            ignored: vope::Vope::new("Ignored".to_owned(), dollar::Dollar::default()),
+            groups: vec![],
+            ignored: vope::Vope::new(IGNORED.to_owned(), dollar::Dollar::default()),
+            transactions: vec![],
+            splits: vec![],
             budgeted: dollar::Dollar::default(),
             holdings: dollar::Dollar::default(),
+            default_vope: None,
+            period: budget::Period::default(),
+            ledger: ledger::Ledger::default(),
+            reconciliations: vec![],
         }
     }
 
@@ -29,6 +74,28 @@ impl Portfolio {
         &self.envelopes
     }
 
+    pub fn view_groups(&self) -> &[vope::Group] {
+        &self.groups
+    }
+
+    /// Every group with its vopes in order, then the vopes in no group
+    /// under `None`. Archived vopes are left out.
+    pub fn grouped_vopes(&self) -> Vec<(Option<&vope::Group>, Vec<&vope::Vope>)> {
+        let listed = || self.envelopes.iter().filter(|v| !v.archived);
+        let mut list: Vec<(Option<&vope::Group>, Vec<&vope::Vope>)> = self
+            .groups
+            .iter()
+            .map(|g| (Some(g), listed().filter(|v| v.group.as_ref() == Some(&g.name)).collect()))
+            .collect();
+
+        list.push((None, listed().filter(|v| v.group.is_none()).collect()));
+        list
+    }
+
+    pub fn view_ignored(&self) -> &vope::Vope {
+        &self.ignored
+    }
+
     pub fn view_holdings(&self) -> dollar::Dollar {
         self.holdings
     }
@@ -37,24 +104,239 @@ impl Portfolio {
         self.budgeted
     }
 
+    pub fn view_default_vope(&self) -> Option<&str> {
+        self.default_vope.as_deref()
+    }
+
+    pub fn view_ledger(&self) -> &ledger::Ledger {
+        &self.ledger
+    }
+
+    pub fn view_reconciliations(&self) -> &[reconcile::Reconciliation] {
+        &self.reconciliations
+    }
+
+    pub fn view_splits(&self) -> &[split::Split] {
+        &self.splits
+    }
+
+    /// How the transaction with `id` was shared between the vopes
+    pub fn splits_of(&self, id: &transaction::TransactionId) -> Vec<&split::Split> {
+        self.splits.iter().filter(|s| &s.transaction == id).collect()
+    }
+
+    /// Every vope's balance and budget at the end of `date`, according to the ledger
+    pub fn balances_as_of(&self, date: chrono::NaiveDate) -> Vec<ledger::Balance> {
+        self.ledger.balances(Some(date))
+    }
+
+    /// The named vope's balance at the end of `date`, according to the ledger
+    ///
+    /// Returns `Err(UnknownVope)` if the vope did not exist then
+    pub fn balance_as_of(&self, name: &str, date: chrono::NaiveDate) -> Result<dollar::Dollar, error::Error> {
+        self.balances_as_of(date)
+            .into_iter()
+            .find(|b| b.name.eq_ignore_ascii_case(name))
+            .map(|b| b.amount)
+            .ok_or_else(|| error::Error::UnknownVope(name.to_string()))
+    }
+
+    /// Compares every vope's balance with what the ledger adds up to.
+    /// Returns nothing if they all agree.
+    pub fn verify(&self) -> Vec<ledger::Mismatch> {
+        let derived = self.ledger.balances(None);
+        let mut list = vec![];
+
+        for v in self.histories() {
+            let d = derived.iter().find(|b| b.name.eq_ignore_ascii_case(&v.name));
+            if d.map(|b| b.amount) != Some(v.actual_amount) && !(d.is_none() && v.actual_amount.is_zero()) {
+                list.push(ledger::Mismatch {
+                    name: v.name.clone(),
+                    stored: Some(v.actual_amount),
+                    derived: d.map(|b| b.amount),
+                });
+            }
+        }
+
+        // Money the ledger has for a vope that is gone
+        for b in derived.iter().filter(|b| !b.amount.is_zero() && !self.contains(&b.name)) {
+            list.push(ledger::Mismatch {
+                name: b.name.clone(),
+                stored: None,
+                derived: Some(b.amount),
+            });
+        }
+
+        list
+    }
+
+    /// Sets every vope's balance and budget to what the ledger says, and
+    /// returns what had to change. See `verify`.
+    pub fn rebuild(&mut self) -> Vec<ledger::Mismatch> {
+        let fixed = self.verify();
+        let derived = self.ledger.balances(None);
+
+        for v in self.envelopes.iter_mut().chain(std::iter::once(&mut self.ignored)) {
+            let b = derived.iter().find(|b| b.name.eq_ignore_ascii_case(&v.name));
+            v.actual_amount = b.map_or(dollar::Dollar::ZERO, |b| b.amount);
+            if let Some(b) = b.filter(|b| b.name != IGNORED) {
+                v.budget = b.budget;
+            }
+        }
+
+        self.calc_holdings();
+        fixed
+    }
+
+    pub fn view_period(&self) -> budget::Period {
+        self.period
+    }
+
+    /// Changes how long budget periods are
+    ///
+    /// Returns `Err(InvalidPeriod)` if the period cannot be used
+    pub fn set_period(&mut self, period: budget::Period) -> Result<(), error::Error> {
+        period.validate()?;
+        self.period = period;
+        Ok(())
+    }
+
+    /// Changes what the named vope carries from one period to the next
+    ///
+    /// Returns `Err(UnknownVope)` if there is no vope with that name
+    pub fn set_rollover(&mut self, name: &str, rollover: budget::Rollover) -> Result<(), error::Error> {
+        let pos = self
+            .get_vope_pos(name)
+            .ok_or_else(|| error::Error::UnknownVope(name.to_string()))?;
+        self.envelopes[pos].rollover = rollover;
+        Ok(())
+    }
+
+    /// The named vope's budgeted, spent and available figures for the period
+    /// containing `date`
+    ///
+    /// Returns `Err(UnknownVope)` if there is no vope with that name
+    pub fn period_summary(
+        &self,
+        name: &str,
+        date: chrono::NaiveDate,
+    ) -> Result<budget::PeriodSummary, error::Error> {
+        let pos = self
+            .get_vope_pos(name)
+            .ok_or_else(|| error::Error::UnknownVope(name.to_string()))?;
+        let v = &self.envelopes[pos];
+        let budgets = self.ledger.budget_history(&v.name);
+        Ok(budget::summarize(v, &self.history(&v.name), &budgets, &self.period, date))
+    }
+
+    /// Gives the named vope a savings goal, or takes it away with `None`
+    ///
+    /// Returns `Err(UnknownVope)` if there is no vope with that name, and
+    /// `Err(InvalidGoal)` if the goal cannot be used
+    pub fn set_goal(&mut self, name: &str, goal: Option<goal::Goal>) -> Result<(), error::Error> {
+        let pos = self
+            .get_vope_pos(name)
+            .ok_or_else(|| error::Error::UnknownVope(name.to_string()))?;
+        if let Some(g) = &goal {
+            g.validate()?;
+        }
+
+        self.envelopes[pos].goal = goal;
+        Ok(())
+    }
+
+    /// Where the named vope's goal stands in the period containing `date`,
+    /// or `None` if it has no goal. See `goal::progress`.
+    ///
+    /// Returns `Err(UnknownVope)` if there is no vope with that name
+    pub fn goal_progress(
+        &self,
+        name: &str,
+        date: chrono::NaiveDate,
+    ) -> Result<Option<goal::Progress>, error::Error> {
+        let pos = self
+            .get_vope_pos(name)
+            .ok_or_else(|| error::Error::UnknownVope(name.to_string()))?;
+        let v = &self.envelopes[pos];
+        let g = match &v.goal {
+            Some(g) => g,
+            None => return Ok(None),
+        };
+
+        let start = self.period.start_of(date);
+        let end = self.period.next(start);
+
+        // A vope created during the period started it with nothing
+        let opening = start
+            .pred_opt()
+            .and_then(|d| self.balance_as_of(&v.name, d).ok())
+            .unwrap_or(dollar::Dollar::ZERO);
+        let balance = end
+            .pred_opt()
+            .and_then(|d| self.balance_as_of(&v.name, d).ok())
+            .unwrap_or(v.actual_amount);
+        let spent: dollar::Dollar = self
+            .history(&v.name)
+            .iter()
+            .filter(|p| p.transaction.date >= start && p.transaction.date < end && p.amount.is_negative())
+            .map(|p| -p.amount)
+            .sum();
+
+        Ok(Some(goal::progress(g, &self.period, date, opening, balance, spent)))
+    }
+
+    /// Every vope's figures for the period containing `date`
+    pub fn period_report(&self, date: chrono::NaiveDate) -> Vec<(String, budget::PeriodSummary)> {
+        self.envelopes
+            .iter()
+            .map(|v| {
+                let budgets = self.ledger.budget_history(&v.name);
+                (v.name.clone(), budget::summarize(v, &self.history(&v.name), &budgets, &self.period, date))
+            })
+            .collect()
+    }
+
+    /// Picks the vope that gets the leftover of every paycheck, or none
+    ///
+    /// Returns `Err(UnknownVope)` if there is no vope with that name
+    pub fn set_default_vope(&mut self, name: Option<&str>) -> Result<(), error::Error> {
+        self.default_vope = match name {
+            Some(n) => {
+                let pos = self
+                    .get_vope_pos(n)
+                    .ok_or_else(|| error::Error::UnknownVope(n.to_string()))?;
+                Some(self.envelopes[pos].name.clone())
+            }
+            None => None,
+        };
+
+        Ok(())
+    }
+
     /// Adds a new vope to the Portfolio
     ///
     /// Returns `Ok(())` on success.
     ///
-    /// Returns `Err(InvalidInput)` if the vope name is a duplicate
-    pub fn add_vope(
-        &mut self,
-        name: &str,
-        budget: dollar::Dollar,
-    ) -> Result<(), Box<dyn error::Error>> {
-        if self.contains(&name) {
+    /// Returns `Err(DuplicateVope)` if the vope name is a duplicate, and
+    /// `Err(BlankVopeName)` if it is blank
+    pub fn add_vope(&mut self, name: &str, budget: dollar::Dollar) -> Result<(), error::Error> {
+        let name = name.trim();
+        if name.is_empty() {
+            Err(error::Error::BlankVopeName)
+        } else if self.contains(name) {
             // Duplicate name
-            Err(Box::new(std::io::Error::from(ErrorKind::InvalidInput)))
+            Err(error::Error::DuplicateVope(name.to_string()))
         } else {
             // Allowed
             self.envelopes
                 .push(vope::Vope::new(name.to_string(), budget));
-            self.budgeted += budget;
+            self.record(
+                ledger::today(),
+                ledger::EventKind::CreateVope {
+                    name: name.to_string(),
+                    budget,
+                },
+            );
 
             Ok(())
         }
@@ -64,164 +346,576 @@ impl Portfolio {
     ///
     /// Returns `Ok(())` on success.
     ///
-    /// Returns `Err(InvalidInput)` if the vope name is a duplicate
-    /// Returns `Err(InvalidData)` if the vope balance is not $0
-    pub fn remove_vope(&mut self, name: &str) -> Result<(), Box<dyn error::Error>> {
+    /// Returns `Err(UnknownVope)` if there is no vope with that name
+    /// Returns `Err(NonZeroBalance)` if the vope balance is not $0
+    pub fn remove_vope(&mut self, name: &str) -> Result<(), error::Error> {
         let pos = self.get_vope_pos(name);
         match pos {
             Some(ind) => {
-                // Unwrap and remove are okay - we just checked for existance
-                if self.envelopes.get(ind).unwrap().actual_amount == dollar::Dollar::from(0.0) {
-                    self.envelopes.remove(ind);
+                // Index is okay - we just checked for existance
+                let v = &self.envelopes[ind];
+                if v.actual_amount == dollar::Dollar::ZERO {
+                    if self.default_vope.as_ref() == Some(&v.name) {
+                        self.default_vope = None;
+                    }
+                    let name = self.envelopes.remove(ind).name;
+                    self.record(ledger::today(), ledger::EventKind::DeleteVope { name });
                     Ok(())
                 } else {
-                    Err(Box::new(std::io::Error::from(ErrorKind::InvalidData)))
+                    Err(error::Error::NonZeroBalance {
+                        name: v.name.clone(),
+                        balance: v.actual_amount,
+                    })
+                }
+            }
+            None => Err(error::Error::UnknownVope(name.to_string())),
+        }
+    }
+
+    /// Gives a vope a new name. Its history and splits follow it, and so
+    /// does the default vope. The ledger records the rename.
+    ///
+    /// Returns `Err(UnknownVope)` if there is no vope named `name`,
+    /// `Err(DuplicateVope)` if another vope already has the new name,
+    /// ignoring case, and `Err(BlankVopeName)` if the new name is blank
+    pub fn rename_vope(&mut self, name: &str, new_name: &str) -> Result<(), error::Error> {
+        let new_name = new_name.trim();
+        if new_name.is_empty() {
+            return Err(error::Error::BlankVopeName);
+        }
+        let pos = self
+            .get_vope_pos(name)
+            .ok_or_else(|| error::Error::UnknownVope(name.to_string()))?;
+        let old = self.envelopes[pos].name.clone();
+
+        // Changing only the case of its own name is allowed
+        if (self.contains(new_name) || Self::is_ignore(new_name)) && !old.eq_ignore_ascii_case(new_name) {
+            return Err(error::Error::DuplicateVope(new_name.to_string()));
+        }
+
+        self.envelopes[pos].name = new_name.to_string();
+        if self.default_vope.as_ref() == Some(&old) {
+            self.default_vope = Some(new_name.to_string());
+        }
+        for s in self.splits.iter_mut().filter(|s| s.vope.eq_ignore_ascii_case(&old)) {
+            s.vope = new_name.to_string();
+        }
+
+        self.record(
+            ledger::today(),
+            ledger::EventKind::Rename {
+                from: old,
+                to: new_name.to_string(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Folds the vope `from` into `into`, which takes its balance and every
+    /// transaction it held. `from` is removed, and `into` keeps its own
+    /// budget, goal and group.
+    ///
+    /// Returns `Err(UnknownVope)` if either vope does not exist, and
+    /// `Err(SameVope)` if they are the same vope
+    pub fn merge_vope(&mut self, from: &str, into: &str) -> Result<(), error::Error> {
+        let src = self
+            .get_vope_pos(from)
+            .ok_or_else(|| error::Error::UnknownVope(from.to_string()))?;
+        let dest = self
+            .get_vope_pos(into)
+            .ok_or_else(|| error::Error::UnknownVope(into.to_string()))?;
+        if src == dest {
+            return Err(error::Error::SameVope(from.to_string()));
+        }
+
+        let from = self.envelopes[src].name.clone();
+        let into = self.envelopes[dest].name.clone();
+
+        // One split per transaction, where both held part of it. `into`'s
+        // splits are found by transaction, so long histories merge quickly.
+        let mut merged: Vec<split::Split> = Vec::with_capacity(self.splits.len());
+        let mut into_split: collections::HashMap<transaction::TransactionId, usize> = collections::HashMap::new();
+        for mut s in self.splits.drain(..) {
+            if s.vope == from {
+                s.vope = into.clone();
+            }
+            if s.vope != into {
+                merged.push(s);
+                continue;
+            }
+            match into_split.get(&s.transaction) {
+                Some(&i) => merged[i].amount += s.amount,
+                None => {
+                    into_split.insert(s.transaction.clone(), merged.len());
+                    merged.push(s);
                 }
             }
-            None => Err(Box::new(std::io::Error::from(ErrorKind::InvalidInput))),
         }
+        self.splits = merged;
+
+        if self.default_vope.as_ref() == Some(&from) {
+            self.default_vope = Some(into.clone());
+        }
+
+        let amount = self.envelopes[src].actual_amount;
+        self.record(ledger::today(), ledger::EventKind::Merge { from, into, amount });
+        self.envelopes.remove(src);
+        self.calc_holdings();
+        Ok(())
     }
 
+    /// Hides a vope from the lists, or brings it back. An archived vope
+    /// keeps its money and history, but gets no part of a paycheck and its
+    /// budget is not counted.
+    ///
+    /// Returns `Err(UnknownVope)` if there is no vope with that name
+    pub fn set_archived(&mut self, name: &str, archived: bool) -> Result<(), error::Error> {
+        let pos = self
+            .get_vope_pos(name)
+            .ok_or_else(|| error::Error::UnknownVope(name.to_string()))?;
+
+        self.envelopes[pos].archived = archived;
+        self.calc_holdings();
+        Ok(())
+    }
+
+    /// Moves money from one vope to another.
+    ///
+    /// Returns `Err(UnknownVope)` if either vope does not exist, in which case
+    /// neither balance is touched.
     pub fn transfer_holdings(
         &mut self,
         from_name: &str,
         dest_name: &str,
         amount: dollar::Dollar,
-    ) -> Result<(), Box<dyn error::Error>> {
-        let from_vope = self
-            .envelopes
-            .iter_mut()
-            .find(|v| v.name.eq_ignore_ascii_case(from_name));
+    ) -> Result<(), error::Error> {
+        // Check both ends before moving anything
+        let from = self
+            .get_vope_pos(from_name)
+            .ok_or_else(|| error::Error::UnknownVope(from_name.to_string()))?;
+        let dest = self
+            .get_vope_pos(dest_name)
+            .ok_or_else(|| error::Error::UnknownVope(dest_name.to_string()))?;
+
+        let kind = ledger::EventKind::Transfer {
+            from: self.envelopes[from].name.clone(),
+            to: self.envelopes[dest].name.clone(),
+            amount,
+        };
+        self.record(ledger::today(), kind);
 
-        if let Some(from) = from_vope {
-            from.actual_amount -= amount;
-        } else {
-            return Err(Box::new(std::io::Error::from(ErrorKind::InvalidData)));
-        }
+        Ok(())
+    }
 
-        let dest_vope = self
-            .envelopes
-            .iter_mut()
-            .find(|v| v.name.eq_ignore_ascii_case(dest_name));
-        if let Some(dest) = dest_vope {
-            dest.actual_amount += amount;
-        } else {
-            return Err(Box::new(std::io::Error::from(ErrorKind::InvalidData)));
-        }
+    /// Changes how much the named vope is budgeted each period
+    ///
+    /// Returns `Err(UnknownVope)` if there is no vope with that name
+    pub fn set_budget(&mut self, name: &str, budget: dollar::Dollar) -> Result<(), error::Error> {
+        let pos = self
+            .get_vope_pos(name)
+            .ok_or_else(|| error::Error::UnknownVope(name.to_string()))?;
+
+        self.envelopes[pos].budget = budget;
+        let name = self.envelopes[pos].name.clone();
+        self.record(ledger::today(), ledger::EventKind::SetBudget { name, budget });
 
         Ok(())
     }
 
     /// Given a set of Transactions, returns a list removing all duplicates
+    ///
+    /// Transactions are compared by ID, so identical lines from one statement
+    /// are kept as long as they were numbered by `number_occurrences`.
     pub fn clean_transaction_list(&self, list: &mut Vec<transaction::Transaction>) {
-        // Remove any dupicates in the input
-        list.sort();
-        list.dedup();
+        // Remove any dupicates in the input, wherever they are in it
+        let mut seen: collections::HashSet<transaction::TransactionId> = collections::HashSet::new();
+        list.retain(|t| seen.insert(t.id.clone()));
+
+        // Remove anything already assigned, including ignored transactions
+        let known: collections::HashSet<&transaction::TransactionId> =
+            self.transactions.iter().map(|t| &t.id).collect();
+
+        list.retain(|t| !known.contains(&t.id));
+        list.sort_by(|a, b| a.statement_order(b));
+    }
 
-        // Remove any duplicates from the list
-        for v in self.envelopes.iter() {
-            list.retain(|t| v.transactions.contains(&t) == false);
+    /// Finds an assigned (or ignored) transaction by its ID
+    pub fn find_transaction(
+        &self,
+        id: &transaction::TransactionId,
+    ) -> Option<&transaction::Transaction> {
+        self.transactions.iter().find(|t| &t.id == id)
+    }
+
+    /// Changes an assigned transaction everywhere it appears.
+    ///
+    /// Returns `Err(UnknownTransaction)` if no vope holds the transaction, and
+    /// `Err(Reconciled)` if it is locked by a reconciliation. The payee, memo,
+    /// notes and tags can still be changed once it is locked.
+    pub fn edit_transaction(
+        &mut self,
+        id: &transaction::TransactionId,
+        edit: &transaction::TransactionEdit,
+    ) -> Result<(), error::Error> {
+        let t = if edit.is_annotation() {
+            self.transactions
+                .iter_mut()
+                .find(|t| &t.id == id)
+                .ok_or_else(|| error::Error::UnknownTransaction(id.clone()))?
+        } else {
+            self.unlocked_mut(id)?
+        };
+        edit.apply(t);
+        Ok(())
+    }
+
+    /// Every assigned transaction with `tag`, whatever its case
+    pub fn tagged(&self, tag: &str) -> Vec<&transaction::Transaction> {
+        self.transactions.iter().filter(|t| t.has_tag(tag)).collect()
+    }
+
+    /// Every assigned (or ignored) transaction once, however many vopes it
+    /// was split across
+    pub fn transactions(&self) -> &[transaction::Transaction] {
+        &self.transactions
+    }
+
+    /// Ticks a transaction off against the statement being reconciled, or
+    /// takes the tick back
+    ///
+    /// Returns `Err(UnknownTransaction)` if it was never assigned, and
+    /// `Err(Reconciled)` if it is locked
+    pub fn set_cleared(&mut self, id: &transaction::TransactionId, cleared: bool) -> Result<(), error::Error> {
+        let t = self.unlocked_mut(id)?;
+        t.status = if cleared {
+            transaction::Status::Cleared
+        } else {
+            transaction::Status::Uncleared
+        };
+        Ok(())
+    }
+
+    /// Compares the cleared transactions with a statement, see `reconcile::summarize`
+    pub fn reconcile_summary(
+        &self,
+        rec: &reconcile::Reconciliation,
+        opening: dollar::Dollar,
+    ) -> reconcile::Summary {
+        reconcile::summarize(&self.transactions, rec.account.as_deref(), opening, rec.date, rec.balance)
+    }
+
+    /// Locks every cleared transaction on the statement, and returns how many
+    /// there were. The statement is kept in `view_reconciliations`.
+    ///
+    /// Returns `Err(Unbalanced)` if the cleared transactions do not add up to
+    /// the statement balance
+    pub fn finish_reconciliation(
+        &mut self,
+        rec: reconcile::Reconciliation,
+        opening: dollar::Dollar,
+    ) -> Result<usize, error::Error> {
+        let summary = self.reconcile_summary(&rec, opening);
+        if !summary.difference.is_zero() {
+            return Err(error::Error::Unbalanced(summary.difference));
+        }
+
+        let mut count = 0;
+        for t in self.transactions.iter_mut().filter(|t| {
+            t.status == transaction::Status::Cleared && reconcile::in_scope(t, rec.account.as_deref(), rec.date)
+        }) {
+            t.status = transaction::Status::Reconciled;
+            count += 1;
+        }
+
+        self.reconciliations.push(rec);
+        Ok(count)
+    }
+
+    /// Every scheduled occurrence that is still waiting for its bank line
+    pub fn scheduled_transactions(&self) -> impl Iterator<Item = &transaction::Transaction> {
+        self.transactions.iter().filter(|t| t.id.is_scheduled())
+    }
+
+    /// Puts `with` in place of the transaction with `id`. The splits, and any
+    /// notes and tags already added, are left as they were. A `Replace` event
+    /// links the two IDs in the ledger.
+    ///
+    /// Returns `Err(UnknownTransaction)` if no vope holds `id`,
+    /// `Err(Reconciled)` if it is locked, and `Err(DuplicateTransaction)` if
+    /// `with` is already assigned
+    pub fn replace_transaction(
+        &mut self,
+        id: &transaction::TransactionId,
+        with: &transaction::Transaction,
+    ) -> Result<(), error::Error> {
+        if self.find_transaction(&with.id).is_some() {
+            return Err(error::Error::DuplicateTransaction(with.id.clone()));
+        }
+
+        let t = self.unlocked_mut(id)?;
+        let notes = std::mem::take(&mut t.notes);
+        let tags = std::mem::take(&mut t.tags);
+        *t = with.clone();
+        if t.notes.is_empty() {
+            t.notes = notes;
         }
+        t.tags = transaction::unique_tags(tags.iter().chain(with.tags.iter()).map(String::as_str));
 
-        // Remove any ignored duplicates
-        list.retain(|t| self.ignored.transactions.contains(&t) == false);
+        for s in self.splits.iter_mut().filter(|s| &s.transaction == id) {
+            s.transaction = with.id.clone();
+        }
+
+        // The Assign event still names the old ID, so say where it went
+        self.record(
+            ledger::today(),
+            ledger::EventKind::Replace {
+                from: id.clone(),
+                to: with.id.clone(),
+            },
+        );
+
+        Ok(())
     }
 
     /// Given a transaction, and a list of names/weights, distributes the
+    /// charge across those vopes. The shares always sum to the exact charge.
     pub fn assign_transaction(
         &mut self,
         names: &[(&str, f32)],
         trans: &transaction::Transaction,
         even_weight: bool,
-    ) -> Result<(), Box<dyn error::Error>> {
-        let mut total_weight = 0.0;
-
-        // Before we start - verify all names, and sum the total weight
-        for (n, w) in names {
-            if self.contains(n) {
-                total_weight += w;
-            } else {
-                return Err(Box::new(std::io::Error::from(ErrorKind::InvalidData)));
-            }
+    ) -> Result<(), error::Error> {
+        if self.find_transaction(&trans.id).is_some() {
+            return Err(error::Error::DuplicateTransaction(trans.id.clone()));
         }
 
-        if even_weight {
-            // Get percentage
-            let weight = 1.0 / (names.len() as f32);
-            let deposit = trans.charge * weight;
-            let delta = deposit * (names.len() as f32) - trans.charge;
-
-            // For now - if I have a rounding error, log it
-            log::debug!("Delta missing! {}", delta);
-
-            for (n, _w) in names {
-                let v = if n.eq_ignore_ascii_case("ignore") {
-                    &mut self.ignored
-                } else {
-                    //This unwrap should be safe, checked above
-                    self.envelopes
-                        .iter_mut()
-                        .find(|v| v.name.eq_ignore_ascii_case(n))
-                        .unwrap()
-                };
-
-                v.actual_amount += deposit;
-                v.transactions.push(trans.clone());
+        // Before we start - verify all names
+        for (n, _w) in names {
+            if !self.contains(n) && !Self::is_ignore(n) {
+                return Err(error::Error::UnknownVope(n.to_string()));
             }
+        }
+
+        // Split the charge exactly, so the shares always add back up to it
+        let weights: Vec<f64> = if even_weight {
+            vec![1.0; names.len()]
         } else {
-            let mut sum = dollar::Dollar::default();
+            names.iter().map(|(_, w)| *w as f64).collect()
+        };
 
-            for (name, w) in names {
-                // Get individuals weight
-                let weight = w / total_weight;
-                let deposit = trans.charge * weight;
-                sum += deposit;
+        let shares = match trans.charge.allocate(&weights) {
+            Some(shares) => shares,
+            None => return Err(error::Error::InvalidWeights),
+        };
 
-                // Can unwrap cause I checked above
-                let v = if name.eq_ignore_ascii_case("ignore") {
-                    &mut self.ignored
-                } else {
-                    self.envelopes
-                        .iter_mut()
-                        .find(|x| &x.name.as_str() == name)
-                        .unwrap()
-                };
-
-                v.actual_amount += deposit;
-                v.transactions.push(trans.clone());
-            }
+        let shares: Vec<(&str, dollar::Dollar)> = names.iter().map(|(n, _)| *n).zip(shares).collect();
+        self.assign_shares(&shares, trans)
+    }
 
-            let delta = sum - trans.charge;
+    /// Assigns a transaction by the exact amount each vope gets, such as the
+    /// splits written in a QIF file. The amounts can have different signs,
+    /// like a purchase with a refund in it, but must add up to the charge.
+    ///
+    /// Returns `Err(DuplicateTransaction)` if it was already assigned,
+    /// `Err(UnknownVope)` if a vope does not exist, and `Err(SplitMismatch)`
+    /// if the amounts do not add up
+    pub fn assign_shares(
+        &mut self,
+        shares: &[(&str, dollar::Dollar)],
+        trans: &transaction::Transaction,
+    ) -> Result<(), error::Error> {
+        if self.find_transaction(&trans.id).is_some() {
+            return Err(error::Error::DuplicateTransaction(trans.id.clone()));
+        }
 
-            // For now - if I have a rounding error, log it
-            log::debug!("Delta missing! {}", delta);
+        let total: dollar::Dollar = shares.iter().map(|(_, a)| *a).sum();
+        if shares.is_empty() || total != trans.charge {
+            return Err(error::Error::SplitMismatch {
+                split: total,
+                charge: trans.charge,
+            });
         }
 
-        // For now, ignore the float part, just assume equal sharing. Hey dummy!
+        let mut split = vec![];
+        for (name, deposit) in shares {
+            let name = if Self::is_ignore(name) { IGNORED } else { name };
+            let v = self
+                .vope_mut(name)
+                .ok_or_else(|| error::Error::UnknownVope(name.to_string()))?;
+            split.push((v.name.clone(), *deposit));
+        }
 
-        self.calc_holdings();
+        self.add_splits(trans, &split);
+        self.record(
+            trans.date,
+            ledger::EventKind::Assign {
+                transaction: trans.id.clone(),
+                shares: split,
+            },
+        );
         Ok(())
     }
 
+    /// Works out how a paycheck of `amount` would be spread, without moving
+    /// anything. See `cash_paycheck`.
+    pub fn plan_paycheck(
+        &self,
+        amount: dollar::Dollar,
+        how: &paycheck::Allocation,
+    ) -> Result<Vec<(String, dollar::Dollar)>, error::Error> {
+        paycheck::plan(self, amount, how)
+    }
+
+    /// Spreads an income transaction across the vopes, and returns how much
+    /// each one got.
     ///
+    /// Whatever the allocation leaves over, rounding included, goes to the
+    /// default vope. Every vope that gets money records the transaction.
     ///
-    pub fn get_vope_history(
-        &self,
-        name: &str,
-    ) -> Result<Vec<transaction::Transaction>, Box<dyn error::Error>> {
-        let op = self.envelopes.iter().find(|v| v.name == name);
-        match op {
-            Some(v) => Ok(v.transactions.clone()),
-            None => Err(Box::new(std::io::Error::from(ErrorKind::InvalidInput))),
+    /// Returns `Err(NotIncome)` if the charge is not positive,
+    /// `Err(DuplicateTransaction)` if it was already assigned, and the errors
+    /// of `plan_paycheck`
+    pub fn cash_paycheck(
+        &mut self,
+        trans: &transaction::Transaction,
+        how: &paycheck::Allocation,
+    ) -> Result<Vec<(String, dollar::Dollar)>, error::Error> {
+        if trans.charge <= dollar::Dollar::ZERO {
+            return Err(error::Error::NotIncome(trans.id.clone()));
         }
+        if self.find_transaction(&trans.id).is_some() {
+            return Err(error::Error::DuplicateTransaction(trans.id.clone()));
+        }
+
+        let shares = self.plan_paycheck(trans.charge, how)?;
+        self.add_splits(trans, &shares);
+
+        self.record(
+            trans.date,
+            ledger::EventKind::Assign {
+                transaction: trans.id.clone(),
+                shares: shares.clone(),
+            },
+        );
+        Ok(shares)
+    }
+
+    /// Returns every transaction that has been assigned to the named vope,
+    /// with the vope's part of each
+    pub fn get_vope_history(&self, name: &str) -> Result<Vec<split::Portion>, error::Error> {
+        if !self.envelopes.iter().any(|v| v.name.eq_ignore_ascii_case(name)) {
+            return Err(error::Error::UnknownVope(name.to_string()));
+        }
+        Ok(self.history(name))
     }
 
+    /// Returns every transaction that was ignored
+    pub fn get_ignored_history(&self) -> Vec<split::Portion> {
+        self.history(IGNORED)
+    }
+
+    /// Adds an empty group to the end of the list
+    ///
+    /// Returns `Err(DuplicateGroup)` if the name is taken
+    pub fn add_group(&mut self, name: &str) -> Result<(), error::Error> {
+        if self.get_group_pos(name).is_some() {
+            return Err(error::Error::DuplicateGroup(name.to_string()));
+        }
+
+        self.groups.push(vope::Group::new(name));
+        Ok(())
+    }
+
+    /// Removes the named group. Its vopes are kept, in no group.
+    ///
+    /// Returns `Err(UnknownGroup)` if there is no group with that name
+    pub fn remove_group(&mut self, name: &str) -> Result<(), error::Error> {
+        let pos = self
+            .get_group_pos(name)
+            .ok_or_else(|| error::Error::UnknownGroup(name.to_string()))?;
+        let group = self.groups.remove(pos);
+
+        for v in self.envelopes.iter_mut().filter(|v| v.group.as_ref() == Some(&group.name)) {
+            v.group = None;
+        }
+
+        Ok(())
+    }
+
+    /// Moves the named group to `index` in the list of groups
+    ///
+    /// Returns `Err(UnknownGroup)` if there is no group with that name
+    pub fn move_group(&mut self, name: &str, index: usize) -> Result<(), error::Error> {
+        let pos = self
+            .get_group_pos(name)
+            .ok_or_else(|| error::Error::UnknownGroup(name.to_string()))?;
+
+        let group = self.groups.remove(pos);
+        self.groups.insert(index.min(self.groups.len()), group);
+        Ok(())
+    }
+
+    /// Folds the named group away to its subtotal, or opens it back up
+    ///
+    /// Returns `Err(UnknownGroup)` if there is no group with that name
+    pub fn set_collapsed(&mut self, name: &str, collapsed: bool) -> Result<(), error::Error> {
+        let pos = self
+            .get_group_pos(name)
+            .ok_or_else(|| error::Error::UnknownGroup(name.to_string()))?;
+        self.groups[pos].collapsed = collapsed;
+        Ok(())
+    }
+
+    /// Lists the vopes of each group by name, groups in their own order and
+    /// the vopes in no group last
     pub fn sort_vope(&mut self) {
-        todo!()
+        let groups = &self.groups;
+        let rank = |v: &vope::Vope| match &v.group {
+            Some(g) => groups.iter().position(|x| &x.name == g).unwrap_or(groups.len()),
+            None => groups.len(),
+        };
+
+        self.envelopes
+            .sort_by_cached_key(|v| (rank(v), v.name.to_ascii_lowercase()));
     }
 
-    pub fn move_vope(&mut self) {
-        todo!()
+    /// Moves the named vope into `group`, or out of every group with `None`,
+    /// and lists it at `index` among that group's vopes
+    ///
+    /// Returns `Err(UnknownVope)` or `Err(UnknownGroup)` if either is missing
+    pub fn move_vope(&mut self, name: &str, group: Option<&str>, index: usize) -> Result<(), error::Error> {
+        let pos = self
+            .get_vope_pos(name)
+            .ok_or_else(|| error::Error::UnknownVope(name.to_string()))?;
+        let group = match group {
+            Some(g) => {
+                let g = self
+                    .get_group_pos(g)
+                    .ok_or_else(|| error::Error::UnknownGroup(g.to_string()))?;
+                Some(self.groups[g].name.clone())
+            }
+            None => None,
+        };
+
+        let mut v = self.envelopes.remove(pos);
+        v.group = group;
+
+        // Before the vope now at `index` in the group, or after its last one
+        let members: Vec<usize> = self
+            .envelopes
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| x.group == v.group)
+            .map(|(i, _)| i)
+            .collect();
+        let at = match (members.get(index), members.last()) {
+            (Some(i), _) => *i,
+            (None, Some(last)) => last + 1,
+            (None, None) => self.envelopes.len(),
+        };
+
+        self.envelopes.insert(at, v);
+        Ok(())
     }
 
     // Helper functions
@@ -246,8 +940,182 @@ impl Portfolio {
     //     self.envelopes.into_iter().find(|x| x.name == name)
     // }
 
+    /*
+     * Any vope with a history by name, the ignored vope included
+     */
+    fn vope_mut(&mut self, name: &str) -> Option<&mut vope::Vope> {
+        self.envelopes
+            .iter_mut()
+            .chain(std::iter::once(&mut self.ignored))
+            .find(|v| v.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Remembers the portfolio as it is, see `Checkpoint`
+    pub(crate) fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            envelopes: self.envelopes.clone(),
+            groups: self.groups.clone(),
+            ignored: self.ignored.clone(),
+            budgeted: self.budgeted,
+            holdings: self.holdings,
+            default_vope: self.default_vope.clone(),
+            period: self.period,
+            reconciliations: self.reconciliations.clone(),
+            transactions: self.transactions.len(),
+            splits: self.splits.len(),
+            events: self.ledger.events().len(),
+            done: None,
+        }
+    }
+
+    /// Marks the change `c` was taken for as made, so undoing it reverses
+    /// only the events it wrote and not any written by later undos
+    pub(crate) fn seal(&self, c: &mut Checkpoint) {
+        c.done = Some(self.ledger.events().len());
+    }
+
+    /// Puts the portfolio back as it was at `c`, dropping the transactions
+    /// and splits appended since, and reversing each ledger event since
+    pub(crate) fn restore(&mut self, c: Checkpoint) {
+        let end = c.done.unwrap_or(self.ledger.events().len());
+        let done: Vec<ledger::Event> = self.ledger.events()[c.events..end].to_vec();
+        for e in done.into_iter().rev() {
+            let budget_at = |name: &str| {
+                c.envelopes
+                    .iter()
+                    .find(|v| v.name.eq_ignore_ascii_case(name))
+                    .map_or(dollar::Dollar::ZERO, |v| v.budget)
+            };
+
+            let reverse = match e.kind {
+                ledger::EventKind::Assign { transaction, shares } => {
+                    ledger::EventKind::Unassign { transaction, shares }
+                }
+                ledger::EventKind::Unassign { transaction, shares } => {
+                    ledger::EventKind::Assign { transaction, shares }
+                }
+                ledger::EventKind::Transfer { from, to, amount } => {
+                    ledger::EventKind::Transfer { from: to, to: from, amount }
+                }
+                ledger::EventKind::CreateVope { name, .. } => ledger::EventKind::DeleteVope { name },
+                ledger::EventKind::DeleteVope { name } => ledger::EventKind::CreateVope {
+                    budget: budget_at(&name),
+                    name,
+                },
+                ledger::EventKind::SetBudget { name, .. } => ledger::EventKind::SetBudget {
+                    budget: budget_at(&name),
+                    name,
+                },
+                other => {
+                    log::warn!("Cannot take back {:?} in the ledger", other);
+                    continue;
+                }
+            };
+
+            // Dated as what it reverses, so balances as of any date are as
+            // if the change was never made
+            self.ledger.push(e.date, reverse);
+        }
+
+        self.envelopes = c.envelopes;
+        self.groups = c.groups;
+        self.ignored = c.ignored;
+        self.budgeted = c.budgeted;
+        self.holdings = c.holdings;
+        self.default_vope = c.default_vope;
+        self.period = c.period;
+        self.reconciliations = c.reconciliations;
+        self.transactions.truncate(c.transactions);
+        self.splits.truncate(c.splits);
+    }
+
+    /*
+     * Writes an event to the ledger, and moves the balances it changes
+     */
+    fn record(&mut self, date: chrono::NaiveDate, kind: ledger::EventKind) {
+        for (name, amount) in kind.effect() {
+            match self.vope_mut(name) {
+                Some(v) => v.actual_amount += amount,
+                None => log::warn!("Ledger names {}, which is not a vope", name),
+            }
+        }
+
+        self.ledger.push(date, kind);
+        self.calc_holdings();
+    }
+
     fn get_vope_pos(&self, name: &str) -> Option<usize> {
-        todo!()
+        self.envelopes
+            .iter()
+            .position(|v| v.name.eq_ignore_ascii_case(name))
+    }
+
+    fn get_group_pos(&self, name: &str) -> Option<usize> {
+        self.groups
+            .iter()
+            .position(|g| g.name.eq_ignore_ascii_case(name))
+    }
+
+    /*
+     * The transaction with `id`, unless a reconciliation locked it
+     */
+    fn unlocked_mut(&mut self, id: &transaction::TransactionId) -> Result<&mut transaction::Transaction, error::Error> {
+        match self.transactions.iter_mut().find(|t| &t.id == id) {
+            Some(t) if t.status == transaction::Status::Reconciled => Err(error::Error::Reconciled(id.clone())),
+            Some(t) => Ok(t),
+            None => Err(error::Error::UnknownTransaction(id.clone())),
+        }
+    }
+
+    /*
+     * Keeps one copy of the transaction, and what each vope got of it
+     */
+    fn add_splits(&mut self, trans: &transaction::Transaction, shares: &[(String, dollar::Dollar)]) {
+        self.transactions.push(trans.clone());
+        for (name, amount) in shares.iter() {
+            self.splits.push(split::Split {
+                transaction: trans.id.clone(),
+                vope: name.clone(),
+                amount: *amount,
+            });
+        }
+    }
+
+    /*
+     * The named vope's part of each transaction, oldest first
+     */
+    fn history(&self, name: &str) -> Vec<split::Portion> {
+        let by_id: collections::HashMap<&transaction::TransactionId, &transaction::Transaction> =
+            self.transactions.iter().map(|t| (&t.id, t)).collect();
+
+        let mut list: Vec<split::Portion> = self
+            .splits
+            .iter()
+            .filter(|s| s.vope.eq_ignore_ascii_case(name))
+            .filter_map(|s| {
+                by_id.get(&s.transaction).map(|t| split::Portion {
+                    transaction: (*t).clone(),
+                    amount: s.amount,
+                })
+            })
+            .collect();
+
+        list.sort_by_key(|p| p.transaction.date);
+        list
+    }
+
+    /*
+     * Every vope that has a balance, including the ignored one
+     */
+    fn histories(&self) -> impl Iterator<Item = &vope::Vope> {
+        self.envelopes.iter().chain(std::iter::once(&self.ignored))
+    }
+
+    /*
+     * The Assign screen sends "Ignore" for transactions that belong to no vope
+     */
+    fn is_ignore(name: &str) -> bool {
+        name.eq_ignore_ascii_case("ignore")
     }
 
     pub(crate) fn calc_holdings(&mut self) {
@@ -256,27 +1124,23 @@ impl Portfolio {
 
         for v in self.envelopes.iter_mut() {
             self.holdings += v.actual_amount;
-            self.budgeted += v.budget;
+            if !v.archived {
+                self.budgeted += v.budget;
+            }
         }
     }
 }
 
-impl ToString for Portfolio {
-    fn to_string(&self) -> String {
-        let mut s = String::new();
-
-        s.push_str(&format!(
-            "Budgeted: {}\n\
-                                    Holdings: {}\n",
-            self.budgeted, self.holdings
-        ));
+impl fmt::Display for Portfolio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Budgeted: {}", self.budgeted)?;
+        writeln!(f, "Holdings: {}", self.holdings)?;
 
         for v in &self.envelopes {
-            let temp = format!("  {} | {} | {}\n", v.name, v.actual_amount, v.budget);
-            s.push_str(&temp);
+            writeln!(f, "  {} | {} | {}", v.name, v.actual_amount, v.budget)?;
         }
 
-        s
+        Ok(())
     }
 }
 
@@ -406,41 +1270,3 @@ impl ToString for Portfolio {
 //     Ok(trans)
 // }
 
-// fn cash_paycheck(&mut self, amount: dollar::Dollar) -> Result<(), Box<dyn error::Error>> {
-//     // Keep a running total of how much we add to even it out in the end
-//     let mut sum_added = dollar::Dollar::from(0.0);
-
-//     // A salary is divided by each
-//     for v in self.envelopes.iter_mut() {
-//         if v.name == self.default.0 {
-//             // Ignore default - handle later
-//         } else {
-//             let percent_of_budget = v.budget / self.budgeted;
-//             let dollar_amount = amount * percent_of_budget;
-//             v.actual_amount += dollar_amount;
-//             sum_added += dollar_amount;
-
-//             // println!("name: {} | percent: {} | dollar_amount: {}", v.name, percent_of_budget, dollar_amount)
-//         }
-//     }
-
-//     // Update default
-//     let op_default = self
-//         .accounts
-//         .iter_mut()
-//         .find(|v| v.name.eq_ignore_ascii_case(&self.default.0))
-//         .unwrap();
-
-//     let default_percent = op_default.budget / self.budgeted;
-//     // Make sure to incorperate the diff
-//     let default_added = amount * default_percent;
-//     sum_added += default_added;
-
-//     op_default.actual_amount += default_added + (amount - sum_added);
-
-//     // println!("Total amount added: {} | default percent {}", sum_added, default_percent);
-
-//     self.calc_holdings();
-
-//     Ok(())
-// }
@@ -0,0 +1,240 @@
+use crate::{dollar, error, misc, portfolio, split, transaction};
+use chrono::NaiveDate;
+use std::{fmt::Write, fs, path};
+
+/// One transaction read from a QIF file, with the category it was filed under
+#[derive(Clone, Debug)]
+pub struct QifTransaction {
+    pub transaction: transaction::Transaction,
+    /// The L line, if there was one
+    pub category: Option<String>,
+    /// The S/E/$ lines of a split transaction, empty if it was not split
+    pub splits: Vec<QifSplit>,
+}
+
+/// One line of a split transaction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QifSplit {
+    pub category: String,
+    pub memo: Option<String>,
+    pub amount: dollar::Dollar,
+}
+
+impl QifTransaction {
+    /// The names and exact amounts to hand to `Portfolio::assign_shares`.
+    ///
+    /// A split transaction gives each split's category its own amount, sign
+    /// and all. Otherwise the category gets the whole charge. Transfers,
+    /// written as `[Account]`, move money between bank accounts rather than
+    /// spending it, so they go to Ignore. Empty if there is no category.
+    pub fn assignment(&self) -> Vec<(String, dollar::Dollar)> {
+        let name = |c: &str| if c.starts_with('[') { "Ignore".to_string() } else { c.to_string() };
+
+        let lines: Vec<(String, dollar::Dollar)> = if self.splits.is_empty() {
+            self.category.iter().map(|c| (name(c), self.transaction.charge)).collect()
+        } else {
+            self.splits.iter().map(|s| (name(&s.category), s.amount)).collect()
+        };
+
+        // A category can be split to more than once
+        let mut shares: Vec<(String, dollar::Dollar)> = vec![];
+        for (n, amount) in lines {
+            match shares.iter_mut().find(|(s, _)| s.eq_ignore_ascii_case(&n)) {
+                Some((_, a)) => *a += amount,
+                None => shares.push((n, amount)),
+            }
+        }
+        shares
+    }
+}
+
+/// Reads the QIF file at `path`
+pub fn parse(path: &path::Path) -> Result<Vec<QifTransaction>, error::Error> {
+    let raw = fs::read(path).map_err(|e| error::Error::io(path, e))?;
+    parse_str(&String::from_utf8_lossy(&raw))
+}
+
+/// Reads every bank, cash and credit card transaction in a QIF document.
+///
+/// Other sections, such as category lists and investments, are skipped.
+pub fn parse_str(s: &str) -> Result<Vec<QifTransaction>, error::Error> {
+    let mut list = vec![];
+    let mut in_register = false;
+    let mut entry = Entry::default();
+
+    for (i, raw_line) in s.lines().enumerate() {
+        let line = i + 1;
+        let text = raw_line.trim_end();
+
+        if text.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = text.strip_prefix('!') {
+            let header = header.to_ascii_lowercase();
+            in_register = ["type:bank", "type:cash", "type:ccard", "type:oth a", "type:oth l"]
+                .iter()
+                .any(|t| header.starts_with(t));
+            entry = Entry::default();
+            continue;
+        }
+
+        if !in_register {
+            continue;
+        }
+
+        let (code, value) = text.split_at(text.chars().next().map_or(0, char::len_utf8));
+        let value = value.trim();
+
+        match code {
+            "D" => entry.date = Some(parse_date(value).ok_or(error::Error::Parse { line, column: 2 })?),
+            "T" | "U" => entry.amount = Some(parse_amount(value, line)?),
+            "P" => entry.payee = Some(value.to_string()),
+            "M" => entry.memo = Some(value.to_string()),
+            "L" => entry.category = Some(value.to_string()),
+            "S" => entry.splits.push(QifSplit {
+                category: value.to_string(),
+                memo: None,
+                amount: dollar::Dollar::ZERO,
+            }),
+            "E" => {
+                if let Some(split) = entry.splits.last_mut() {
+                    split.memo = Some(value.to_string());
+                }
+            }
+            "$" => {
+                if let Some(split) = entry.splits.last_mut() {
+                    split.amount = parse_amount(value, line)?;
+                }
+            }
+            "^" => {
+                let e = std::mem::take(&mut entry);
+                list.push(e.finish(line)?);
+            }
+            // Check numbers, cleared flags, addresses and the like
+            _ => {}
+        }
+    }
+
+    let mut trans: Vec<transaction::Transaction> =
+        list.iter().map(|q| q.transaction.clone()).collect();
+    transaction::number_occurrences(&mut trans);
+    for (q, t) in list.iter_mut().zip(trans) {
+        q.transaction = t;
+    }
+
+    Ok(list)
+}
+
+/// Writes one vope's history as a QIF bank register, each line filed under
+/// the vope for the vope's part of the charge
+pub fn write_vope(name: &str, history: &[split::Portion]) -> String {
+    let mut s = String::from("!Type:Bank\n");
+
+    for p in history.iter() {
+        // Writing to a String cannot fail
+        let _ = write!(
+            s,
+            "D{}\nT{}\nP{}\n",
+            p.transaction.date.format("%m/%d/%Y"),
+            p.amount.to_decimal_string(),
+            p.transaction.desc,
+        );
+        if let Some(memo) = &p.transaction.memo {
+            let _ = writeln!(s, "M{}", memo);
+        }
+        let _ = write!(s, "L{}\n^\n", name);
+    }
+
+    s
+}
+
+/// Writes every vope, and the ignored transactions, as a QIF file.
+///
+/// Each vope becomes its own account so the histories stay apart when the
+/// file is imported elsewhere.
+pub fn write_portfolio(p: &portfolio::Portfolio) -> String {
+    let mut s = String::new();
+
+    for v in p.view_vopes().iter() {
+        let _ = write!(s, "!Account\nN{}\nTBank\n^\n", v.name);
+        s.push_str(&write_vope(&v.name, &p.get_vope_history(&v.name).unwrap_or_default()));
+    }
+
+    let ignored = p.view_ignored();
+    let _ = write!(s, "!Account\nN{}\nTBank\n^\n", ignored.name);
+    s.push_str(&write_vope(&ignored.name, &p.get_ignored_history()));
+
+    s
+}
+
+/// Saves `write_portfolio` to `path`
+pub fn export(p: &portfolio::Portfolio, path: &path::Path) -> Result<(), error::Error> {
+    misc::write_atomic(path, write_portfolio(p).as_bytes()).map_err(|e| error::Error::io(path, e))
+}
+
+/*
+ * The lines of the transaction being read
+ */
+#[derive(Default)]
+struct Entry {
+    date: Option<NaiveDate>,
+    amount: Option<dollar::Dollar>,
+    payee: Option<String>,
+    memo: Option<String>,
+    category: Option<String>,
+    splits: Vec<QifSplit>,
+}
+
+impl Entry {
+    fn finish(self, line: usize) -> Result<QifTransaction, error::Error> {
+        let date = self.date.ok_or(error::Error::Parse { line, column: 1 })?;
+
+        // Some tools leave out T on split transactions
+        let amount = match self.amount {
+            Some(a) => a,
+            None if !self.splits.is_empty() => self.splits.iter().map(|s| s.amount).sum(),
+            None => return Err(error::Error::Parse { line, column: 1 }),
+        };
+
+        let desc = self
+            .payee
+            .clone()
+            .or(self.memo.clone())
+            .unwrap_or_else(|| "Unknown".to_string());
+
+        Ok(QifTransaction {
+            transaction: transaction::Transaction::new(date, desc, amount)
+                .with_payee_memo(self.payee.as_deref(), self.memo.as_deref()),
+            category: self.category,
+            splits: self.splits,
+        })
+    }
+}
+
+/*
+ * Quicken writes dates many ways: 07/01/2023, 7/ 1/23, 7/1'23, 2023-07-01
+ */
+fn parse_date(s: &str) -> Option<NaiveDate> {
+    let s: String = s
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| if c == '\'' { '/' } else { c })
+        .collect();
+
+    // chrono would read a two digit year as the first century
+    let short_year = s.rsplit('/').next().is_some_and(|y| y.len() == 2);
+    let formats: &[&str] = if short_year {
+        &["%m/%d/%y"]
+    } else {
+        &["%m/%d/%Y", "%Y-%m-%d", "%d.%m.%Y"]
+    };
+
+    formats
+        .iter()
+        .find_map(|f| NaiveDate::parse_from_str(&s, f).ok())
+}
+
+fn parse_amount(s: &str, line: usize) -> Result<dollar::Dollar, error::Error> {
+    s.parse().map_err(|_| error::Error::Parse { line, column: 2 })
+}
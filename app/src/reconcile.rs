@@ -0,0 +1,57 @@
+use crate::{dollar::Dollar, transaction};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// A bank statement that was matched against the transactions
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Reconciliation {
+    /// The funding account the statement was for, or `None` for all of them
+    pub account: Option<String>,
+    /// The statement's end date
+    pub date: NaiveDate,
+    /// The balance the statement gave
+    pub balance: Dollar,
+}
+
+/// How far the cleared transactions are from a statement
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Summary {
+    /// What the account held before any transaction
+    pub opening: Dollar,
+    /// The opening balance plus every cleared or reconciled transaction up
+    /// to the statement's end date
+    pub cleared: Dollar,
+    pub statement: Dollar,
+    /// `statement - cleared`, zero once everything is ticked off
+    pub difference: Dollar,
+}
+
+/// True if `t` is on the statement for `account`, or any statement if there
+/// is no account
+pub fn in_scope(t: &transaction::Transaction, account: Option<&str>, date: NaiveDate) -> bool {
+    t.date <= date
+        && account.is_none_or(|a| t.source.as_deref().is_some_and(|s| s.eq_ignore_ascii_case(a)))
+}
+
+/// Compares the cleared transactions in `list` with a statement
+pub fn summarize(
+    list: &[transaction::Transaction],
+    account: Option<&str>,
+    opening: Dollar,
+    date: NaiveDate,
+    statement: Dollar,
+) -> Summary {
+    let cleared = opening
+        + list
+            .iter()
+            .filter(|t| t.status != transaction::Status::Uncleared && in_scope(t, account, date))
+            .map(|t| t.charge)
+            .sum::<Dollar>();
+
+    Summary {
+        opening,
+        cleared,
+        statement,
+        difference: statement - cleared,
+    }
+}
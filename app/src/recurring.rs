@@ -0,0 +1,230 @@
+use crate::{dollar::Dollar, error, ledger, portfolio, rules, transaction};
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// How many days a bank line may be from the scheduled date and still match it
+pub const MATCH_DAYS: i64 = 4;
+
+/// How often a recurring transaction happens
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Biweekly,
+    /// On the start date's day of the month, or the last day of shorter months
+    Monthly,
+    Yearly,
+}
+
+/// A transaction that happens on a fixed schedule, such as rent or a paycheck.
+///
+/// Each occurrence is assigned to the target vopes when it comes due, and
+/// swapped for the real bank line once a statement with it is imported.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Recurring {
+    pub name: String,
+    pub desc: String,
+    pub amount: Dollar,
+    pub frequency: Frequency,
+    /// The date of the first occurrence
+    pub start: NaiveDate,
+    /// No occurrences after this date
+    pub end: Option<NaiveDate>,
+    pub target: rules::Target,
+    /// The date of the last occurrence that was assigned
+    pub last_posted: Option<NaiveDate>,
+}
+
+impl Recurring {
+    /// The date of occurrence `n`, counting the start date as 0.
+    ///
+    /// Always counted from the start date, so a schedule starting on the 31st
+    /// goes back to the 31st after a short month.
+    pub fn occurrence(&self, n: u32) -> Option<NaiveDate> {
+        let date = match self.frequency {
+            Frequency::Daily => self.start.checked_add_signed(Duration::days(n as i64))?,
+            Frequency::Weekly => self.start.checked_add_signed(Duration::weeks(n as i64))?,
+            Frequency::Biweekly => self.start.checked_add_signed(Duration::weeks(2 * n as i64))?,
+            Frequency::Monthly => add_months(self.start, n)?,
+            Frequency::Yearly => add_months(self.start, n.checked_mul(12)?)?,
+        };
+
+        match self.end {
+            Some(end) if date > end => None,
+            _ => Some(date),
+        }
+    }
+
+    /// Every occurrence after `last_posted`, up to and including `today`
+    pub fn due(&self, today: NaiveDate) -> Vec<NaiveDate> {
+        (0..)
+            .map_while(|n| self.occurrence(n))
+            .take_while(|d| *d <= today)
+            .filter(|d| self.last_posted.is_none_or(|last| *d > last))
+            .collect()
+    }
+
+    /// The transaction for the occurrence on `date`
+    pub fn instance(&self, date: NaiveDate) -> transaction::Transaction {
+        transaction::Transaction {
+            id: transaction::TransactionId::scheduled(&self.name, date),
+            date,
+            desc: self.desc.clone(),
+            charge: self.amount,
+            source: None,
+            status: transaction::Status::Uncleared,
+            payee: None,
+            memo: None,
+            notes: String::new(),
+            tags: vec![],
+        }
+    }
+}
+
+/// Assigns every occurrence that has come due by `today`, and returns them.
+///
+/// An occurrence whose bank line is already assigned, such as one imported
+/// before the scheduled date, is passed over rather than counted twice. Each
+/// line stands in for one occurrence at most. An
+/// occurrence that cannot be assigned, because a target vope is gone, stops
+/// its schedule until the template is fixed.
+pub fn post_due(
+    port: &mut portfolio::Portfolio,
+    list: &mut [Recurring],
+    today: NaiveDate,
+) -> Vec<transaction::Transaction> {
+    let mut posted = vec![];
+    // Lines that already stand in for an occurrence in this pass
+    let mut claimed: HashSet<transaction::TransactionId> = HashSet::new();
+
+    for r in list.iter_mut() {
+        for date in r.due(today) {
+            let t = r.instance(date);
+
+            if let Some(line) = already_assigned(port, &t, &claimed) {
+                log::info!("{} for {} was already assigned as {}", r.name, date, line);
+                claimed.insert(line);
+                r.last_posted = Some(date);
+                continue;
+            }
+
+            match port.assign_transaction(&r.target.assignment(), &t, false) {
+                // Already there, the schedule was edited and posted again
+                Ok(_) | Err(error::Error::DuplicateTransaction(_)) => {
+                    r.last_posted = Some(date);
+                    posted.push(t);
+                }
+                Err(e) => {
+                    log::warn!("Could not post {} for {}: {}", r.name, date, e);
+                    break;
+                }
+            }
+        }
+    }
+
+    posted
+}
+
+/// Takes every line of `list` that is a scheduled transaction already posted,
+/// and puts the bank's version in its place.
+///
+/// A line matches a posted occurrence with the same amount and description,
+/// dated on the scheduled day or up to `MATCH_DAYS` either side of it. Returns the
+/// replaced occurrence IDs, with the line that replaced each.
+pub fn match_imported(
+    port: &mut portfolio::Portfolio,
+    list: &mut Vec<transaction::Transaction>,
+) -> Vec<(transaction::TransactionId, transaction::Transaction)> {
+    let mut matched = vec![];
+
+    list.retain(|t| {
+        let found = port
+            .scheduled_transactions()
+            .filter(|s| is_bank_line_for(s, t))
+            .min_by_key(|s| (s.date - t.date).num_days().abs())
+            .map(|s| s.id.clone());
+
+        match found {
+            Some(id) => match port.replace_transaction(&id, t) {
+                Ok(_) => {
+                    matched.push((id, t.clone()));
+                    false
+                }
+                Err(e) => {
+                    log::warn!("Could not match {} to {}: {}", t, id, e);
+                    true
+                }
+            },
+            None => true,
+        }
+    });
+
+    matched
+}
+
+/*
+ * An assigned bank line that could be `occurrence`, and is not already
+ * standing in for another occurrence, whether matched before or `claimed`
+ * earlier in this pass
+ */
+fn already_assigned(
+    port: &portfolio::Portfolio,
+    occurrence: &transaction::Transaction,
+    claimed: &HashSet<transaction::TransactionId>,
+) -> Option<transaction::TransactionId> {
+    let replacements: HashSet<&transaction::TransactionId> = port
+        .view_ledger()
+        .events()
+        .iter()
+        .filter_map(|e| match &e.kind {
+            ledger::EventKind::Replace { to, .. } => Some(to),
+            _ => None,
+        })
+        .collect();
+
+    port.transactions()
+        .iter()
+        .filter(|t| !t.id.is_scheduled() && !replacements.contains(&t.id) && !claimed.contains(&t.id))
+        .find(|t| is_bank_line_for(occurrence, t))
+        .map(|t| t.id.clone())
+}
+
+/*
+ * Whether `line` could be the bank's version of `occurrence`: the same
+ * amount, within `MATCH_DAYS` of it, and a description or payee that
+ * contains the occurrence's, or is contained in it, ignoring case and
+ * punctuation
+ */
+fn is_bank_line_for(occurrence: &transaction::Transaction, line: &transaction::Transaction) -> bool {
+    let squash = |s: &str| -> String {
+        s.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+
+    let want = squash(&occurrence.desc);
+    let named = !want.is_empty()
+        && std::iter::once(&line.desc)
+            .chain(line.payee.as_ref())
+            .map(|s| squash(s))
+            .any(|have| !have.is_empty() && (have.contains(&want) || want.contains(&have)));
+
+    named
+        && line.charge == occurrence.charge
+        && (line.date - occurrence.date).num_days().abs() <= MATCH_DAYS
+}
+
+/*
+ * Moves `n` months on, keeping the day of the month where the month has it
+ */
+fn add_months(date: NaiveDate, n: u32) -> Option<NaiveDate> {
+    let months = date.year() as i64 * 12 + date.month0() as i64 + n as i64;
+    let year = i32::try_from(months.div_euclid(12)).ok()?;
+    let month = months.rem_euclid(12) as u32 + 1;
+
+    (1..=date.day())
+        .rev()
+        .find_map(|d| NaiveDate::from_ymd_opt(year, month, d))
+}
@@ -0,0 +1,104 @@
+use crate::{budget, dollar::Dollar, portfolio, transaction};
+use chrono::NaiveDate;
+use std::collections;
+
+/// Money in and out of the vopes during one period
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PeriodTotals {
+    pub start: NaiveDate,
+    /// The first day of the next period
+    pub end: NaiveDate,
+    /// Every positive share, as a positive amount
+    pub income: Dollar,
+    /// Every negative share, as a positive amount
+    pub expense: Dollar,
+    /// What each vope spent, as a positive amount, in the order of `view_vopes`
+    pub spent: Vec<(String, Dollar)>,
+}
+
+impl PeriodTotals {
+    /// `income - expense`
+    pub fn net(&self) -> Dollar {
+        self.income - self.expense
+    }
+}
+
+/// One vope's budget against what it actually spent in a period
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Variance {
+    pub vope: String,
+    pub budgeted: Dollar,
+    pub spent: Dollar,
+    /// `budgeted - spent`, negative when the vope went over
+    pub variance: Dollar,
+}
+
+/// Adds up every split by period, for each period from the one containing
+/// `from` to the one containing `to`.
+///
+/// Ignored transactions are left out, so transfers between the user's own
+/// accounts are neither income nor expense. Only the vope's part of a split
+/// transaction counts towards it.
+pub fn by_period(
+    port: &portfolio::Portfolio,
+    period: &budget::Period,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<PeriodTotals> {
+    let dates: collections::HashMap<&transaction::TransactionId, NaiveDate> =
+        port.transactions().iter().map(|t| (&t.id, t.date)).collect();
+    let ignored = &port.view_ignored().name;
+
+    let mut list = vec![];
+    let mut start = period.start_of(from);
+
+    while start <= to {
+        let end = period.next(start);
+        let mut totals = PeriodTotals {
+            start,
+            end,
+            income: Dollar::ZERO,
+            expense: Dollar::ZERO,
+            spent: port
+                .view_vopes()
+                .iter()
+                .map(|v| (v.name.clone(), Dollar::ZERO))
+                .collect(),
+        };
+
+        let splits = port.view_splits().iter().filter(|s| {
+            !s.vope.eq_ignore_ascii_case(ignored)
+                && dates.get(&s.transaction).is_some_and(|d| *d >= start && *d < end)
+        });
+
+        for s in splits {
+            if s.amount.is_negative() {
+                totals.expense -= s.amount;
+                if let Some((_, spent)) = totals.spent.iter_mut().find(|(n, _)| n.eq_ignore_ascii_case(&s.vope)) {
+                    *spent -= s.amount;
+                }
+            } else {
+                totals.income += s.amount;
+            }
+        }
+
+        list.push(totals);
+        start = end;
+    }
+
+    list
+}
+
+/// Each vope's budget against its spending in the budget period containing
+/// `date`, see `portfolio::Portfolio::period_report`
+pub fn variance(port: &portfolio::Portfolio, date: NaiveDate) -> Vec<Variance> {
+    port.period_report(date)
+        .into_iter()
+        .map(|(vope, s)| Variance {
+            vope,
+            budgeted: s.budgeted,
+            spent: s.spent,
+            variance: s.budgeted - s.spent,
+        })
+        .collect()
+}
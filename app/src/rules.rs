@@ -0,0 +1,180 @@
+use crate::{dollar, error, journal, portfolio, transaction};
+use chrono::Datelike;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+/// Re-exported so callers can build a `DatePattern::Weekdays`
+pub use chrono::Weekday;
+
+/// Where a rule sends the transactions it matches
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Target {
+    /// Split across these vopes by weight
+    Vopes(Vec<(String, f32)>),
+    /// File under the ignored vope
+    Ignore,
+}
+
+impl Target {
+    /// The names and weights to hand to `Portfolio::assign_transaction`
+    pub fn assignment(&self) -> Vec<(&str, f32)> {
+        match self {
+            Target::Vopes(list) => list.iter().map(|(n, w)| (n.as_str(), *w)).collect(),
+            Target::Ignore => vec![("Ignore", 1.0)],
+        }
+    }
+
+    /// Sends what went to the vope `from` to `to` instead. If `to` was
+    /// already in the list, the weights are added together.
+    pub fn rename(&mut self, from: &str, to: &str) {
+        if let Target::Vopes(list) = self {
+            let mut merged: Vec<(String, f32)> = vec![];
+            for (n, w) in list.drain(..) {
+                let n = if n.eq_ignore_ascii_case(from) { to.to_string() } else { n };
+                match merged.iter_mut().find(|(m, _)| m.eq_ignore_ascii_case(&n)) {
+                    Some((_, total)) => *total += w,
+                    None => merged.push((n, w)),
+                }
+            }
+            *list = merged;
+        }
+    }
+}
+
+/// Which dates a rule matches
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum DatePattern {
+    /// Between these days of the month, inclusive
+    DayOfMonth { first: u32, last: u32 },
+    /// On any of these days of the week
+    Weekdays(Vec<Weekday>),
+}
+
+/// Files imported transactions automatically.
+///
+/// A transaction matches when every condition that is set holds. Amounts
+/// are compared with their sign, so spending $5 to $20 is `-20..=-5`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Rule {
+    pub name: String,
+    /// A regex searched for in the description, ignoring case
+    pub desc_pattern: Option<String>,
+    pub min_amount: Option<dollar::Dollar>,
+    pub max_amount: Option<dollar::Dollar>,
+    pub date_pattern: Option<DatePattern>,
+    pub target: Target,
+}
+
+impl Rule {
+    /// Checks the rule can be used, the pattern must be a valid regex
+    pub fn validate(&self) -> Result<(), error::Error> {
+        self.compile().map(|_| ())
+    }
+
+    fn compile(&self) -> Result<Option<Regex>, error::Error> {
+        self.desc_pattern
+            .as_ref()
+            .map(|p| {
+                RegexBuilder::new(p)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|_| error::Error::InvalidRule(self.name.clone()))
+            })
+            .transpose()
+    }
+
+    fn matches(&self, desc: Option<&Regex>, t: &transaction::Transaction) -> bool {
+        let date_ok = match &self.date_pattern {
+            Some(DatePattern::DayOfMonth { first, last }) => (*first..=*last).contains(&t.date.day()),
+            Some(DatePattern::Weekdays(days)) => days.contains(&t.date.weekday()),
+            None => true,
+        };
+
+        desc.is_none_or(|re| re.is_match(&t.desc))
+            && self.min_amount.is_none_or(|min| t.charge >= min)
+            && self.max_amount.is_none_or(|max| t.charge <= max)
+            && date_ok
+    }
+
+    /// The names and weights to hand to `Portfolio::assign_transaction`
+    pub fn assignment(&self) -> Vec<(&str, f32)> {
+        self.target.assignment()
+    }
+}
+
+/// A list of rules ready to be matched, the first matching rule wins
+pub struct Matcher<'a> {
+    rules: Vec<(&'a Rule, Option<Regex>)>,
+}
+
+impl<'a> Matcher<'a> {
+    /// Returns `Err(InvalidRule)` if any rule has a bad pattern
+    pub fn new(rules: &'a [Rule]) -> Result<Matcher<'a>, error::Error> {
+        let rules = rules
+            .iter()
+            .map(|r| r.compile().map(|re| (r, re)))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Matcher { rules })
+    }
+
+    pub fn find(&self, t: &transaction::Transaction) -> Option<&'a Rule> {
+        self.rules
+            .iter()
+            .find(|(r, re)| r.matches(re.as_ref(), t))
+            .map(|(r, _)| *r)
+    }
+}
+
+/// A transaction a rule filed on import
+#[derive(Clone, Debug)]
+pub struct AutoAssigned {
+    pub transaction: transaction::Transaction,
+    pub rule: String,
+    pub target: Target,
+}
+
+/// Assigns every transaction in `list` that a rule matches, and removes it
+/// from the list. What is left still needs to be assigned by hand. Each
+/// assignment goes through `journal`, so it can be undone like any other.
+///
+/// A rule naming a vope that no longer exists is skipped, leaving its
+/// transactions in the list.
+pub fn auto_assign(
+    port: &mut portfolio::Portfolio,
+    journal: &mut journal::Journal,
+    rules: &[Rule],
+    list: &mut Vec<transaction::Transaction>,
+) -> Result<Vec<AutoAssigned>, error::Error> {
+    let matcher = Matcher::new(rules)?;
+    let mut done = vec![];
+
+    list.retain(|t| {
+        let rule = match matcher.find(t) {
+            Some(rule) => rule,
+            None => return true,
+        };
+
+        let command = journal::Command::Assign {
+            names: rule.assignment().into_iter().map(|(n, w)| (n.to_string(), w)).collect(),
+            transaction: t.clone(),
+            even_weight: false,
+        };
+        match journal.execute(port, command) {
+            Ok(_) => {
+                done.push(AutoAssigned {
+                    transaction: t.clone(),
+                    rule: rule.name.clone(),
+                    target: rule.target.clone(),
+                });
+                false
+            }
+            Err(e) => {
+                log::warn!("Rule {} could not assign {}: {}", rule.name, t, e);
+                true
+            }
+        }
+    });
+
+    Ok(done)
+}
@@ -0,0 +1,20 @@
+use crate::{dollar::Dollar, transaction};
+use serde::{Deserialize, Serialize};
+
+/// The part of one transaction that was assigned to one vope.
+///
+/// The splits of a transaction always add up to its charge.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Split {
+    pub transaction: transaction::TransactionId,
+    pub vope: String,
+    pub amount: Dollar,
+}
+
+/// One line of a vope's history: the whole transaction, and how much of it
+/// the vope got
+#[derive(Clone, Debug, PartialEq)]
+pub struct Portion {
+    pub transaction: transaction::Transaction,
+    pub amount: Dollar,
+}
@@ -0,0 +1,145 @@
+use crate::{portfolio, split, transaction};
+use std::collections;
+
+/// A vope a transaction probably belongs in
+#[derive(Clone, Debug, PartialEq)]
+pub struct Suggestion {
+    pub vope: String,
+    /// How sure the classifier is, from 0 to 1. All suggestions for one
+    /// transaction add up to 1.
+    pub confidence: f64,
+}
+
+/// Guesses where a transaction goes from what was assigned before.
+///
+/// A naive Bayes classifier: every vope's history is the training data for
+/// that vope, and a transaction is described by the words in its description
+/// plus a token for the rough size of the charge. Nothing leaves the machine,
+/// it is rebuilt from the portfolio whenever it is needed.
+#[derive(Clone, Debug, Default)]
+pub struct Classifier {
+    classes: Vec<Class>,
+    vocabulary: collections::HashSet<String>,
+    documents: usize,
+}
+
+/*
+ * What was learned about one vope
+ */
+#[derive(Clone, Debug)]
+struct Class {
+    name: String,
+    documents: usize,
+    tokens: collections::HashMap<String, usize>,
+    total_tokens: usize,
+}
+
+impl Classifier {
+    /// Learns from every vope's history. Ignored transactions are learned
+    /// under "Ignore", the name the Assign screen uses.
+    pub fn train(port: &portfolio::Portfolio) -> Classifier {
+        let mut c = Classifier::default();
+
+        for v in port.view_vopes() {
+            c.learn(&v.name, &port.get_vope_history(&v.name).unwrap_or_default());
+        }
+        c.learn("Ignore", &port.get_ignored_history());
+
+        c
+    }
+
+    fn learn(&mut self, name: &str, history: &[split::Portion]) {
+        if history.is_empty() {
+            return;
+        }
+
+        let mut class = Class {
+            name: name.to_string(),
+            documents: history.len(),
+            tokens: collections::HashMap::new(),
+            total_tokens: 0,
+        };
+
+        for p in history.iter() {
+            for token in tokens(&p.transaction) {
+                *class.tokens.entry(token.clone()).or_insert(0) += 1;
+                class.total_tokens += 1;
+                self.vocabulary.insert(token);
+            }
+        }
+
+        self.documents += class.documents;
+        self.classes.push(class);
+    }
+
+    /// Every vope with history, most likely first. Empty if nothing has been
+    /// assigned yet.
+    pub fn rank(&self, t: &transaction::Transaction) -> Vec<Suggestion> {
+        // Words never seen before say nothing about any vope
+        let known: Vec<String> = tokens(t)
+            .into_iter()
+            .filter(|tok| self.vocabulary.contains(tok))
+            .collect();
+
+        let vocab = self.vocabulary.len() as f64;
+        let scores: Vec<f64> = self
+            .classes
+            .iter()
+            .map(|c| {
+                let prior = (c.documents as f64 / self.documents as f64).ln();
+                let denom = c.total_tokens as f64 + vocab;
+
+                // Laplace smoothing, so one unseen word does not rule a vope out
+                prior
+                    + known
+                        .iter()
+                        .map(|tok| {
+                            let count = c.tokens.get(tok).copied().unwrap_or(0) as f64;
+                            ((count + 1.0) / denom).ln()
+                        })
+                        .sum::<f64>()
+            })
+            .collect();
+
+        // Turn the log scores into probabilities without overflowing
+        let max = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let weights: Vec<f64> = scores.iter().map(|s| (s - max).exp()).collect();
+        let total: f64 = weights.iter().sum();
+
+        let mut ranked: Vec<Suggestion> = self
+            .classes
+            .iter()
+            .zip(weights)
+            .map(|(c, w)| Suggestion {
+                vope: c.name.clone(),
+                confidence: w / total,
+            })
+            .collect();
+
+        ranked.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        ranked
+    }
+}
+
+/*
+ * The words of the description, and the size of the charge.
+ *
+ * Numbers are dropped since store numbers and dates change every time.
+ * Amounts are bucketed by order of magnitude, with the sign kept, so $4
+ * and $6 coffees look alike but rent does not.
+ */
+fn tokens(t: &transaction::Transaction) -> Vec<String> {
+    let mut list: Vec<String> = t
+        .desc
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.len() > 1 && !w.chars().all(|c| c.is_ascii_digit()))
+        .map(str::to_lowercase)
+        .collect();
+
+    let cents = t.charge.cents();
+    let bucket = cents.unsigned_abs().checked_ilog10().unwrap_or(0);
+    let sign = if cents < 0 { "-" } else { "+" };
+    list.push(format!("#amount{}{}", sign, bucket));
+
+    list
+}
@@ -3,4 +3,1372 @@ fn it_works() {
     if let Ok(_) = crate::Account::new()  {
         
     }
-}
\ No newline at end of file
+}
+#[test]
+fn dollar_parses_exactly() {
+    use crate::dollar::Dollar;
+
+    assert_eq!("$1,234.56".parse::<Dollar>(), Ok(Dollar::from_cents(123_456)));
+    assert_eq!("-0.1".parse::<Dollar>(), Ok(Dollar::from_cents(-10)));
+    assert_eq!("$-19.999".parse::<Dollar>(), Ok(Dollar::from_cents(-2000)));
+    assert!("12a".parse::<Dollar>().is_err());
+    assert_eq!(Dollar::from_cents(-5).to_string(), "$-0.05");
+}
+
+#[test]
+fn dollar_allocate_is_exact() {
+    use crate::dollar::Dollar;
+
+    let shares = Dollar::from_cents(-1000).split_evenly(3).unwrap();
+    assert_eq!(shares.iter().sum::<Dollar>(), Dollar::from_cents(-1000));
+    assert_eq!(shares[0], Dollar::from_cents(-334));
+
+    let shares = Dollar::from_cents(10_000_001).allocate(&[1.0, 2.0, 0.0]).unwrap();
+    assert_eq!(shares.iter().sum::<Dollar>(), Dollar::from_cents(10_000_001));
+    assert_eq!(shares[2], Dollar::ZERO);
+
+    assert!(Dollar::from_cents(100).allocate(&[0.0]).is_none());
+
+    // Far past where a float can count every cent, the shares still add up
+    for total in [i64::MAX, i64::MIN, (1 << 53) + 1] {
+        let shares = Dollar::from_cents(total).allocate(&[1.0, 1.0, 1.0]).unwrap();
+        assert_eq!(shares.iter().map(|s| s.cents() as i128).sum::<i128>(), total as i128);
+        assert!(shares.iter().all(|s| (s.cents() as i128 - total as i128 / 3).abs() <= 1));
+    }
+}
+
+#[test]
+fn dollar_loads_legacy_floats() {
+    use crate::dollar::Dollar;
+
+    let legacy: Dollar = serde_json::from_str(r#"{"amount":16777.22}"#).unwrap();
+    assert_eq!(legacy, Dollar::from_cents(1_677_722));
+
+    let js = serde_json::to_string(&legacy).unwrap();
+    assert_eq!(js, r#"{"cents":1677722}"#);
+    assert_eq!(serde_json::from_str::<Dollar>(&js).unwrap(), legacy);
+}
+
+#[test]
+fn portfolio_errors_name_the_vope() {
+    use crate::{dollar::Dollar, portfolio::Portfolio, transaction::Transaction, Error};
+
+    let mut p = Portfolio::new();
+    p.add_vope("Food", Dollar::from_cents(100)).unwrap();
+    p.add_vope("Rent", Dollar::from_cents(900)).unwrap();
+
+    assert!(matches!(p.add_vope("food", Dollar::ZERO), Err(Error::DuplicateVope(n)) if n == "food"));
+    assert!(matches!(p.remove_vope("Gas"), Err(Error::UnknownVope(n)) if n == "Gas"));
+
+    // A failed transfer must not touch either balance
+    assert!(matches!(
+        p.transfer_holdings("Food", "Gas", Dollar::from_cents(50)),
+        Err(Error::UnknownVope(n)) if n == "Gas"
+    ));
+    assert!(p.view_vopes().iter().all(|v| v.actual_amount.is_zero()));
+
+    let t = Transaction::new(
+        chrono::NaiveDate::from_ymd_opt(2023, 5, 1).unwrap(),
+        "Paycheck".to_string(),
+        Dollar::from_cents(1000),
+    );
+    assert!(matches!(p.assign_transaction(&[], &t, true), Err(Error::InvalidWeights)));
+    p.assign_transaction(&[("Food", 1.0)], &t, false).unwrap();
+
+    let refund = Transaction::new(t.date, "Refund".to_string(), Dollar::from_cents(5));
+    p.assign_transaction(&[("Ignore", 0.0)], &refund, true).unwrap();
+
+    assert!(matches!(
+        p.remove_vope("Food"),
+        Err(Error::NonZeroBalance { balance, .. }) if balance == Dollar::from_cents(1000)
+    ));
+    assert!(p.remove_vope("Rent").is_ok());
+}
+
+/// A fresh, empty directory for a test to write account files into
+#[cfg(test)]
+fn scratch_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("money_man_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn account_saves_where_it_is_told() {
+    use crate::{dollar::Dollar, Account, Error};
+
+    let dir = scratch_dir("save");
+    let first = dir.join("first.json");
+    let second = dir.join("nested").join("second.json");
+
+    let mut acc = Account::new_at(first.clone()).unwrap();
+    acc.get_portfolio_mut().add_vope("Food", Dollar::from_cents(2500)).unwrap();
+    acc.save().unwrap();
+
+    let reopened = Account::open(first.clone()).unwrap();
+    assert_eq!(reopened.get_path(), first.as_path());
+    assert_eq!(reopened.get_portfolio().view_budgeted(), Dollar::from_cents(2500));
+
+    // Save as moves the account, and no temp file is left behind
+    std::fs::create_dir_all(second.parent().unwrap()).unwrap();
+    acc.save_as(&second).unwrap();
+    assert_eq!(acc.get_path(), second.as_path());
+    assert!(Account::open(second.clone()).is_ok());
+    assert_eq!(std::fs::read_dir(second.parent().unwrap()).unwrap().count(), 1);
+
+    // A new account never overwrites one silently, replacing keeps a backup
+    assert!(matches!(Account::new_at(second.clone()), Err(Error::AccountExists(p)) if p == second));
+    let fresh = Account::replace_at(second.clone()).unwrap();
+    assert_eq!(fresh.get_portfolio().view_budgeted(), Dollar::ZERO);
+    let backup = Account::open(Account::replaced_path(&second)).unwrap();
+    assert_eq!(backup.get_portfolio().view_budgeted(), Dollar::from_cents(2500));
+
+    // Failed writes are reported, not swallowed
+    let missing = dir.join("missing").join("acc.json");
+    assert!(matches!(acc.save_as(&missing), Err(Error::Io { path, .. }) if path == missing));
+    assert_eq!(acc.get_path(), second.as_path());
+    assert!(matches!(Account::open(missing), Err(Error::Io { .. })));
+}
+
+/// An account file as written before the schema was versioned
+#[cfg(test)]
+const V0_ACCOUNT: &str = r#"{
+    "port": {
+        "envelopes": [
+            {
+                "name": "Food",
+                "budget": {"amount": 150.5},
+                "actual_amount": {"amount": -12.34},
+                "transactions": ["2023-04-01 | Coffee | $-6.17", "2023-04-01 | Coffee | $-6.17"]
+            }
+        ],
+        "ignored": {"name": "Ignored", "budget": {"amount": 0.0}, "actual_amount": {"amount": 0.0}, "transactions": []},
+        "budgeted": {"amount": 150.5},
+        "holdings": {"amount": -12.34}
+    },
+    "name": "Unknown",
+    "date": "Today",
+    "path": "/somewhere/else/acc.json"
+}"#;
+
+#[test]
+fn old_account_files_are_migrated() {
+    use crate::{dollar::Dollar, migrate, Account};
+
+    let dir = scratch_dir("migrate");
+    let path = dir.join("acc.json");
+    std::fs::write(&path, V0_ACCOUNT).unwrap();
+
+    let acc = Account::open(path.clone()).unwrap();
+    let food = &acc.get_portfolio().view_vopes()[0];
+    assert_eq!(food.budget, Dollar::from_cents(15050));
+    assert_eq!(food.actual_amount, Dollar::from_cents(-1234));
+    let history = acc.get_portfolio().get_vope_history("Food").unwrap();
+    assert_eq!(history.len(), 2);
+    assert_ne!(history[0].transaction.id, history[1].transaction.id);
+    assert_eq!(history[0].amount, Dollar::from_cents(-617));
+    assert!(history[0].transaction.source.is_none());
+    assert!(acc.get_funding().is_empty());
+
+    // The ledger is written from the history, and agrees with it
+    let port = acc.get_portfolio();
+    assert!(port.verify().is_empty());
+    let april = chrono::NaiveDate::from_ymd_opt(2023, 4, 1).unwrap();
+    assert_eq!(port.balance_as_of("Food", april).unwrap(), Dollar::from_cents(-1234));
+    assert!(port.balance_as_of("Food", april.pred_opt().unwrap()).is_err());
+
+    // The original is kept, and the rewritten file is current
+    let backup = Account::backup_path(&path, 0);
+    assert_eq!(std::fs::read_to_string(backup).unwrap(), V0_ACCOUNT);
+
+    let saved: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(migrate::file_version(&saved), migrate::CURRENT_VERSION);
+    assert_eq!(saved["port"]["budgeted"], serde_json::json!({"cents": 15050}));
+}
+
+#[test]
+fn newer_account_files_are_refused() {
+    use crate::{migrate, Error};
+
+    let mut js = serde_json::json!({"version": migrate::CURRENT_VERSION + 1});
+    assert!(matches!(migrate::migrate(&mut js), Err(Error::UnsupportedVersion(_))));
+}
+
+#[test]
+fn identical_transactions_keep_their_own_ids() {
+    use crate::{dollar::Dollar, portfolio::Portfolio, transaction, Error};
+
+    let day = chrono::NaiveDate::from_ymd_opt(2023, 6, 2).unwrap();
+    let coffee = || transaction::Transaction::new(day, "Coffee".to_string(), Dollar::from_cents(-450));
+
+    let mut statement = vec![coffee(), coffee()];
+    transaction::number_occurrences(&mut statement);
+
+    let mut p = Portfolio::new();
+    p.add_vope("Food", Dollar::ZERO).unwrap();
+
+    let mut list = statement.clone();
+    p.clean_transaction_list(&mut list);
+    assert_eq!(list.len(), 2);
+
+    p.assign_transaction(&[("Food", 1.0)], &statement[0], true).unwrap();
+    assert!(matches!(
+        p.assign_transaction(&[("Food", 1.0)], &statement[0], true),
+        Err(Error::DuplicateTransaction(_))
+    ));
+
+    // Importing an overlapping statement skips the coffee already assigned
+    let mut list = vec![coffee(), coffee(), coffee()];
+    transaction::number_occurrences(&mut list);
+    p.clean_transaction_list(&mut list);
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0].id, statement[1].id);
+
+    // Edits keep the ID, so the edited line is still recognized
+    let edit = transaction::TransactionEdit {
+        desc: Some("Coffee with Sam".to_string()),
+        ..Default::default()
+    };
+    p.edit_transaction(&statement[0].id, &edit).unwrap();
+    assert_eq!(p.find_transaction(&statement[0].id).unwrap().desc, "Coffee with Sam");
+
+    let mut list = vec![coffee()];
+    p.clean_transaction_list(&mut list);
+    assert!(list.is_empty());
+
+    // One bank ID is one transaction, even if the lines read differently
+    let fee = |d, desc: &str| transaction::Transaction::new(day + chrono::Days::new(d), desc.to_string(), Dollar::from_cents(-300)).with_bank_id("77");
+    let bagel = transaction::Transaction::new(day, "Bagel".to_string(), Dollar::from_cents(-250));
+    let mut list = vec![fee(1, "FEE"), bagel, fee(3, "Monthly fee")];
+    transaction::number_occurrences(&mut list);
+    p.clean_transaction_list(&mut list);
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[1].desc, "FEE");
+}
+
+#[test]
+fn import_profiles_read_other_layouts() {
+    use crate::{
+        dollar::Dollar,
+        import::{AmountColumns, ImportProfile, SignConvention},
+        Error,
+    };
+
+    let profile = ImportProfile {
+        name: "Credit Union".to_string(),
+        delimiter: ';',
+        skip_rows: 2,
+        date_column: 1,
+        date_format: "%Y-%m-%d".to_string(),
+        desc_column: 2,
+        amount: AmountColumns::Split { debit: 3, credit: 4 },
+        sign: SignConvention::Normal,
+        id_column: Some(0),
+    };
+
+    let csv = "Statement for account 1234\n\
+               Ref;Date;Payee;Debit;Credit\n\
+               A1;2023-07-01;Grocer;$45.10;\n\
+               ;2023-07-02;Employer;;1,200.00\n\
+               \n";
+
+    let list = profile.parse_reader(csv.as_bytes()).unwrap();
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0].charge, Dollar::from_cents(-4510));
+    assert!(list[0].id.is_bank());
+    assert_eq!(list[1].charge, Dollar::from_cents(120_000));
+    assert!(!list[1].id.is_bank());
+
+    let inverted = ImportProfile {
+        sign: SignConvention::Inverted,
+        ..ImportProfile::default()
+    };
+    let list = inverted.parse_reader("Date,Desc,Amount\n07/03/2023,Card,12.00\n".as_bytes()).unwrap();
+    assert_eq!(list[0].charge, Dollar::from_cents(-1200));
+
+    let bad = "Date,Desc,Amount\n07/03/2023,Card,1\n2023-07-04,Card,1\n";
+    assert!(matches!(
+        ImportProfile::default().parse_reader(bad.as_bytes()),
+        Err(Error::Parse { line: 3, column: 1 })
+    ));
+
+    // A delimiter the CSV reader cannot split on is refused rather than truncated
+    let wide = ImportProfile {
+        delimiter: '§',
+        ..ImportProfile::default()
+    };
+    assert!(matches!(wide.delimiter_byte(), Err(Error::InvalidDelimiter('§'))));
+    assert!(matches!(
+        wide.parse_reader("Date§Desc§Amount\n".as_bytes()),
+        Err(Error::InvalidDelimiter('§'))
+    ));
+}
+
+#[test]
+fn ofx_statements_carry_fitids_and_balance() {
+    use crate::{dollar::Dollar, ofx};
+
+    let sgml = "OFXHEADER:100\nDATA:OFXSGML\n\n<OFX>\n<BANKMSGSRSV1><STMTTRNRS><STMTRS>\n\
+                <BANKACCTFROM><BANKID>123<ACCTID>9876<ACCTTYPE>CHECKING</BANKACCTFROM>\n\
+                <BANKTRANLIST>\n\
+                <STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20230701120000.000[-5:EST]<TRNAMT>-4.50\n\
+                <FITID>A1<NAME>COFFEE SHOP</STMTTRN>\n\
+                <STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20230701<TRNAMT>-4,50<FITID>A2<NAME>COFFEE SHOP</STMTTRN>\n\
+                </BANKTRANLIST>\n\
+                <LEDGERBAL><BALAMT>1234.56<DTASOF>20230702</LEDGERBAL>\n\
+                </STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>\n";
+
+    let s = ofx::parse_str(sgml).unwrap();
+    assert_eq!(s.account_id.as_deref(), Some("9876"));
+    assert_eq!(s.transactions.len(), 2);
+    assert_eq!(s.transactions[1].charge, Dollar::from_cents(-450));
+    assert_ne!(s.transactions[0].id, s.transactions[1].id);
+    assert!(s.transactions[0].id.is_bank());
+
+    let bal = s.ledger_balance.unwrap();
+    assert_eq!(bal.amount, Dollar::from_cents(123_456));
+    assert_eq!(bal.date, chrono::NaiveDate::from_ymd_opt(2023, 7, 2).unwrap());
+
+    // The XML form of the same transaction gets the same ID
+    let xml = "<?xml version=\"1.0\"?>\n<OFX><BANKACCTFROM><ACCTID>9876</ACCTID></BANKACCTFROM>\n\
+               <STMTTRN><DTPOSTED>20230701</DTPOSTED><TRNAMT>-4.50</TRNAMT>\n\
+               <FITID>A1</FITID><MEMO>Coffee</MEMO></STMTTRN></OFX>";
+    let x = ofx::parse_str(xml).unwrap();
+    assert_eq!(x.transactions[0].id, s.transactions[0].id);
+    assert_eq!(x.transactions[0].desc, "Coffee");
+
+    // Entities are decoded before the transaction is built
+    let escaped = "<OFX><STMTTRN><DTPOSTED>20230701<TRNAMT>-9.99<FITID>B&amp;1\n\
+                   <NAME>AT&amp;T &lt;Wireless&gt; &quot;Bill&quot; Joe&apos;s &#38; &#x43;o &#y;</STMTTRN></OFX>";
+    let e = ofx::parse_str(escaped).unwrap();
+    assert_eq!(e.transactions[0].desc, "AT&T <Wireless> \"Bill\" Joe's & Co &#y;");
+    let plain = ofx::parse_str("<OFX><STMTTRN><DTPOSTED>20230701<TRNAMT>-9.99<FITID>B&1</STMTTRN></OFX>").unwrap();
+    assert_eq!(e.transactions[0].id, plain.transactions[0].id);
+
+    assert!(ofx::parse_str("<OFX>\n<STMTTRN><TRNAMT>1.00</STMTTRN></OFX>").is_err());
+}
+
+#[test]
+fn qif_reads_splits_and_writes_histories() {
+    use crate::{dollar::Dollar, portfolio::Portfolio, qif};
+
+    let file = "!Account\nNChecking\nTBank\n^\n\
+                !Type:Bank\n\
+                D7/ 1'23\nT-60.00\nPSuper Market\nSFood\nEweekly shop\n$-45.00\nSHousehold\n$-15.00\n^\n\
+                D07/02/2023\nU1,500.00\nT1,500.00\nPEmployer\nLPaycheck\nN1001\nCX\n^\n\
+                D07/03/2023\nT-100.00\nL[Savings]\n^\n\
+                !Type:Cat\nNFood\n^\n";
+
+    let list = qif::parse_str(file).unwrap();
+    assert_eq!(list.len(), 3);
+
+    let shop = &list[0];
+    assert_eq!(shop.transaction.date, chrono::NaiveDate::from_ymd_opt(2023, 7, 1).unwrap());
+    assert_eq!(shop.transaction.charge, Dollar::from_cents(-6000));
+    assert_eq!(shop.splits[0].memo.as_deref(), Some("weekly shop"));
+    assert_eq!(list[1].assignment(), vec![("Paycheck".to_string(), Dollar::from_cents(150_000))]);
+    assert_eq!(list[2].assignment(), vec![("Ignore".to_string(), Dollar::from_cents(-10_000))]);
+
+    // Splits land on multiple vopes by their exact amounts
+    let mut p = Portfolio::new();
+    p.add_vope("Food", Dollar::ZERO).unwrap();
+    p.add_vope("Household", Dollar::ZERO).unwrap();
+
+    let shares = |q: &qif::QifTransaction| q.assignment();
+    let assign = |p: &mut Portfolio, q: &qif::QifTransaction| {
+        let shares = shares(q);
+        let shares: Vec<(&str, Dollar)> = shares.iter().map(|(n, a)| (n.as_str(), *a)).collect();
+        p.assign_shares(&shares, &q.transaction)
+    };
+    assign(&mut p, shop).unwrap();
+    assert_eq!(p.view_vopes()[0].actual_amount, Dollar::from_cents(-4500));
+    assert_eq!(p.view_vopes()[1].actual_amount, Dollar::from_cents(-1500));
+
+    // A refund inside a purchase keeps its sign, and a transfer split is ignored
+    let mixed = "!Type:Bank\nD07/04/2023\nT-100.00\nPOutlet\nSFood\n$-110.00\nSHousehold\n$20.00\nS[Savings]\n$-10.00\n^\n";
+    let mixed = &qif::parse_str(mixed).unwrap()[0];
+    assign(&mut p, mixed).unwrap();
+    assert_eq!(p.view_vopes()[0].actual_amount, Dollar::from_cents(-4500 - 11_000));
+    assert_eq!(p.view_vopes()[1].actual_amount, Dollar::from_cents(-1500 + 2_000));
+    assert_eq!(p.view_ignored().actual_amount, Dollar::from_cents(-1_000));
+
+    let short = "!Type:Bank\nD07/05/2023\nT-50.00\nPOutlet\nSFood\n$-40.00\n^\n";
+    let short = &qif::parse_str(short).unwrap()[0];
+    assert!(matches!(assign(&mut p, short), Err(crate::Error::SplitMismatch { .. })));
+
+    // And can be read back out, each vope with its part
+    let out = qif::write_portfolio(&p);
+    assert!(out.contains("!Account\nNFood\nTBank\n^\n!Type:Bank\nD07/01/2023\nT-45.00\nPSuper Market\nLFood\n^\n"));
+    assert_eq!(qif::parse_str(&out).unwrap().len(), 5);
+
+    assert!(qif::parse_str("!Type:Bank\nT1.00\n^\n").is_err());
+}
+
+#[test]
+fn rules_file_matching_transactions() {
+    use crate::{
+        dollar::Dollar,
+        journal,
+        rules::{DatePattern, Rule, Target},
+        transaction::{self, Transaction},
+        Account, Error,
+    };
+
+    let dir = scratch_dir("rules");
+    let mut acc = Account::new_at(dir.join("acc.json")).unwrap();
+    acc.get_portfolio_mut().add_vope("Food", Dollar::ZERO).unwrap();
+    acc.get_portfolio_mut().add_vope("Rent", Dollar::ZERO).unwrap();
+
+    let coffee = Rule {
+        name: "Coffee".to_string(),
+        desc_pattern: Some("coffee|starbucks".to_string()),
+        min_amount: Some(Dollar::from_cents(-2000)),
+        max_amount: Some(Dollar::ZERO),
+        date_pattern: None,
+        target: Target::Vopes(vec![("Food".to_string(), 1.0)]),
+    };
+    let rent = Rule {
+        name: "Rent".to_string(),
+        desc_pattern: None,
+        min_amount: None,
+        max_amount: Some(Dollar::from_cents(-100_000)),
+        date_pattern: Some(DatePattern::DayOfMonth { first: 1, last: 5 }),
+        target: Target::Vopes(vec![("Rent".to_string(), 1.0)]),
+    };
+    let transfers = Rule {
+        name: "Transfers".to_string(),
+        desc_pattern: Some("^online transfer".to_string()),
+        min_amount: None,
+        max_amount: None,
+        date_pattern: None,
+        target: Target::Ignore,
+    };
+
+    acc.add_rule(coffee.clone(), false).unwrap();
+    acc.add_rule(rent, false).unwrap();
+    acc.add_rule(transfers, false).unwrap();
+    assert!(matches!(acc.add_rule(coffee, false), Err(Error::DuplicateRule(_))));
+
+    let bad = Rule {
+        name: "Bad".to_string(),
+        desc_pattern: Some("(".to_string()),
+        min_amount: None,
+        max_amount: None,
+        date_pattern: None,
+        target: Target::Ignore,
+    };
+    assert!(matches!(acc.add_rule(bad, false), Err(Error::InvalidRule(n)) if n == "Bad"));
+
+    let day = |d| chrono::NaiveDate::from_ymd_opt(2023, 8, d).unwrap();
+    let mut list = vec![
+        Transaction::new(day(2), "STARBUCKS #123".to_string(), Dollar::from_cents(-575)),
+        Transaction::new(day(2), "Landlord".to_string(), Dollar::from_cents(-150_000)),
+        Transaction::new(day(20), "Landlord".to_string(), Dollar::from_cents(-150_000)),
+        Transaction::new(day(9), "Online Transfer to savings".to_string(), Dollar::from_cents(-5000)),
+        Transaction::new(day(9), "Coffee machine".to_string(), Dollar::from_cents(-30_000)),
+    ];
+    transaction::number_occurrences(&mut list);
+
+    let done = acc.auto_assign(&mut list).unwrap();
+    let rules: Vec<&str> = done.iter().map(|a| a.rule.as_str()).collect();
+    assert_eq!(rules, vec!["Coffee", "Rent", "Transfers"]);
+
+    // Rent outside the first days, and the pricey coffee machine, are left over
+    assert_eq!(list.len(), 2);
+    let port = acc.get_portfolio();
+    assert_eq!(port.view_vopes()[0].actual_amount, Dollar::from_cents(-575));
+    assert_eq!(port.get_ignored_history().len(), 1);
+
+    // A wrong match can be taken back like any other assignment
+    let undone = acc.undo().unwrap();
+    assert!(matches!(undone, journal::Command::Assign { transaction, .. } if transaction.desc == "Online Transfer to savings"));
+    assert!(acc.get_portfolio().get_ignored_history().is_empty());
+    assert_eq!(acc.get_portfolio().view_vopes()[0].actual_amount, Dollar::from_cents(-575));
+}
+
+#[test]
+fn suggestions_learn_from_history() {
+    use crate::{dollar::Dollar, portfolio::Portfolio, suggest::Classifier, transaction::Transaction};
+
+    let mut port = Portfolio::new();
+    port.add_vope("Food", Dollar::ZERO).unwrap();
+    port.add_vope("Rent", Dollar::ZERO).unwrap();
+
+    // Nothing assigned yet, so nothing to suggest
+    let day = |d| chrono::NaiveDate::from_ymd_opt(2023, 9, d).unwrap();
+    let latte = Transaction::new(day(20), "STARBUCKS #4411".to_string(), Dollar::from_cents(-610));
+    assert!(Classifier::train(&port).rank(&latte).is_empty());
+
+    let history = [
+        ("STARBUCKS #1234", -450, "Food"),
+        ("Starbucks 0087", -520, "Food"),
+        ("SAFEWAY 1456", -6_400, "Food"),
+        ("PROPERTY MGMT RENT", -150_000, "Rent"),
+        ("Online transfer to savings", -20_000, "Ignore"),
+    ];
+    for (i, (desc, cents, vope)) in history.iter().enumerate() {
+        let t = Transaction::new(day(i as u32 + 1), desc.to_string(), Dollar::from_cents(*cents));
+        port.assign_transaction(&[(vope, 1.0)], &t, false).unwrap();
+    }
+
+    let c = Classifier::train(&port);
+
+    let ranked = c.rank(&latte);
+    assert_eq!(ranked.len(), 3);
+    assert_eq!(ranked[0].vope, "Food");
+    assert!(ranked[0].confidence > 0.8);
+    let total: f64 = ranked.iter().map(|s| s.confidence).sum();
+    assert!((total - 1.0).abs() < 1e-9);
+
+    let rent = Transaction::new(day(30), "Property Mgmt".to_string(), Dollar::from_cents(-150_000));
+    assert_eq!(c.rank(&rent)[0].vope, "Rent");
+}
+
+#[test]
+fn paychecks_fund_vopes_and_leave_the_rest_in_the_default() {
+    use crate::{dollar::Dollar, paycheck::Allocation, portfolio::Portfolio, transaction::Transaction, Error};
+
+    let mut port = Portfolio::new();
+    port.add_vope("Rent", Dollar::from_cents(100_000)).unwrap();
+    port.add_vope("Food", Dollar::from_cents(40_000)).unwrap();
+    port.add_vope("Savings", Dollar::from_cents(60_000)).unwrap();
+
+    let day = |d| chrono::NaiveDate::from_ymd_opt(2023, 10, d).unwrap();
+    let pay = Transaction::new(day(1), "ACME PAYROLL".to_string(), Dollar::from_cents(100_001));
+
+    assert!(matches!(port.cash_paycheck(&pay, &Allocation::BudgetShare), Err(Error::NoDefaultVope)));
+    port.set_default_vope(Some("savings")).unwrap();
+    assert_eq!(port.view_default_vope(), Some("Savings"));
+
+    // Half, a fifth, and the rest with the odd cent
+    let shares = port.cash_paycheck(&pay, &Allocation::BudgetShare).unwrap();
+    assert_eq!(
+        shares,
+        vec![
+            ("Rent".to_string(), Dollar::from_cents(50_000)),
+            ("Food".to_string(), Dollar::from_cents(20_000)),
+            ("Savings".to_string(), Dollar::from_cents(30_001)),
+        ]
+    );
+    assert_eq!(port.view_holdings(), pay.charge);
+    assert!(matches!(
+        port.cash_paycheck(&pay, &Allocation::BudgetShare),
+        Err(Error::DuplicateTransaction(_))
+    ));
+
+    // Rent is topped up first, then Food, from what they already hold, and
+    // the rest reaches Savings
+    let priority = Allocation::Priority(vec!["Rent".to_string(), "Food".to_string()]);
+    let plan = port.plan_paycheck(Dollar::from_cents(120_000), &priority).unwrap();
+    assert_eq!(
+        plan,
+        vec![
+            ("Rent".to_string(), Dollar::from_cents(50_000)),
+            ("Food".to_string(), Dollar::from_cents(20_000)),
+            ("Savings".to_string(), Dollar::from_cents(50_000)),
+        ]
+    );
+    let plan = port.plan_paycheck(Dollar::from_cents(60_000), &priority).unwrap();
+    assert_eq!(plan[1], ("Food".to_string(), Dollar::from_cents(10_000)));
+
+    let fixed = Allocation::Fixed(vec![("Food".to_string(), Dollar::from_cents(30_000))]);
+    let plan = port.plan_paycheck(Dollar::from_cents(50_000), &fixed).unwrap();
+    assert_eq!(plan[1], ("Savings".to_string(), Dollar::from_cents(20_000)));
+    assert!(matches!(
+        port.plan_paycheck(Dollar::from_cents(10_000), &fixed),
+        Err(Error::OverAllocated { .. })
+    ));
+    let negative = Allocation::Fixed(vec![("Food".to_string(), Dollar::from_cents(-1))]);
+    assert!(matches!(
+        port.plan_paycheck(Dollar::from_cents(10_000), &negative),
+        Err(Error::NegativeAllocation { name, .. }) if name == "Food"
+    ));
+
+    let refund = Transaction::new(day(2), "Return".to_string(), Dollar::from_cents(-500));
+    assert!(matches!(port.cash_paycheck(&refund, &fixed), Err(Error::NotIncome(_))));
+}
+
+#[test]
+fn budget_periods_roll_over_by_rule() {
+    use crate::{
+        budget::{Period, Rollover},
+        dollar::Dollar,
+        portfolio::Portfolio,
+        transaction::Transaction,
+        Error,
+    };
+
+    let date = |m, d| chrono::NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+
+    let mut port = Portfolio::new();
+    port.add_vope("Food", Dollar::from_cents(30_000)).unwrap();
+
+    // $350 spent in January, $100 in February, and a $20 refund that is not spending
+    let spending = [(1, 5, -20_000), (1, 20, -15_000), (2, 3, -10_000), (2, 4, 2_000)];
+    for (m, d, cents) in spending {
+        let t = Transaction::new(date(m, d), format!("Grocer {}", d), Dollar::from_cents(cents));
+        port.assign_transaction(&[("Food", 1.0)], &t, false).unwrap();
+    }
+
+    let jan = port.period_summary("food", date(1, 31)).unwrap();
+    assert_eq!((jan.start, jan.end), (date(1, 1), date(2, 1)));
+    assert_eq!(jan.spent, Dollar::from_cents(35_000));
+    assert_eq!(jan.available, Dollar::from_cents(-5_000));
+
+    let carried = |port: &Portfolio| port.period_summary("Food", date(2, 10)).unwrap().carried;
+    assert_eq!(carried(&port), Dollar::from_cents(-5_000));
+
+    port.set_rollover("Food", Rollover::CarrySurplus).unwrap();
+    assert_eq!(carried(&port), Dollar::ZERO);
+    port.set_rollover("Food", Rollover::Reset).unwrap();
+    let feb = port.period_summary("Food", date(2, 10)).unwrap();
+    assert_eq!(feb.available, Dollar::from_cents(20_000));
+
+    // A new budget applies from the period it was set in, past ones keep theirs
+    port.set_budget("Food", Dollar::from_cents(50_000)).unwrap();
+    assert_eq!(port.period_summary("Food", date(2, 10)).unwrap().budgeted, Dollar::from_cents(30_000));
+    let later = chrono::NaiveDate::from_ymd_opt(2100, 1, 1).unwrap();
+    assert_eq!(port.period_summary("Food", later).unwrap().budgeted, Dollar::from_cents(50_000));
+
+    let food = port.get_vope_history("Food").unwrap();
+    let budgets = [(date(1, 1), Dollar::from_cents(30_000)), (date(2, 1), Dollar::from_cents(10_000))];
+    let march = crate::budget::summarize(&port.view_vopes()[0], &food, &budgets, &Period::default(), date(3, 1));
+    assert_eq!(march.budgeted, Dollar::from_cents(10_000));
+    let feb = crate::budget::summarize(&port.view_vopes()[0], &food, &budgets, &Period::default(), date(2, 10));
+    assert_eq!((feb.budgeted, feb.carried), (Dollar::from_cents(10_000), Dollar::ZERO));
+
+    // Periods can start mid month, or run a number of weeks
+    port.set_period(Period::Monthly { start_day: 15 }).unwrap();
+    let p = port.period_summary("Food", date(2, 3)).unwrap();
+    assert_eq!((p.start, p.end), (date(1, 15), date(2, 15)));
+    assert_eq!(p.spent, Dollar::from_cents(25_000));
+
+    let biweekly = Period::Weeks { weeks: 2, anchor: date(1, 5) };
+    assert_eq!(biweekly.start_of(date(1, 4)), chrono::NaiveDate::from_ymd_opt(2023, 12, 22).unwrap());
+    assert_eq!(biweekly.start_of(date(1, 19)), date(1, 19));
+
+    assert!(matches!(port.set_period(Period::Monthly { start_day: 31 }), Err(Error::InvalidPeriod)));
+    assert!(matches!(port.set_rollover("Rent", Rollover::Reset), Err(Error::UnknownVope(_))));
+}
+
+#[test]
+fn recurring_transactions_post_and_match_bank_lines() {
+    use crate::{
+        dollar::Dollar,
+        ledger::EventKind,
+        recurring::{Frequency, Recurring},
+        rules::Target,
+        transaction::Transaction,
+        Account, Error,
+    };
+
+    let date = |m, d| chrono::NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+
+    let dir = scratch_dir("recurring");
+    let mut acc = Account::new_at(dir.join("acc.json")).unwrap();
+    acc.get_portfolio_mut().add_vope("Rent", Dollar::ZERO).unwrap();
+
+    let rent = Recurring {
+        name: "Rent".to_string(),
+        desc: "Landlord".to_string(),
+        amount: Dollar::from_cents(-120_000),
+        frequency: Frequency::Monthly,
+        start: date(1, 31),
+        end: Some(date(4, 29)),
+        target: Target::Vopes(vec![("Rent".to_string(), 1.0)]),
+        last_posted: None,
+    };
+
+    // Short months get their last day, and the schedule stops at the end date
+    let dates: Vec<_> = (0..5).filter_map(|n| rent.occurrence(n)).collect();
+    assert_eq!(dates, vec![date(1, 31), date(2, 29), date(3, 31)]);
+
+    acc.add_recurring(rent.clone(), false).unwrap();
+    assert!(matches!(acc.add_recurring(rent, false), Err(Error::DuplicateSchedule(_))));
+
+    assert_eq!(acc.post_due(date(3, 1)).len(), 2);
+    assert!(acc.post_due(date(3, 1)).is_empty());
+    assert_eq!(acc.get_recurring()[0].last_posted, Some(date(2, 29)));
+    assert_eq!(acc.get_portfolio().view_vopes()[0].actual_amount, Dollar::from_cents(-240_000));
+
+    // The bank posts February's rent a day late, it replaces the scheduled one
+    let mut statement = vec![
+        Transaction::new(date(3, 1), "ACH LANDLORD LLC".to_string(), Dollar::from_cents(-120_000)),
+        Transaction::new(date(3, 1), "Coffee".to_string(), Dollar::from_cents(-500)),
+    ];
+    let bank_id = statement[0].id.clone();
+
+    let matched = acc.match_scheduled(&mut statement);
+    assert_eq!(matched.len(), 1);
+    assert_eq!(statement.len(), 1);
+
+    let port = acc.get_portfolio();
+    let history = port.get_vope_history("Rent").unwrap();
+    assert_eq!(history.len(), 2);
+    assert!(history.iter().any(|p| p.transaction.id == bank_id));
+    assert_eq!(port.splits_of(&bank_id).len(), 1);
+    assert_eq!(port.scheduled_transactions().count(), 1);
+    assert_eq!(port.view_vopes()[0].actual_amount, Dollar::from_cents(-240_000));
+    assert!(matches!(
+        port.view_ledger().events().last().map(|e| &e.kind),
+        Some(EventKind::Replace { to, .. }) if *to == bank_id
+    ));
+
+    // March's rent clears early and is imported before its scheduled date,
+    // so the occurrence is not posted on top of it
+    let early = Transaction::new(date(3, 29), "ACH LANDLORD LLC".to_string(), Dollar::from_cents(-120_000));
+    acc.get_portfolio_mut().assign_transaction(&[("Rent", 1.0)], &early, false).unwrap();
+    assert!(acc.post_due(date(4, 1)).is_empty());
+    assert_eq!(acc.get_recurring()[0].last_posted, Some(date(3, 31)));
+    assert_eq!(acc.get_portfolio().view_vopes()[0].actual_amount, Dollar::from_cents(-360_000));
+
+    acc.remove_recurring("rent").unwrap();
+    assert!(matches!(acc.remove_recurring("rent"), Err(Error::UnknownSchedule(_))));
+
+    // Two subscriptions for the same amount, and one line already imported:
+    // it stands in for the one it names, and only once
+    acc.get_portfolio_mut().add_vope("Fun", Dollar::ZERO).unwrap();
+    let subscription = |name: &str, desc: &str| Recurring {
+        name: name.to_string(),
+        desc: desc.to_string(),
+        amount: Dollar::from_cents(-5_000),
+        frequency: Frequency::Monthly,
+        start: date(5, 10),
+        end: None,
+        target: Target::Vopes(vec![("Fun".to_string(), 1.0)]),
+        last_posted: None,
+    };
+    for (name, desc) in [("Gym", "City Gym"), ("Films", "Netflix"), ("Films 2", "Netflix")] {
+        acc.add_recurring(subscription(name, desc), false).unwrap();
+    }
+
+    let line = Transaction::new(date(5, 9), "NETFLIX.COM".to_string(), Dollar::from_cents(-5_000));
+    acc.get_portfolio_mut().assign_transaction(&[("Fun", 1.0)], &line, false).unwrap();
+
+    let posted = acc.post_due(date(5, 10));
+    let descs: Vec<&str> = posted.iter().map(|t| t.desc.as_str()).collect();
+    assert_eq!(descs, vec!["City Gym", "Netflix"]);
+    assert!(acc.get_recurring().iter().all(|r| r.last_posted == Some(date(5, 10))));
+}
+
+#[test]
+fn undo_and_redo_portfolio_changes() {
+    use crate::{dollar::Dollar, journal::Command, ledger::EventKind, transaction::Transaction, Account, Error};
+
+    let dir = scratch_dir("journal");
+    let mut acc = Account::new_at(dir.join("acc.json")).unwrap();
+
+    let day = chrono::NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+    let pay = Transaction::new(day, "Payroll".to_string(), Dollar::from_cents(50_000));
+    let coffee = Transaction::new(day, "Coffee".to_string(), Dollar::from_cents(-500));
+
+    let add = |name: &str| Command::AddVope {
+        name: name.to_string(),
+        budget: Dollar::ZERO,
+    };
+    let assign = |t: &Transaction, name: &str| Command::Assign {
+        names: vec![(name.to_string(), 1.0)],
+        transaction: t.clone(),
+        even_weight: false,
+    };
+
+    acc.execute(add("Food")).unwrap();
+    acc.execute(add("Fun")).unwrap();
+    acc.execute(assign(&pay, "Food")).unwrap();
+    acc.execute(assign(&coffee, "Food")).unwrap();
+    acc.execute(Command::Transfer {
+        from: "Food".to_string(),
+        to: "Fun".to_string(),
+        amount: Dollar::from_cents(10_000),
+    })
+    .unwrap();
+
+    // A change that fails is not recorded
+    assert!(matches!(acc.execute(add("food")), Err(Error::DuplicateVope(_))));
+
+    let balances = |acc: &Account| -> Vec<i64> {
+        acc.get_portfolio().view_vopes().iter().map(|v| v.actual_amount.cents()).collect()
+    };
+    assert_eq!(balances(&acc), vec![39_500, 10_000]);
+    let events = acc.get_portfolio().view_ledger().events().len();
+
+    assert!(matches!(acc.undo(), Some(Command::Transfer { .. })));
+    assert!(matches!(acc.undo(), Some(Command::Assign { .. })));
+    assert_eq!(balances(&acc), vec![50_000, 0]);
+    assert!(acc.get_portfolio().splits_of(&coffee.id).is_empty());
+
+    // The ledger keeps what was done, and adds what takes it back
+    let ledger = acc.get_portfolio().view_ledger().events();
+    assert_eq!(ledger.len(), events + 2);
+    assert!(matches!(&ledger[events].kind, EventKind::Transfer { from, .. } if from == "Fun"));
+    assert!(matches!(&ledger[events + 1].kind, EventKind::Unassign { transaction, .. } if *transaction == coffee.id));
+    assert!(acc.get_portfolio().verify().is_empty());
+    assert!(acc.get_portfolio().find_transaction(&coffee.id).is_none());
+    assert_eq!(acc.get_portfolio().view_holdings(), Dollar::from_cents(50_000));
+
+    assert!(matches!(acc.redo(), Ok(Some(Command::Assign { .. }))));
+    assert_eq!(balances(&acc), vec![49_500, 0]);
+
+    // A new change drops what was left to redo
+    acc.execute(add("Rent")).unwrap();
+    assert!(acc.get_journal().next_redo().is_none());
+    assert!(matches!(acc.redo(), Ok(None)));
+
+    // Changing the portfolio directly forgets the history
+    acc.get_portfolio_mut().set_default_vope(Some("Food")).unwrap();
+    assert!(acc.undo().is_none());
+}
+
+#[test]
+fn balances_follow_the_ledger() {
+    use crate::{dollar::Dollar, ledger::EventKind, transaction::Transaction, Account};
+
+    let date = |m, d| chrono::NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+
+    let dir = scratch_dir("ledger");
+    let path = dir.join("acc.json");
+    let mut acc = Account::new_at(path.clone()).unwrap();
+
+    let port = acc.get_portfolio_mut();
+    port.add_vope("Food", Dollar::from_cents(40_000)).unwrap();
+    port.add_vope("Fun", Dollar::ZERO).unwrap();
+
+    let pay = Transaction::new(date(6, 1), "Payroll".to_string(), Dollar::from_cents(90_000));
+    let lunch = Transaction::new(date(6, 10), "Lunch".to_string(), Dollar::from_cents(-1_501));
+    port.assign_transaction(&[("Food", 2.0), ("Fun", 1.0)], &pay, false).unwrap();
+    port.assign_transaction(&[("Food", 1.0), ("Fun", 1.0)], &lunch, false).unwrap();
+    port.transfer_holdings("Food", "Fun", Dollar::from_cents(5_000)).unwrap();
+    port.set_budget("food", Dollar::from_cents(45_000)).unwrap();
+
+    // Transfers and budget edits leave a trace
+    let kinds: Vec<&EventKind> = port.view_ledger().events().iter().map(|e| &e.kind).collect();
+    assert!(matches!(kinds[4], EventKind::Transfer { .. }));
+    assert!(matches!(kinds[5], EventKind::SetBudget { .. }));
+    assert!(matches!(kinds[3], EventKind::Assign { shares, .. } if shares[0].1 == Dollar::from_cents(-751)));
+
+    // Before lunch, and after it
+    assert_eq!(port.balance_as_of("Food", date(6, 5)).unwrap(), Dollar::from_cents(60_000));
+    assert_eq!(port.balance_as_of("Fun", date(6, 30)).unwrap(), Dollar::from_cents(-750 + 30_000));
+    assert!(port.verify().is_empty());
+    acc.save().unwrap();
+
+    // A balance edited by hand no longer matches, and is put back by rebuild
+    let mut js: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    js["port"]["envelopes"][0]["actual_amount"] = serde_json::json!({"cents": 1});
+    std::fs::write(&path, js.to_string()).unwrap();
+
+    let mut acc = Account::open(path).unwrap();
+    let port = acc.get_portfolio_mut();
+    let bad = port.verify();
+    assert_eq!(bad.len(), 1);
+    assert_eq!(bad[0].stored, Some(Dollar::from_cents(1)));
+
+    assert_eq!(port.rebuild(), bad);
+    assert!(port.verify().is_empty());
+    assert_eq!(port.view_vopes()[0].actual_amount, Dollar::from_cents(60_000 - 751 - 5_000));
+}
+
+#[test]
+fn funding_accounts_keep_their_own_balance() {
+    use crate::{dollar::Dollar, funding, transaction::Transaction, Account, Error};
+
+    let date = chrono::NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+    let mut acc = Account::new_at(scratch_dir("funding").join("acc.json")).unwrap();
+
+    let checking = funding::FundingAccount {
+        name: "Checking".to_string(),
+        kind: funding::Kind::Checking,
+        institution: "First Bank".to_string(),
+        number: Some("000123".to_string()),
+        opening_balance: Dollar::from_cents(100_000),
+    };
+    let card = funding::FundingAccount {
+        name: "Visa".to_string(),
+        kind: funding::Kind::CreditCard,
+        institution: "First Bank".to_string(),
+        number: None,
+        opening_balance: Dollar::from_cents(-20_000),
+    };
+    acc.add_funding(checking.clone(), false).unwrap();
+    acc.add_funding(card, false).unwrap();
+    assert!(matches!(acc.add_funding(checking, false), Err(Error::DuplicateFunding(_))));
+    assert_eq!(acc.funding_for_number(" 000123").unwrap().name, "Checking");
+
+    let port = acc.get_portfolio_mut();
+    port.add_vope("Food", Dollar::ZERO).unwrap();
+    port.add_vope("Fun", Dollar::ZERO).unwrap();
+
+    // A split charge still only leaves the card once
+    let dinner = Transaction::new(date, "Dinner".to_string(), Dollar::from_cents(-6_000)).from_source("visa");
+    let pay = Transaction::new(date, "Payroll".to_string(), Dollar::from_cents(50_000)).from_source("Checking");
+    let cash = Transaction::new(date, "Market".to_string(), Dollar::from_cents(-500));
+    port.assign_transaction(&[("Food", 1.0), ("Fun", 1.0)], &dinner, false).unwrap();
+    port.assign_transaction(&[("Food", 1.0)], &pay, false).unwrap();
+    port.assign_transaction(&[("Food", 1.0)], &cash, false).unwrap();
+
+    let balances: Vec<(&str, Dollar)> = acc
+        .funding_balances()
+        .into_iter()
+        .map(|(f, b)| (f.name.as_str(), b))
+        .collect();
+    assert_eq!(
+        balances,
+        vec![("Checking", Dollar::from_cents(150_000)), ("Visa", Dollar::from_cents(-26_000))]
+    );
+
+    acc.remove_funding("VISA").unwrap();
+    assert!(matches!(acc.remove_funding("Visa"), Err(Error::UnknownFunding(_))));
+}
+
+#[test]
+fn splits_keep_each_vopes_part() {
+    use crate::{budget, dollar::Dollar, migrate, split::Split, transaction::Transaction};
+
+    let date = chrono::NaiveDate::from_ymd_opt(2024, 8, 3).unwrap();
+    let mut p = crate::portfolio::Portfolio::new();
+    p.add_vope("Food", Dollar::from_cents(10_000)).unwrap();
+    p.add_vope("Home", Dollar::from_cents(10_000)).unwrap();
+
+    let shop = Transaction::new(date, "Superstore".to_string(), Dollar::from_cents(-10_000));
+    p.assign_transaction(&[("Food", 3.0), ("Home", 1.0)], &shop, false).unwrap();
+
+    // Stored once, with the parts adding back up to the charge
+    assert_eq!(p.transactions().len(), 1);
+    let parts: Dollar = p.splits_of(&shop.id).iter().map(|s| s.amount).sum();
+    assert_eq!(parts, shop.charge);
+
+    let food = p.get_vope_history("food").unwrap();
+    assert_eq!(food[0].amount, Dollar::from_cents(-7_500));
+    assert_eq!(food[0].transaction.charge, shop.charge);
+
+    // Only the part counts against the budget
+    let summary = p.period_summary("Home", date).unwrap();
+    assert_eq!(summary.spent, Dollar::from_cents(2_500));
+    assert_eq!(budget::summarize(&p.view_vopes()[0], &food, &[], &p.view_period(), date).spent, Dollar::from_cents(7_500));
+
+    // Older files copied the whole transaction into each vope, with no record
+    // of the shares, so they are split evenly
+    let t = serde_json::to_value(&shop).unwrap();
+    let vope = |name: &str, history: serde_json::Value| {
+        serde_json::json!({"name": name, "budget": {"cents": 0}, "actual_amount": {"cents": 0}, "rollover": "Carry", "transactions": history})
+    };
+    let mut js = serde_json::json!({
+        "version": 9,
+        "port": {
+            "envelopes": [vope("Food", serde_json::json!([t])), vope("Home", serde_json::json!([t]))],
+            "ignored": vope("Ignored", serde_json::json!([])),
+            "ledger": {"events": []}
+        }
+    });
+    migrate::migrate(&mut js).unwrap();
+
+    let splits: Vec<Split> = serde_json::from_value(js["port"]["splits"].clone()).unwrap();
+    let amounts: Vec<Dollar> = splits.iter().map(|s| s.amount).collect();
+    assert_eq!(amounts, vec![Dollar::from_cents(-5_000), Dollar::from_cents(-5_000)]);
+    assert_eq!(js["port"]["transactions"].as_array().unwrap().len(), 1);
+    assert!(js["port"]["envelopes"][0].get("transactions").is_none());
+}
+
+#[test]
+fn reconciling_locks_cleared_transactions() {
+    use crate::{dollar::Dollar, funding, reconcile::Reconciliation, transaction, Account, Error};
+
+    let date = |d| chrono::NaiveDate::from_ymd_opt(2024, 9, d).unwrap();
+    let mut acc = Account::new_at(scratch_dir("reconcile").join("acc.json")).unwrap();
+    acc.add_funding(
+        funding::FundingAccount {
+            name: "Checking".to_string(),
+            kind: funding::Kind::Checking,
+            institution: "First Bank".to_string(),
+            number: None,
+            opening_balance: Dollar::from_cents(10_000),
+        },
+        false,
+    )
+    .unwrap();
+
+    let port = acc.get_portfolio_mut();
+    port.add_vope("Food", Dollar::ZERO).unwrap();
+    let lines = [(2, "Grocer", -2_500), (5, "Cafe", -400), (28, "Bakery", -300)];
+    let list: Vec<transaction::Transaction> = lines
+        .iter()
+        .map(|(d, desc, c)| {
+            transaction::Transaction::new(date(*d), desc.to_string(), Dollar::from_cents(*c)).from_source("Checking")
+        })
+        .collect();
+    for t in list.iter() {
+        port.assign_transaction(&[("Food", 1.0)], t, false).unwrap();
+    }
+
+    // The statement ends on the 25th, so the bakery is not on it
+    let rec = Reconciliation {
+        account: Some("Checking".to_string()),
+        date: date(25),
+        balance: Dollar::from_cents(7_100),
+    };
+    acc.get_portfolio_mut().set_cleared(&list[0].id, true).unwrap();
+    acc.get_portfolio_mut().set_cleared(&list[2].id, true).unwrap();
+    assert_eq!(acc.reconcile_summary(&rec).unwrap().difference, Dollar::from_cents(-400));
+    assert!(matches!(acc.finish_reconciliation(rec.clone()), Err(Error::Unbalanced(_))));
+
+    acc.get_portfolio_mut().set_cleared(&list[1].id, true).unwrap();
+    let summary = acc.reconcile_summary(&rec).unwrap();
+    assert_eq!(summary.cleared, Dollar::from_cents(7_100));
+    assert_eq!(acc.finish_reconciliation(rec).unwrap(), 2);
+
+    // What was reconciled cannot change, what was not still can
+    let port = acc.get_portfolio_mut();
+    let edit = transaction::TransactionEdit {
+        desc: Some("Grocery".to_string()),
+        ..Default::default()
+    };
+    assert!(matches!(port.edit_transaction(&list[0].id, &edit), Err(Error::Reconciled(_))));
+    let note = transaction::TransactionEdit {
+        notes: Some("Weekly shop".to_string()),
+        ..Default::default()
+    };
+    port.edit_transaction(&list[0].id, &note).unwrap();
+    assert!(matches!(port.set_cleared(&list[1].id, false), Err(Error::Reconciled(_))));
+    port.set_cleared(&list[2].id, false).unwrap();
+    assert_eq!(port.view_reconciliations().len(), 1);
+}
+
+#[test]
+fn reports_add_up_spending_by_month() {
+    use crate::{budget, dollar::Dollar, portfolio::Portfolio, report, transaction::Transaction};
+
+    let date = |m, d| chrono::NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+    let mut p = Portfolio::new();
+    p.add_vope("Food", Dollar::from_cents(30_000)).unwrap();
+    p.add_vope("Fun", Dollar::from_cents(5_000)).unwrap();
+
+    let lines = [
+        (1, 1, "Payroll", 200_000, "Food"),
+        (1, 9, "Grocer", -12_000, "Food"),
+        (1, 20, "Cinema", -6_000, "Fun"),
+        (2, 3, "Grocer", -8_000, "Food"),
+        (2, 4, "Card payment", -50_000, "Ignore"),
+    ];
+    for (m, d, desc, cents, vope) in lines {
+        let t = Transaction::new(date(m, d), desc.to_string(), Dollar::from_cents(cents));
+        p.assign_transaction(&[(vope, 1.0)], &t, false).unwrap();
+    }
+
+    let months = report::by_period(&p, &budget::Period::default(), date(1, 15), date(3, 1));
+    assert_eq!(months.len(), 3);
+    assert_eq!(months[0].income, Dollar::from_cents(200_000));
+    assert_eq!(months[0].spent, vec![("Food".to_string(), Dollar::from_cents(12_000)), ("Fun".to_string(), Dollar::from_cents(6_000))]);
+
+    // The card payment was ignored, so it is not spending
+    assert_eq!(months[1].expense, Dollar::from_cents(8_000));
+    assert_eq!(months[1].net(), Dollar::from_cents(-8_000));
+    assert_eq!(months[2].expense, Dollar::ZERO);
+
+    let fun = &report::variance(&p, date(1, 31))[1];
+    assert_eq!(fun.spent, Dollar::from_cents(6_000));
+    assert_eq!(fun.variance, Dollar::from_cents(-1_000));
+}
+
+#[test]
+fn exports_balance_in_plain_text_formats() {
+    use crate::{dollar::Dollar, export, portfolio::Portfolio, transaction::Transaction};
+
+    let date = |d| chrono::NaiveDate::from_ymd_opt(2024, 10, d).unwrap();
+    let mut p = Portfolio::new();
+    p.add_vope("Food", Dollar::ZERO).unwrap();
+    p.add_vope("eating out", Dollar::ZERO).unwrap();
+
+    let pay = Transaction::new(date(1), "Payroll".to_string(), Dollar::from_cents(100_000)).from_source("Checking");
+    let dinner = Transaction::new(date(4), "Bistro \"Chez\"".to_string(), Dollar::from_cents(-9_000));
+    let card = Transaction::new(date(5), "Card payment".to_string(), Dollar::from_cents(-20_000));
+    p.assign_transaction(&[("Food", 1.0)], &pay, false).unwrap();
+    p.assign_transaction(&[("Food", 1.0), ("eating out", 2.0)], &dinner, false).unwrap();
+    p.assign_transaction(&[("Ignore", 1.0)], &card, false).unwrap();
+    p.transfer_holdings("Food", "eating out", Dollar::from_cents(10_000)).unwrap();
+    p.set_cleared(&pay.id, true).unwrap();
+
+    let journal = export::write(&p, export::Format::Ledger);
+    assert!(journal.starts_with("2024-10-01 * Payroll\n    ; source: Checking\n    Assets:Vopes:Food  $1000.00\n    Income:Food  $-1000.00\n"));
+    assert!(journal.contains("2024-10-04 Bistro \"Chez\"\n    Assets:Vopes:Food  $-30.00\n    Expenses:Food  $30.00\n    Assets:Vopes:Eating-out  $-60.00\n"));
+
+    // Each entry balances, and each vope account ends on the vope's balance
+    let mut totals: std::collections::HashMap<String, Dollar> = std::collections::HashMap::new();
+    for entry in journal.split("\n\n").filter(|e| !e.trim().is_empty()) {
+        let mut sum = Dollar::ZERO;
+        for line in entry.lines().filter(|l| l.starts_with("    ") && !l.trim_start().starts_with(';')) {
+            let (account, amount) = line.trim().split_once("  ").unwrap();
+            let amount: Dollar = amount.parse().unwrap();
+            sum += amount;
+            *totals.entry(account.to_string()).or_default() += amount;
+        }
+        assert!(sum.is_zero(), "{}", entry);
+    }
+    for v in p.view_vopes().iter().chain(std::iter::once(p.view_ignored())) {
+        assert_eq!(totals[&export::vope_account(&v.name)], v.actual_amount);
+    }
+
+    let bean = export::write(&p, export::Format::Beancount);
+    assert!(bean.contains("2024-10-04 open Assets:Vopes:Eating-out USD\n"));
+    assert!(bean.contains("2024-10-04 ! \"Bistro \\\"Chez\\\"\"\n"));
+    assert!(!bean.contains("Equity:Opening-Balances"));
+    assert!(bean.contains("  Assets:Vopes:Ignored  -200.00 USD\n"));
+
+    // Names that clean up to the same account are kept apart
+    p.add_vope("Eating-out", Dollar::ZERO).unwrap();
+    p.transfer_holdings("eating out", "Eating-out", Dollar::from_cents(500)).unwrap();
+    let journal = export::write(&p, export::Format::Ledger);
+    assert!(journal.contains("    Assets:Vopes:Eating-out  $-5.00\n    Assets:Vopes:Eating-out-2  $5.00\n"));
+}
+
+#[test]
+fn vopes_are_listed_in_groups() {
+    use crate::{dollar::Dollar, journal, migrate, portfolio::Portfolio, Account, Error};
+
+    let mut p = Portfolio::new();
+    for name in ["Rent", "Food", "Gas", "Vacation"] {
+        p.add_vope(name, Dollar::from_cents(10_000)).unwrap();
+    }
+    p.add_group("Bills").unwrap();
+    p.add_group("Everyday").unwrap();
+    assert!(matches!(p.add_group("bills"), Err(Error::DuplicateGroup(_))));
+    assert!(matches!(p.move_vope("Rent", Some("Savings"), 0), Err(Error::UnknownGroup(_))));
+
+    p.move_vope("Rent", Some("bills"), 0).unwrap();
+    p.move_vope("Gas", Some("Everyday"), 0).unwrap();
+    p.move_vope("Food", Some("Everyday"), 0).unwrap();
+
+    let names = |p: &Portfolio| -> Vec<(Option<String>, Vec<String>)> {
+        p.grouped_vopes()
+            .into_iter()
+            .map(|(g, list)| (g.map(|g| g.name.clone()), list.iter().map(|v| v.name.clone()).collect()))
+            .collect()
+    };
+    let listed = |list: &[(Option<&str>, &[&str])]| -> Vec<(Option<String>, Vec<String>)> {
+        list.iter()
+            .map(|(g, l)| (g.map(str::to_string), l.iter().map(|n| n.to_string()).collect()))
+            .collect()
+    };
+    assert_eq!(
+        names(&p),
+        listed(&[(Some("Bills"), &["Rent"]), (Some("Everyday"), &["Food", "Gas"]), (None, &["Vacation"])])
+    );
+
+    // Dragging to the end of a group, and the group list itself
+    p.move_vope("Food", Some("Everyday"), 5).unwrap();
+    p.move_group("Everyday", 0).unwrap();
+    p.set_collapsed("Bills", true).unwrap();
+    assert_eq!(
+        names(&p),
+        listed(&[(Some("Everyday"), &["Gas", "Food"]), (Some("Bills"), &["Rent"]), (None, &["Vacation"])])
+    );
+    assert!(p.view_groups()[1].collapsed);
+
+    p.sort_vope();
+    let order: Vec<&str> = p.view_vopes().iter().map(|v| v.name.as_str()).collect();
+    assert_eq!(order, ["Food", "Gas", "Rent", "Vacation"]);
+
+    // The vopes of a removed group stay, in no group
+    p.remove_group("Everyday").unwrap();
+    assert_eq!(names(&p), listed(&[(Some("Bills"), &["Rent"]), (None, &["Food", "Gas", "Vacation"])]));
+
+    // Moves can be undone, and folding a group keeps the undo history
+    let mut acc = Account::new_at(scratch_dir("groups").join("acc.json")).unwrap();
+    *acc.get_portfolio_mut() = p;
+    acc.execute(journal::Command::MoveVope {
+        name: "Food".to_string(),
+        group: Some("Bills".to_string()),
+        index: 0,
+    })
+    .unwrap();
+    acc.set_collapsed("Bills", false).unwrap();
+    assert!(acc.undo().is_some());
+    assert_eq!(names(acc.get_portfolio()), listed(&[(Some("Bills"), &["Rent"]), (None, &["Food", "Gas", "Vacation"])]));
+    assert!(!acc.get_portfolio().view_groups()[0].collapsed);
+
+    // Older files have no groups
+    let mut js = serde_json::json!({
+        "version": 11,
+        "port": {
+            "envelopes": [{"name": "Food", "budget": {"cents": 0}, "actual_amount": {"cents": 0}, "rollover": "Carry"}],
+            "ignored": {"name": "Ignored", "budget": {"cents": 0}, "actual_amount": {"cents": 0}, "rollover": "Carry"}
+        }
+    });
+    migrate::migrate(&mut js).unwrap();
+    assert!(js["port"]["envelopes"][0]["group"].is_null());
+    assert_eq!(js["port"]["groups"], serde_json::json!([]));
+}
+
+#[test]
+fn goals_work_out_what_each_period_needs() {
+    use crate::{budget, dollar::Dollar, goal, paycheck, portfolio::Portfolio, transaction::Transaction, Error};
+
+    let date = |m, d| chrono::NaiveDate::from_ymd_opt(2025, m, d).unwrap();
+    let months = budget::Period::default();
+    let cents = Dollar::from_cents;
+
+    // $1200 by mid June is $200 a month from January, less what is in already
+    let trip = goal::Goal::TargetByDate { target: cents(120_000), date: date(6, 15) };
+    let p = goal::progress(&trip, &months, date(1, 10), Dollar::ZERO, cents(15_000), Dollar::ZERO);
+    assert_eq!((p.per_period, p.required, p.fraction), (cents(20_000), cents(5_000), 0.125));
+
+    // Late, so everything is needed now
+    let p = goal::progress(&trip, &months, date(7, 1), cents(100_000), cents(100_000), Dollar::ZERO);
+    assert_eq!((p.per_period, p.required), (cents(20_000), cents(20_000)));
+
+    // Spending from a contribution does not count against it
+    let car = goal::Goal::Contribution { amount: cents(10_000) };
+    let p = goal::progress(&car, &months, date(1, 10), cents(50_000), cents(45_000), cents(8_000));
+    assert_eq!((p.required, p.fraction), (cents(7_000), 0.3));
+
+    let gifts = goal::Goal::SpendDown { date: date(3, 31) };
+    let p = goal::progress(&gifts, &months, date(1, 10), cents(30_000), cents(25_000), cents(5_000));
+    assert_eq!((p.per_period, p.required, p.fraction), (cents(10_000), Dollar::ZERO, 0.5));
+
+    // A paycheck funds what the goals still need, and the rest goes to the default
+    let today = date(2, 10);
+    let mut port = Portfolio::new();
+    port.add_vope("Rent", cents(90_000)).unwrap();
+    port.add_vope("Car", Dollar::ZERO).unwrap();
+    port.add_vope("Pay", Dollar::ZERO).unwrap();
+    port.set_default_vope(Some("Pay")).unwrap();
+    assert!(matches!(port.set_goal("Car", Some(goal::Goal::Contribution { amount: Dollar::ZERO })), Err(Error::InvalidGoal)));
+    port.set_goal("car", Some(car)).unwrap();
+    assert_eq!(port.goal_progress("Rent", today).unwrap(), None);
+
+    let how = paycheck::Allocation::Goals { date: today };
+    let first = Transaction::new(today, "Payroll".to_string(), cents(50_000));
+    let shares = port.cash_paycheck(&first, &how).unwrap();
+    assert_eq!(shares, vec![("Car".to_string(), cents(10_000)), ("Pay".to_string(), cents(40_000))]);
+    assert_eq!(port.goal_progress("Car", today).unwrap().unwrap().fraction, 1.0);
+
+    // Already funded this period
+    let second = Transaction::new(today, "Bonus".to_string(), cents(20_000));
+    assert_eq!(port.plan_paycheck(second.charge, &how).unwrap(), vec![("Pay".to_string(), cents(20_000))]);
+}
+
+#[test]
+fn renamed_and_merged_vopes_keep_their_history() {
+    use crate::{dollar::Dollar, export, journal, ledger::EventKind, rules, transaction::Transaction, Account, Error};
+
+    let date = chrono::NaiveDate::from_ymd_opt(2024, 3, 2).unwrap();
+    let mut acc = Account::new_at(scratch_dir("rename").join("acc.json")).unwrap();
+
+    let port = acc.get_portfolio_mut();
+    for name in ["Food", "Dining", "Old car", "Pay"] {
+        port.add_vope(name, Dollar::from_cents(10_000)).unwrap();
+    }
+    port.set_default_vope(Some("Food")).unwrap();
+    let pay = Transaction::new(date, "Payroll".to_string(), Dollar::from_cents(60_000));
+    let dinner = Transaction::new(date, "Bistro".to_string(), Dollar::from_cents(-3_000));
+    port.assign_transaction(&[("Food", 1.0), ("Dining", 1.0), ("Old car", 1.0)], &pay, false).unwrap();
+    port.assign_transaction(&[("Food", 1.0), ("Dining", 2.0)], &dinner, false).unwrap();
+    port.transfer_holdings("Dining", "Food", Dollar::from_cents(1_000)).unwrap();
+
+    let rule = rules::Rule {
+        name: "Restaurants".to_string(),
+        desc_pattern: Some("bistro".to_string()),
+        min_amount: None,
+        max_amount: None,
+        date_pattern: None,
+        target: rules::Target::Vopes(vec![("Food".to_string(), 1.0), ("Dining".to_string(), 2.0)]),
+    };
+    acc.add_rule(rule, false).unwrap();
+
+    // Names stay unique ignoring case, but a vope can change its own case.
+    // A rename or merge that fails keeps the undo history.
+    acc.execute(journal::Command::AddGroup { name: "Bills".to_string() }).unwrap();
+    assert!(matches!(acc.rename_vope("Food", "dining"), Err(Error::DuplicateVope(_))));
+    assert!(matches!(acc.rename_vope("Food", "ignored"), Err(Error::DuplicateVope(_))));
+    assert!(matches!(acc.rename_vope("Food", "  "), Err(Error::BlankVopeName)));
+    assert!(matches!(acc.merge_vope("Food", "Nowhere"), Err(Error::UnknownVope(_))));
+    assert!(matches!(acc.undo(), Some(journal::Command::AddGroup { .. })));
+    assert!(matches!(acc.get_portfolio_mut().add_vope("", Dollar::ZERO), Err(Error::BlankVopeName)));
+    acc.rename_vope("food", "FOOD").unwrap();
+    acc.rename_vope("FOOD", "Groceries").unwrap();
+
+    let port = acc.get_portfolio();
+    assert_eq!(port.view_default_vope(), Some("Groceries"));
+    assert_eq!(port.get_vope_history("Groceries").unwrap().len(), 2);
+    assert_eq!(port.balance_as_of("Groceries", date).unwrap(), Dollar::from_cents(20_000 - 1_000));
+    assert!(matches!(port.view_ledger().events().last().map(|e| &e.kind), Some(EventKind::Rename { to, .. }) if to == "Groceries"));
+    assert!(port.verify().is_empty());
+
+    // Merging takes the balance and both parts of a shared transaction
+    assert!(matches!(acc.merge_vope("Dining", "dining"), Err(Error::SameVope(_))));
+    let before = acc.get_portfolio().view_ledger().events().to_vec();
+    acc.merge_vope("Dining", "Groceries").unwrap();
+    let port = acc.get_portfolio();
+
+    // Nothing already in the ledger changes, and before the merge the
+    // two vopes are still apart
+    assert_eq!(port.view_ledger().events()[..before.len()], before[..]);
+    assert!(matches!(port.view_ledger().events().last().map(|e| &e.kind), Some(EventKind::Merge { amount, .. }) if *amount == Dollar::from_cents(17_000)));
+    assert_eq!(port.balance_as_of("Groceries", date).unwrap(), Dollar::from_cents(20_000 - 1_000));
+    assert_eq!(port.balance_as_of("Dining", date).unwrap(), Dollar::from_cents(20_000 - 2_000));
+
+    assert!(port.get_vope_history("Dining").is_err());
+    let history = port.get_vope_history("Groceries").unwrap();
+    let parts: Vec<Dollar> = history.iter().map(|p| p.amount).collect();
+    assert_eq!(parts, vec![Dollar::from_cents(40_000), Dollar::from_cents(-3_000)]);
+    assert_eq!(port.view_vopes()[0].actual_amount, Dollar::from_cents(37_000));
+    assert!(port.verify().is_empty());
+    assert_eq!(acc.get_rules()[0].target, rules::Target::Vopes(vec![("Groceries".to_string(), 3.0)]));
+
+    // The export only knows the new names, and still balances
+    let journal = export::write(port, export::Format::Ledger);
+    assert!(!journal.contains("Food") && !journal.contains("Dining"));
+
+    // Archived vopes keep their money, but leave the lists and the budget
+    let budgeted = port.view_budgeted();
+    acc.get_portfolio_mut().set_archived("old car", true).unwrap();
+    let port = acc.get_portfolio();
+    assert_eq!(port.view_budgeted(), budgeted - Dollar::from_cents(10_000));
+    assert_eq!(port.view_vopes()[1].actual_amount, Dollar::from_cents(20_000));
+    let listed: Vec<&str> = port.grouped_vopes()[0].1.iter().map(|v| v.name.as_str()).collect();
+    assert_eq!(listed, ["Groceries", "Pay"]);
+}
+
+#[test]
+fn transactions_keep_notes_and_tags() {
+    use crate::{dollar::Dollar, export, transaction, Account};
+
+    let date = chrono::NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+    let path = scratch_dir("tags").join("acc.json");
+    let mut acc = Account::new_at(path.clone()).unwrap();
+
+    let port = acc.get_portfolio_mut();
+    port.add_vope("Travel", Dollar::from_cents(20_000)).unwrap();
+    let hotel = transaction::Transaction::new(date, "HOTEL 1234".to_string(), Dollar::from_cents(-15_000))
+        .with_payee_memo(Some(" Seaside Inn "), Some(""));
+    assert_eq!(hotel.payee.as_deref(), Some("Seaside Inn"));
+    assert_eq!(hotel.memo, None);
+    port.assign_transaction(&[("Travel", 1.0)], &hotel, true).unwrap();
+
+    // Tags are trimmed, and kept once whatever their case
+    assert_eq!(transaction::parse_tags(" reimbursable, Work,,REIMBURSABLE "), ["reimbursable", "Work"]);
+    let edit = transaction::TransactionEdit {
+        memo: Some("Conference".to_string()),
+        notes: Some("Claim back from work".to_string()),
+        tags: Some(transaction::parse_tags("reimbursable, work")),
+        ..Default::default()
+    };
+    port.edit_transaction(&hotel.id, &edit).unwrap();
+    assert_eq!(port.tagged("Reimbursable").len(), 1);
+    assert!(port.tagged("gift").is_empty());
+    acc.save().unwrap();
+
+    // They are saved with the account, and the ID is unchanged
+    let acc = Account::open(path).unwrap();
+    let t = acc.get_portfolio().find_transaction(&hotel.id).unwrap();
+    assert_eq!(t.payee.as_deref(), Some("Seaside Inn"));
+    assert_eq!(t.memo.as_deref(), Some("Conference"));
+    assert_eq!(t.notes, "Claim back from work");
+    assert!(t.has_tag("WORK"));
+
+    let beancount = export::write(acc.get_portfolio(), export::Format::Beancount);
+    assert!(beancount.contains("\"HOTEL 1234\" #reimbursable #work"));
+    let journal = export::write(acc.get_portfolio(), export::Format::Ledger);
+    assert!(journal.contains("2024-05-06 HOTEL 1234\n    ; :reimbursable:work:\n"));
+    assert!(beancount.contains("notes: \"Claim back from work\""));
+}
@@ -1,162 +1,274 @@
-use crate::{dollar, misc};
-use serde::{de::{self, Unexpected, Visitor}, Deserialize, Deserializer, Serialize};
+use crate::{dollar, error, import};
+use serde::{Deserialize, Serialize};
 use lazy_static::lazy_static;
 use regex::Regex;
 use chrono::NaiveDate;
-use std::{cmp, error, fmt, hash, path};
+use std::{cmp, collections, fmt, hash, path};
 
-// A transaction
-#[derive(Clone, PartialEq, Eq, Debug)]
-pub struct Transaction {
-    pub date: chrono::NaiveDate,
-    pub desc: String,
-    pub charge: dollar::Dollar,
-}
+/// Identifies one real world transaction, and stays the same every time it
+/// is imported.
+///
+/// Banks that provide their own ID (such as an OFX FITID) use that. Otherwise
+/// the ID is a hash of the date, description and charge, plus which
+/// occurrence of that exact line it was in the statement, so two identical
+/// coffees on the same day stay two transactions.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct TransactionId(String);
+
+impl TransactionId {
+    /// An ID the bank assigned
+    pub fn bank(id: &str) -> TransactionId {
+        TransactionId(format!("bank:{}", id))
+    }
 
-impl Serialize for Transaction {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        serializer.serialize_str(&format!("{} | {} | {}", self.date, self.desc, self.charge,))
+    /// An ID made from the content of the transaction
+    pub fn content(
+        date: NaiveDate,
+        desc: &str,
+        charge: dollar::Dollar,
+        occurrence: u32,
+    ) -> TransactionId {
+        let hash = fnv1a(format!("{}|{}|{}", date, desc, charge.cents()).as_bytes());
+        TransactionId(format!("h:{:016x}:{}", hash, occurrence))
+    }
+
+    /// An ID for one occurrence of a recurring transaction, until the bank's
+    /// line for it is imported
+    pub fn scheduled(name: &str, date: NaiveDate) -> TransactionId {
+        TransactionId(format!("s:{}:{}", date, name))
+    }
+
+    /// True if the bank provided this ID
+    pub fn is_bank(&self) -> bool {
+        self.0.starts_with("bank:")
+    }
+
+    /// True if this is a scheduled occurrence no bank line has replaced yet
+    pub fn is_scheduled(&self) -> bool {
+        self.0.starts_with("s:")
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
     }
 }
 
-impl<'de> Deserialize<'de> for Transaction {
-    fn deserialize<D>(deserializer: D) -> Result<Transaction, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        deserializer.deserialize_string(TransactionVisitor)
+impl fmt::Display for TransactionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
     }
 }
 
-struct TransactionVisitor;
+/// How far a transaction is through reconciliation
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Status {
+    /// Not yet seen on a statement
+    #[default]
+    Uncleared,
+    /// Ticked off against the statement being reconciled
+    Cleared,
+    /// Part of a finished reconciliation, and can no longer be changed
+    Reconciled,
+}
+
+// A transaction
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Transaction {
+    pub id: TransactionId,
+    pub date: chrono::NaiveDate,
+    pub desc: String,
+    pub charge: dollar::Dollar,
+    /// The funding account it was imported from, see `funding::FundingAccount`
+    pub source: Option<String>,
+    pub status: Status,
+    /// Who was paid, or who paid, when the description does not say
+    pub payee: Option<String>,
+    /// Extra detail, such as the bank's memo line
+    pub memo: Option<String>,
+    /// Free-form notes
+    pub notes: String,
+    /// Labels such as "reimbursable" or "gift", whichever vopes it is in.
+    /// See `parse_tags`.
+    pub tags: Vec<String>,
+}
 
 lazy_static! {
     static ref MY_REGEX: Regex =
         Regex::new(r"^(\d{4}-\d{2}-\d{2}) \| ([^\|]+) \| \$(-?[0-9]+\.[0-9]+)$").unwrap();
 }
 
-// lazy_static! {
-// static ref re: Regex::new.unwrap(); // PERF: move this into a lazy_static!
-// }
-
-impl<'de> Visitor<'de> for TransactionVisitor {
-    type Value = Transaction;
-
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("Of form date, desc, amount")
-    }
-
-    fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
-    where
-        E: de::Error,
-    {
-        if let Some(nums) = MY_REGEX.captures_iter(s).next() {
-            if let Ok(date) = NaiveDate::parse_from_str(&nums[1], "%Y-%m-%d") {
-                // nums[0] is the whole match, so we must skip that
-                let desc = &nums[2].to_string();
-                if let Ok(float) = &nums[3].parse::<f32>() {
-                    Ok(Transaction::new(
-                        date,
-                        desc.to_string(),
-                        dollar::Dollar::from(*float),
-                    ))
-                } else {
-                    Err(de::Error::invalid_value(Unexpected::Str(s), &self))
-                }
-            } else {
-                Err(de::Error::invalid_value(Unexpected::Str(s), &self))
-            }
-        } else {
-            Err(de::Error::invalid_value(Unexpected::Str(s), &self))
-        }
+impl fmt::Display for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} | {} | {}", self.date, self.desc, self.charge)
     }
 }
 
-impl ToString for Transaction {
-    fn to_string(&self) -> String {
-        "Test".to_string()
+impl hash::Hash for Transaction {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
     }
 }
 
-impl hash::Hash for Transaction {
-    fn hash<H: hash::Hasher>(&self, state: &mut H) {
-        self.date.hash(state);
-        self.desc.hash(state);
+/// Two transactions are the same if they have the same ID
+impl PartialEq for Transaction {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
     }
 }
 
-impl PartialOrd for Transaction {
-    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+impl Eq for Transaction {}
 
-        // Match by date
-        match self.date.partial_cmp(&other.date) {
-            Some(core::cmp::Ordering::Equal) => {}
-            ord => return ord,
-        }
-        // Then by description
-        match self.desc.partial_cmp(&other.desc) {
-            Some(core::cmp::Ordering::Equal) => {}
-            ord => return ord,
+impl Transaction {
+    /// Makes a transaction identified by its content, see `number_occurrences`
+    pub fn new(date: chrono::NaiveDate, desc: String, charge: dollar::Dollar) -> Self {
+        let id = TransactionId::content(date, &desc, charge, 0);
+        Self {
+            id,
+            date,
+            desc,
+            charge,
+            source: None,
+            status: Status::Uncleared,
+            payee: None,
+            memo: None,
+            notes: String::new(),
+            tags: vec![],
         }
-        // Then by charge
-        self.charge.partial_cmp(&other.charge)
     }
-}
 
-impl Ord for Transaction {
-    fn cmp(&self, other: &Self) -> cmp::Ordering {
-        // Match by date
-        match self.date.cmp(&other.date) {
-            cmp::Ordering::Equal => {},
-            ord => return ord,
-        }
-        // Then by description
-        match self.desc.cmp(&other.desc) {
-            cmp::Ordering::Equal => {}
-            ord => return ord,
-        }
-        // Then by charge
-        self.charge.cmp(&other.charge)
+    /// Uses the ID the bank gave this transaction instead of a content hash
+    pub fn with_bank_id(mut self, id: &str) -> Self {
+        self.id = TransactionId::bank(id);
+        self
+    }
 
+    /// Tags the transaction with the funding account it came from
+    pub fn from_source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
     }
-}
 
-impl Transaction {
-    pub fn new(date: chrono::NaiveDate, desc: String, charge: dollar::Dollar) -> Self {
-        Self { date, desc, charge }
+    /// Fills in the payee and memo, leaving out empty ones
+    pub fn with_payee_memo(mut self, payee: Option<&str>, memo: Option<&str>) -> Self {
+        let keep = |s: Option<&str>| s.map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+        self.payee = keep(payee);
+        self.memo = keep(memo);
+        self
+    }
+
+    /// True if the transaction has `tag`, whatever its case
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag.trim()))
+    }
+
+    /// Orders transactions by date, then description, charge and ID, the way
+    /// statements are listed. Not an `Ord` impl, since two transactions are
+    /// equal only when their IDs are.
+    pub fn statement_order(&self, other: &Transaction) -> cmp::Ordering {
+        (self.date, &self.desc, self.charge, &self.id).cmp(&(other.date, &other.desc, other.charge, &other.id))
+    }
+
+    /// Reads the `date | desc | $charge` form transactions were saved in before
+    /// they had IDs
+    pub fn from_legacy(s: &str) -> Option<Transaction> {
+        let nums = MY_REGEX.captures(s)?;
+        // nums[0] is the whole match, so we must skip that
+        let date = NaiveDate::parse_from_str(&nums[1], "%Y-%m-%d").ok()?;
+        let charge = nums[3].parse::<dollar::Dollar>().ok()?;
+
+        Some(Transaction::new(date, nums[2].to_string(), charge))
     }
 }
 
-// Adds all the transactions in - for now, we are parsing the CSV's
-pub fn parse_transactions(path: &path::Path) -> Result<Vec<Transaction>, Box<dyn error::Error>> {
-    let mut trans = vec![];
+/// The parts of a transaction that can be changed once it is assigned.
+///
+/// Fields left as `None` are kept. The ID never changes, so an edited
+/// transaction is still recognized when it is imported again. An empty
+/// payee or memo takes it away.
+#[derive(Clone, Debug, Default)]
+pub struct TransactionEdit {
+    pub date: Option<NaiveDate>,
+    pub desc: Option<String>,
+    pub payee: Option<String>,
+    pub memo: Option<String>,
+    pub notes: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
 
-    let mut rdr = csv::Reader::from_path(path)?;
+impl TransactionEdit {
+    /// True if only the payee, memo, notes or tags change, which is allowed
+    /// even on a reconciled transaction
+    pub fn is_annotation(&self) -> bool {
+        self.date.is_none() && self.desc.is_none()
+    }
 
-    // For line in csv...
-    for result in rdr.records() {
-        // Get the record
-        let record = result?;
+    pub(crate) fn apply(&self, t: &mut Transaction) {
+        if let Some(date) = self.date {
+            t.date = date;
+        }
+        if let Some(desc) = &self.desc {
+            t.desc = desc.clone();
+        }
+        if let Some(payee) = &self.payee {
+            t.payee = Some(payee.trim()).filter(|p| !p.is_empty()).map(str::to_string);
+        }
+        if let Some(memo) = &self.memo {
+            t.memo = Some(memo.trim()).filter(|m| !m.is_empty()).map(str::to_string);
+        }
+        if let Some(notes) = &self.notes {
+            t.notes = notes.clone();
+        }
+        if let Some(tags) = &self.tags {
+            t.tags = unique_tags(tags.iter().map(String::as_str));
+        }
+    }
+}
+
+/// Reads a comma separated list of tags. Blank tags are dropped, and a tag
+/// already listed in another case is only kept once.
+pub fn parse_tags(s: &str) -> Vec<String> {
+    unique_tags(s.split(','))
+}
 
-        // csv should be of form:
-        // date(mm/dd/yyyy), description, amount
-        let date = record[0].to_owned();
-        let desc = record[1].to_owned();
+/*
+ * Trims each tag, and keeps the first of any that only differ in case
+ */
+pub(crate) fn unique_tags<'a>(list: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+    for tag in list.map(str::trim).filter(|t| !t.is_empty()) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
 
-        let amount = match misc::money_to_float(&record[2]) {
-            Ok(okay) => okay,
-            Err(_) => 0.0,
-        };
+/// Gives identical lines from one statement distinct IDs.
+///
+/// The first time a date, description and charge appears it is occurrence 0,
+/// the next identical line is 1, and so on. Bank provided IDs are left alone.
+pub fn number_occurrences(list: &mut [Transaction]) {
+    let mut seen: collections::HashMap<TransactionId, u32> = collections::HashMap::new();
 
-        trans.push(Transaction::new(
-            chrono::NaiveDate::parse_from_str(&date, "%m/%d/%Y").unwrap(),
-            desc,
-            dollar::Dollar::from(amount),
-        ));
+    for t in list.iter_mut().filter(|t| !t.id.is_bank()) {
+        let first = TransactionId::content(t.date, &t.desc, t.charge, 0);
+        let count = seen.entry(first).or_insert(0);
+        t.id = TransactionId::content(t.date, &t.desc, t.charge, *count);
+        *count += 1;
     }
+}
+
+/*
+ * 64 bit FNV-1a. Used instead of std's hasher, which may change between
+ * Rust releases, since these hashes are saved in the account file.
+ */
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
 
-    Ok(trans)
+/// Reads a CSV file in the default three column format, see `import::ImportProfile`
+pub fn parse_transactions(path: &path::Path) -> Result<Vec<Transaction>, error::Error> {
+    import::ImportProfile::default().parse(path)
 }
@@ -1,5 +1,5 @@
 use serde::{Serialize, Deserialize};
-use crate::{transaction, dollar};
+use crate::{budget, dollar, goal};
 
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -7,7 +7,10 @@ pub struct Vope {
     pub name: String,
     pub budget: dollar::Dollar,
     pub actual_amount: dollar::Dollar,
-    pub transactions: Vec<transaction::Transaction>,
+    pub rollover: budget::Rollover,
+    pub group: Option<String>, // The group it is listed under, if any
+    pub goal: Option<goal::Goal>, // What it is saving towards, if anything
+    pub archived: bool,           // Hidden from lists, but kept with its history
 }
 
 impl Vope {
@@ -15,8 +18,27 @@ impl Vope {
         Vope {
             name,
             budget,
-            actual_amount: dollar::Dollar::from(0.0),
-            transactions: vec![],
+            actual_amount: dollar::Dollar::ZERO,
+            rollover: budget::Rollover::default(),
+            group: None,
+            goal: None,
+            archived: false,
+        }
+    }
+}
+
+/// A named heading vopes are listed under, such as Bills or Savings
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+    pub name: String,
+    pub collapsed: bool, // Only the subtotal is shown
+}
+
+impl Group {
+    pub fn new(name: &str) -> Group {
+        Group {
+            name: name.to_string(),
+            collapsed: false,
         }
     }
 }
@@ -0,0 +1,115 @@
+use std::{collections, fmt};
+
+/// Options that take no value
+const FLAGS: [&str; 3] = ["even", "force", "help"];
+
+/// What went wrong running a command
+#[derive(Debug)]
+pub enum CliError {
+    /// The command line did not make sense
+    Usage(String),
+    /// The account refused the change
+    App(app::Error),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(msg) => f.write_str(msg),
+            CliError::App(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<app::Error> for CliError {
+    fn from(e: app::Error) -> Self {
+        CliError::App(e)
+    }
+}
+
+/// A command line, split into positional arguments and `--name value`
+/// options. `--name=value` works too.
+#[derive(Debug, Default)]
+pub struct Args {
+    positional: Vec<String>,
+    options: collections::HashMap<String, String>,
+}
+
+impl Args {
+    pub fn parse(list: impl IntoIterator<Item = String>) -> Result<Args, CliError> {
+        let mut args = Args::default();
+        let mut list = list.into_iter();
+
+        while let Some(arg) = list.next() {
+            let name = match arg.strip_prefix("--") {
+                Some(name) => name,
+                None => {
+                    args.positional.push(arg);
+                    continue;
+                }
+            };
+
+            let (name, value) = match name.split_once('=') {
+                Some((n, v)) => (n.to_string(), v.to_string()),
+                None if FLAGS.contains(&name) => (name.to_string(), String::new()),
+                None => {
+                    let value = list
+                        .next()
+                        .ok_or_else(|| CliError::Usage(format!("--{} needs a value", name)))?;
+                    (name.to_string(), value)
+                }
+            };
+
+            if args.options.insert(name.clone(), value).is_some() {
+                return Err(CliError::Usage(format!("--{} was given twice", name)));
+            }
+        }
+
+        Ok(args)
+    }
+
+    /// Takes the next positional argument, which must be there
+    pub fn next(&mut self, what: &str) -> Result<String, CliError> {
+        if self.positional.is_empty() {
+            Err(CliError::Usage(format!("Missing {}", what)))
+        } else {
+            Ok(self.positional.remove(0))
+        }
+    }
+
+    /// Takes every positional argument that is left
+    pub fn rest(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.positional)
+    }
+
+    /// Takes an option, if it was given
+    pub fn option(&mut self, name: &str) -> Option<String> {
+        self.options.remove(name)
+    }
+
+    /// Takes a flag, true if it was given
+    pub fn flag(&mut self, name: &str) -> bool {
+        self.options.remove(name).is_some()
+    }
+
+    /// Takes an option holding a `2024-01-31` date, if it was given
+    pub fn date(&mut self, name: &str) -> Result<Option<chrono::NaiveDate>, CliError> {
+        self.option(name)
+            .map(|s| {
+                chrono::NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d")
+                    .map_err(|_| CliError::Usage(format!("--{} takes a date like 2024-01-31", name)))
+            })
+            .transpose()
+    }
+
+    /// Fails if anything was given that the command did not take
+    pub fn finish(self) -> Result<(), CliError> {
+        if let Some(arg) = self.positional.first() {
+            return Err(CliError::Usage(format!("Unexpected argument \"{}\"", arg)));
+        }
+        match self.options.keys().next() {
+            Some(name) => Err(CliError::Usage(format!("Unknown option --{}", name))),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use crate::args::{Args, CliError};
+use app::{budget, dollar::Dollar, export, journal, ofx, qif, report, transaction};
+use serde_json::{json, Value};
+use std::path;
+
+pub const USAGE: &str = "\
+Usage: moneyman [--account <acc.json>] <command> ...
+
+Commands:
+  new [--force]                             Create an empty account. --force replaces one
+                                            already there, keeping it as <account>.bak
+  import <statement> [--profile <name>] [--source <account>]
+                                            Read a statement, and file what the rules match
+  assign <statement> <id> <vope[=weight]>... [--profile <name>] [--source <account>] [--even]
+                                            File one line of a statement, \"Ignore\" ignores it
+  transfer <from> <to> <amount>             Move money between vopes
+  balance [--as-of <date>]                  Vope and bank account balances
+  report [--from <date>] [--to <date>] [--date <date>]
+                                            Spending per month, and budget variance
+  export <qif|ledger|beancount> <file>      Write the account for other tools
+
+Everything is printed as JSON. Dates look like 2024-01-31.";
+
+/// Runs one command against the account at `acc_path`
+pub fn run(command: &str, args: Args, acc_path: path::PathBuf) -> Result<Value, CliError> {
+    if command == "new" {
+        return new(args, acc_path);
+    }
+
+    let mut acc = app::Account::open(acc_path)?;
+    match command {
+        "import" => import(args, &mut acc),
+        "assign" => assign(args, &mut acc),
+        "transfer" => transfer(args, &mut acc),
+        "balance" => balance(args, &acc),
+        "report" => report(args, &acc),
+        "export" => export(args, &acc),
+        _ => Err(CliError::Usage(format!("Unknown command \"{}\"\n\n{}", command, USAGE))),
+    }
+}
+
+fn money(d: Dollar) -> Value {
+    Value::String(d.to_decimal_string())
+}
+
+fn parse_money(s: &str) -> Result<Dollar, CliError> {
+    s.parse::<Dollar>()
+        .map_err(|e| CliError::Usage(format!("\"{}\" is not an amount: {}", s, e)))
+}
+
+fn transaction_json(t: &transaction::Transaction) -> Value {
+    json!({
+        "id": t.id.as_str(),
+        "date": t.date.to_string(),
+        "desc": t.desc,
+        "amount": money(t.charge),
+        "source": t.source,
+        "payee": t.payee,
+        "memo": t.memo,
+        "notes": t.notes,
+        "tags": t.tags,
+    })
+}
+
+/*
+ * Reads OFX/QFX and QIF files directly, anything else with the named CSV
+ * profile, and tags every line with the funding account. An OFX statement
+ * for a known account number is tagged with that account.
+ */
+fn read_statement(
+    args: &mut Args,
+    acc: &app::Account,
+    file: &str,
+) -> Result<Vec<transaction::Transaction>, CliError> {
+    let path = path::Path::new(file);
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let profile = args.option("profile").unwrap_or_else(|| "Default".to_string());
+    let mut source = args.option("source");
+
+    let mut list = if ext == "qif" {
+        qif::parse(path)?.into_iter().map(|q| q.transaction).collect()
+    } else if ext == "ofx" || ext == "qfx" {
+        let statement = ofx::parse(path)?;
+        if let Some(f) = statement.account_id.as_deref().and_then(|n| acc.funding_for_number(n)) {
+            source.get_or_insert(f.name.clone());
+        }
+        statement.transactions
+    } else {
+        acc.get_profile(&profile)?.parse(path)?
+    };
+
+    if let Some(source) = source {
+        acc.opening_balance(Some(&source))?;
+        for t in list.iter_mut() {
+            t.source = Some(source.clone());
+        }
+    }
+
+    Ok(list)
+}
+
+fn new(mut args: Args, acc_path: path::PathBuf) -> Result<Value, CliError> {
+    let force = args.flag("force");
+    args.finish()?;
+
+    let backup = app::Account::replaced_path(&acc_path);
+    let replaced = force && acc_path.exists();
+    let acc = if force {
+        app::Account::replace_at(acc_path)?
+    } else {
+        app::Account::new_at(acc_path)?
+    };
+
+    Ok(json!({ "created": acc.get_path(), "backup": replaced.then_some(backup) }))
+}
+
+fn import(mut args: Args, acc: &mut app::Account) -> Result<Value, CliError> {
+    let file = args.next("statement file")?;
+    let mut list = read_statement(&mut args, acc, &file)?;
+    args.finish()?;
+
+    let read = list.len();
+    acc.get_portfolio().clean_transaction_list(&mut list);
+    let new = list.len();
+    let matched = acc.match_scheduled(&mut list);
+    let done = acc.auto_assign(&mut list)?;
+    acc.save()?;
+
+    Ok(json!({
+        "read": read,
+        "already_assigned": read - new,
+        "matched_scheduled": matched.iter().map(|(id, t)| json!({"scheduled": id.as_str(), "transaction": transaction_json(t)})).collect::<Vec<_>>(),
+        "auto_assigned": done.iter().map(|a| json!({"rule": a.rule, "transaction": transaction_json(&a.transaction)})).collect::<Vec<_>>(),
+        "unassigned": list.iter().map(transaction_json).collect::<Vec<_>>(),
+    }))
+}
+
+fn assign(mut args: Args, acc: &mut app::Account) -> Result<Value, CliError> {
+    let file = args.next("statement file")?;
+    let id = args.next("transaction id")?;
+    let list = read_statement(&mut args, acc, &file)?;
+    let even_weight = args.flag("even");
+
+    let mut names = vec![];
+    for spec in args.rest() {
+        let (name, weight) = match spec.split_once('=') {
+            Some((n, w)) => {
+                let w = w
+                    .parse::<f32>()
+                    .map_err(|_| CliError::Usage(format!("\"{}\" is not a weight", w)))?;
+                (n.to_string(), w)
+            }
+            None => (spec, 1.0),
+        };
+        names.push((name, weight));
+    }
+    if names.is_empty() {
+        return Err(CliError::Usage("Name at least one vope, or Ignore".to_string()));
+    }
+    args.finish()?;
+
+    let t = list
+        .into_iter()
+        .find(|t| t.id.as_str() == id)
+        .ok_or_else(|| CliError::Usage(format!("There is no line {} in {}", id, file)))?;
+
+    acc.execute(journal::Command::Assign {
+        names,
+        transaction: t.clone(),
+        even_weight,
+    })?;
+    acc.save()?;
+
+    let port = acc.get_portfolio();
+    Ok(json!({
+        "transaction": transaction_json(&t),
+        "splits": port.splits_of(&t.id).iter().map(|s| json!({"vope": s.vope, "amount": money(s.amount)})).collect::<Vec<_>>(),
+    }))
+}
+
+fn transfer(mut args: Args, acc: &mut app::Account) -> Result<Value, CliError> {
+    let from = args.next("vope to move from")?;
+    let to = args.next("vope to move to")?;
+    let amount = parse_money(&args.next("amount")?)?;
+    args.finish()?;
+
+    acc.execute(journal::Command::Transfer {
+        from: from.clone(),
+        to: to.clone(),
+        amount,
+    })?;
+    acc.save()?;
+
+    Ok(json!({ "from": from, "to": to, "amount": money(amount) }))
+}
+
+fn balance(mut args: Args, acc: &app::Account) -> Result<Value, CliError> {
+    let as_of = args.date("as-of")?;
+    args.finish()?;
+
+    let port = acc.get_portfolio();
+    if let Some(date) = as_of {
+        let vopes: Vec<Value> = port
+            .balances_as_of(date)
+            .iter()
+            .map(|b| json!({"name": b.name, "budget": money(b.budget), "balance": money(b.amount)}))
+            .collect();
+        return Ok(json!({ "as_of": date.to_string(), "vopes": vopes }));
+    }
+
+    let vopes: Vec<Value> = port
+        .view_vopes()
+        .iter()
+        .map(|v| json!({"name": v.name, "budget": money(v.budget), "balance": money(v.actual_amount)}))
+        .collect();
+    let accounts: Vec<Value> = acc
+        .funding_balances()
+        .iter()
+        .map(|(f, b)| json!({"name": f.name, "kind": f.kind.to_string(), "institution": f.institution, "balance": money(*b)}))
+        .collect();
+
+    Ok(json!({
+        "holdings": money(port.view_holdings()),
+        "budgeted": money(port.view_budgeted()),
+        "ignored": money(port.view_ignored().actual_amount),
+        "vopes": vopes,
+        "accounts": accounts,
+    }))
+}
+
+fn report(mut args: Args, acc: &app::Account) -> Result<Value, CliError> {
+    let today = chrono::Local::now().date_naive();
+    let to = args.date("to")?.unwrap_or(today);
+    let from = args.date("from")?.unwrap_or(to);
+    let date = args.date("date")?.unwrap_or(to);
+    args.finish()?;
+
+    let port = acc.get_portfolio();
+    let months: Vec<Value> = report::by_period(port, &budget::Period::default(), from, to)
+        .iter()
+        .map(|m| {
+            json!({
+                "start": m.start.to_string(),
+                "income": money(m.income),
+                "expense": money(m.expense),
+                "net": money(m.net()),
+                "spent": m.spent.iter().map(|(n, d)| json!({"vope": n, "amount": money(*d)})).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    let variance: Vec<Value> = report::variance(port, date)
+        .iter()
+        .map(|v| {
+            json!({
+                "vope": v.vope,
+                "budgeted": money(v.budgeted),
+                "spent": money(v.spent),
+                "variance": money(v.variance),
+            })
+        })
+        .collect();
+
+    Ok(json!({ "months": months, "variance": variance }))
+}
+
+fn export(mut args: Args, acc: &app::Account) -> Result<Value, CliError> {
+    let name = args.next("format")?;
+    let file = args.next("file to write")?;
+    args.finish()?;
+
+    let format = match name.to_ascii_lowercase().as_str() {
+        "qif" => export::Format::Qif,
+        "ledger" | "hledger" => export::Format::Ledger,
+        "beancount" => export::Format::Beancount,
+        _ => return Err(CliError::Usage(format!("Unknown format \"{}\"", name))),
+    };
+
+    export::export(acc.get_portfolio(), format, path::Path::new(&file))?;
+    Ok(json!({ "format": format.to_string(), "written": file }))
+}
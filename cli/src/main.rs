@@ -0,0 +1,53 @@
+// A command line for scripting Money Man. Every command opens the account,
+// makes its change through the same API the GUI uses, saves, and prints what
+// happened as JSON.
+
+mod args;
+mod commands;
+#[cfg(test)]
+mod tests;
+
+use args::{Args, CliError};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let mut args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => return fail(e),
+    };
+
+    if args.flag("help") {
+        println!("{}", commands::USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let acc_path = args
+        .option("account")
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(app::Account::default_path);
+
+    let command = match args.next("command") {
+        Ok(c) => c,
+        Err(e) => return fail(e),
+    };
+
+    match commands::run(&command, args, acc_path) {
+        Ok(out) => {
+            println!("{}", serde_json::to_string_pretty(&out).unwrap_or_default());
+            ExitCode::SUCCESS
+        }
+        Err(e) => fail(e),
+    }
+}
+
+/*
+ * Prints the error as JSON. Bad command lines exit with 2, anything the
+ * account refused with 1.
+ */
+fn fail(e: CliError) -> ExitCode {
+    println!("{}", serde_json::json!({ "error": e.to_string() }));
+    match e {
+        CliError::Usage(_) => ExitCode::from(2),
+        CliError::App(_) => ExitCode::from(1),
+    }
+}
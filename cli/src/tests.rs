@@ -0,0 +1,44 @@
+use crate::args::{Args, CliError};
+
+fn parse(line: &[&str]) -> Result<Args, CliError> {
+    Args::parse(line.iter().map(|s| s.to_string()))
+}
+
+#[test]
+fn args_split_positionals_options_and_flags() {
+    let mut args = parse(&["transfer", "--account=acc.json", "Savings", "--as-of", "2024-01-31", "--even", "Food"]).unwrap();
+
+    assert_eq!(args.next("command").unwrap(), "transfer");
+    assert_eq!(args.option("account").as_deref(), Some("acc.json"));
+    assert_eq!(args.date("as-of").unwrap(), chrono::NaiveDate::from_ymd_opt(2024, 1, 31));
+    assert!(args.flag("even"));
+    assert!(!args.flag("force"));
+    assert_eq!(args.rest(), ["Savings", "Food"]);
+    assert!(args.finish().is_ok());
+
+    // A flag never takes the next argument as its value
+    let mut args = parse(&["new", "--force", "extra"]).unwrap();
+    assert!(args.flag("force"));
+    assert_eq!(args.rest(), ["new", "extra"]);
+}
+
+#[test]
+fn args_reject_what_does_not_make_sense() {
+    let usage = |r: Result<Args, CliError>| matches!(r, Err(CliError::Usage(_)));
+
+    assert!(usage(parse(&["balance", "--as-of"])));
+    assert!(usage(parse(&["import", "--profile", "A", "--profile=B"])));
+
+    let mut args = parse(&["balance", "--as-of", "31/01/2024"]).unwrap();
+    assert!(matches!(args.date("as-of"), Err(CliError::Usage(_))));
+
+    let mut args = parse(&["balance"]).unwrap();
+    args.next("command").unwrap();
+    assert!(matches!(args.next("vope"), Err(CliError::Usage(m)) if m == "Missing vope"));
+
+    // Anything the command did not take is an error, not ignored
+    let args = parse(&["--colour", "red"]).unwrap();
+    assert!(matches!(args.finish(), Err(CliError::Usage(m)) if m == "Unknown option --colour"));
+    let args = parse(&["stray"]).unwrap();
+    assert!(matches!(args.finish(), Err(CliError::Usage(m)) if m.contains("stray")));
+}
@@ -0,0 +1,84 @@
+// Runs the moneyman binary against scratch accounts, the way a script would
+
+use serde_json::Value;
+use std::{path, process};
+
+/*
+ * A fresh, empty directory for a test to write files into
+ */
+fn scratch_dir(name: &str) -> path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("moneyman_cli_{}_{}", name, process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/*
+ * Runs one command, and returns its exit code and the JSON it printed
+ */
+fn run(acc: &path::Path, args: &[&str]) -> (i32, Value) {
+    let out = process::Command::new(env!("CARGO_BIN_EXE_moneyman"))
+        .arg("--account")
+        .arg(acc)
+        .args(args)
+        .output()
+        .unwrap();
+
+    let json = serde_json::from_slice(&out.stdout).unwrap();
+    (out.status.code().unwrap(), json)
+}
+
+#[test]
+fn commands_print_json_and_exit_codes() {
+    let dir = scratch_dir("commands");
+    let acc = dir.join("acc.json");
+    let statement = dir.join("statement.csv");
+    std::fs::write(&statement, "Date,Description,Amount\n01/05/2024,Payroll,1000.00\n01/09/2024,Grocer,-42.10\n").unwrap();
+
+    let (code, out) = run(&acc, &["new"]);
+    assert_eq!(code, 0);
+    assert_eq!(out["backup"], Value::Null);
+
+    // Nothing is overwritten without --force
+    let (code, out) = run(&acc, &["new"]);
+    assert_eq!(code, 1);
+    assert!(out["error"].as_str().unwrap().contains("already an account"));
+
+    // There is no command to add a vope, so make one the way the GUI would
+    let mut account = app::Account::open(acc.clone()).unwrap();
+    account.get_portfolio_mut().add_vope("Safety", app::dollar::Dollar::ZERO).unwrap();
+    account.save().unwrap();
+
+    let (code, out) = run(&acc, &["import", statement.to_str().unwrap()]);
+    assert_eq!(code, 0);
+    assert_eq!(out["read"], 2);
+    let unassigned = out["unassigned"].as_array().unwrap();
+    assert_eq!(unassigned.len(), 2);
+    assert_eq!(unassigned[1]["desc"], "Grocer");
+    assert_eq!(unassigned[1]["amount"], "-42.10");
+
+    let pay = unassigned[0]["id"].as_str().unwrap();
+    let (code, out) = run(&acc, &["assign", statement.to_str().unwrap(), pay, "Safety"]);
+    assert_eq!(code, 0, "{}", out);
+    assert_eq!(out["splits"][0]["vope"], "Safety");
+    assert_eq!(out["splits"][0]["amount"], "1000.00");
+
+    let (code, out) = run(&acc, &["balance"]);
+    assert_eq!(code, 0);
+    assert_eq!(out["holdings"], "1000.00");
+    assert_eq!(out["vopes"][0]["balance"], "1000.00");
+
+    // Bad command lines exit with 2, anything the account refuses with 1
+    let (code, out) = run(&acc, &["transfer", "Safety"]);
+    assert_eq!(code, 2);
+    assert!(out["error"].is_string());
+    let (code, _) = run(&acc, &["balance", "--colour", "red"]);
+    assert_eq!(code, 2);
+    let (code, out) = run(&acc, &["transfer", "Safety", "Nowhere", "5.00"]);
+    assert_eq!(code, 1);
+    assert!(out["error"].as_str().unwrap().contains("Nowhere"));
+
+    let (code, out) = run(&acc, &["new", "--force"]);
+    assert_eq!(code, 0);
+    assert!(out["backup"].as_str().unwrap().ends_with("acc.json.bak"));
+}
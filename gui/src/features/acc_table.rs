@@ -1,68 +1,200 @@
-use app::{dollar, vope};
+use app::{dollar, goal, portfolio, vope};
 use eframe::egui;
 
+/// A change the user made to how the vopes are listed
+pub enum Reorder {
+    /// Put the vope in the group (or none) at this index among its vopes
+    Move {
+        name: String,
+        group: Option<String>,
+        index: usize,
+    },
+    /// Fold a group to its subtotal, or open it again
+    Collapse { group: String, collapsed: bool },
+    /// Move a group to this index among the groups
+    MoveGroup { group: String, index: usize },
+    /// Remove a group, keeping its vopes
+    RemoveGroup(String),
+    /// List each group's vopes by name, see `Portfolio::sort_vope`
+    Sort,
+}
+
+/*
+ * One group as it is drawn, with its subtotals
+ */
+struct GroupRows {
+    group: Option<vope::Group>,
+    vopes: Vec<(vope::Vope, Option<goal::Progress>)>,
+    budget: dollar::Dollar,
+    actual: dollar::Dollar,
+}
+
+/// The vopes under their groups, with a subtotal for each group. Vopes can
+/// be dragged onto another vope, or a group heading, to move them.
 pub struct VertAccDisp {
-    accounts: Vec<vope::Vope>,
+    groups: Vec<GroupRows>,
 }
 
 impl VertAccDisp {
-    pub fn new(
-        accounts: Vec<vope::Vope>,
-    ) -> Self {
-        Self {
-            accounts,
-        }
+    pub fn new(port: &portfolio::Portfolio) -> Self {
+        let today = chrono::Local::now().date_naive();
+        let groups = port
+            .grouped_vopes()
+            .into_iter()
+            .map(|(g, list)| GroupRows {
+                group: g.cloned(),
+                budget: list.iter().map(|v| v.budget).sum(),
+                actual: list.iter().map(|v| v.actual_amount).sum(),
+                vopes: list
+                    .into_iter()
+                    .map(|v| (v.clone(), port.goal_progress(&v.name, today).ok().flatten()))
+                    .collect(),
+            })
+            .collect();
+
+        Self { groups }
     }
 
-    // fn get_grid(&self) -> egui::Grid {
-    //     egui::Grid::new("vope_view")
-    //         .num_columns(3)
-    //         .spacing([40.0, 40.0])
-    //         .striped(true)
-    //         .show(ui, |ui| {
-    //             ui.label("Vope");
-    //             ui.label("Budgeted");
-    //             ui.label("Actual");
-
-    //             ui.end_row();
-
-    //             for v in self.account.iter() {
-    //                 ui.label(v.name.as_str());
-    //                 ui.label(v.budget.to_string());
-    //                 ui.label(v.actual_amount.to_string());
-
-    //                 ui.end_row();
-    //             }
-    //         })
-    // }
-}
-// self.account.len()
+    /// Draws the table, and returns what the user dragged or folded
+    pub fn show(self, ui: &mut egui::Ui) -> Option<Reorder> {
+        let mut change = None;
 
-/**
- *
- */
+        for (i, rows) in self.groups.iter().enumerate() {
+            let name = rows.group.as_ref().map(|g| g.name.clone());
+
+            let body = |ui: &mut egui::Ui| Self::rows(ui, rows, &name);
+
+            match &rows.group {
+                Some(g) => {
+                    let header = format!("{}    {} / {}", g.name, rows.actual, rows.budget);
+                    let res = egui::CollapsingHeader::new(header)
+                        .id_source(("vope_group", &g.name))
+                        .open(Some(!g.collapsed))
+                        .show(ui, body);
 
-impl egui::Widget for VertAccDisp {
-    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
-        egui::Grid::new("vope_view")
-        .num_columns(3)
-        .spacing([40.0, 40.0])
-        .striped(true)
-        .show(ui, |ui| {
+                    if let Some(dragged) = res.header_response.dnd_release_payload::<String>() {
+                        change = Some(Reorder::Move {
+                            name: (*dragged).clone(),
+                            group: name.clone(),
+                            index: usize::MAX,
+                        });
+                    } else if res.header_response.clicked() {
+                        change = Some(Reorder::Collapse {
+                            group: g.name.clone(),
+                            collapsed: !g.collapsed,
+                        });
+                    }
+                    res.header_response.context_menu(|ui| {
+                        if ui.button("Move up").clicked() {
+                            change = Some(Reorder::MoveGroup {
+                                group: g.name.clone(),
+                                index: i.saturating_sub(1),
+                            });
+                            ui.close_menu();
+                        }
+                        if ui.button("Move down").clicked() {
+                            change = Some(Reorder::MoveGroup {
+                                group: g.name.clone(),
+                                index: i + 1,
+                            });
+                            ui.close_menu();
+                        }
+                        if ui.button("Remove group").clicked() {
+                            change = Some(Reorder::RemoveGroup(g.name.clone()));
+                            ui.close_menu();
+                        }
+                    });
+                    if let Some(Some(c)) = res.body_returned {
+                        change = Some(c);
+                    }
+                }
+                // Vopes in no group are listed without a heading, and only
+                // need a drop zone when there are groups to drag them out of
+                None if rows.vopes.is_empty() && self.groups.len() > 1 => {
+                    let (_, dropped) = ui.dnd_drop_zone::<String, _>(egui::Frame::default(), |ui| {
+                        ui.weak("Drop here to take a vope out of its group");
+                    });
+                    if let Some(dragged) = dropped {
+                        change = Some(Reorder::Move {
+                            name: (*dragged).clone(),
+                            group: None,
+                            index: 0,
+                        });
+                    }
+                }
+                None => {
+                    if self.groups.len() > 1 {
+                        ui.separator();
+                    }
+                    if let Some(c) = body(ui) {
+                        change = Some(c);
+                    }
+                }
+            }
+        }
 
-            ui.label("Vope");
-            ui.label("Budgeted");
-            ui.label("Actual");
+        change
+    }
 
-            ui.end_row();
+    /*
+     * One row per vope. Dropping a vope on a row puts it in that row's place.
+     */
+    fn rows(ui: &mut egui::Ui, rows: &GroupRows, group: &Option<String>) -> Option<Reorder> {
+        let mut change = None;
+        let id = ("vope_view", group.clone());
 
-            for v in self.accounts.iter() {
-                ui.label(v.name.as_str());
-                ui.label(v.budget.to_string());
-                ui.label(v.actual_amount.to_string());
+        egui::Grid::new(id)
+            .num_columns(4)
+            .spacing([40.0, 8.0])
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Vope");
+                ui.label("Budgeted");
+                ui.label("Actual");
+                ui.label("Goal");
 
                 ui.end_row();
-            }
-        }).response
+
+                for (i, (v, progress)) in rows.vopes.iter().enumerate() {
+                    let res = ui
+                        .dnd_drag_source(egui::Id::new(("drag_vope", &v.name)), v.name.clone(), |ui| {
+                            ui.label(v.name.as_str())
+                        })
+                        .response
+                        .on_hover_text("Drag to move");
+
+                    if let Some(dragged) = res.dnd_release_payload::<String>() {
+                        change = Some(Reorder::Move {
+                            name: (*dragged).clone(),
+                            group: group.clone(),
+                            index: i,
+                        });
+                    }
+
+                    ui.label(v.budget.to_string());
+                    ui.label(v.actual_amount.to_string());
+
+                    match progress {
+                        Some(p) => {
+                            ui.add(egui::ProgressBar::new(p.fraction).desired_width(60.0))
+                                .on_hover_text(format!("{} still needed this period", p.required));
+                        }
+                        None => {
+                            ui.label("");
+                        }
+                    }
+
+                    ui.end_row();
+                }
+
+                if group.is_some() {
+                    ui.strong("Subtotal");
+                    ui.strong(rows.budget.to_string());
+                    ui.strong(rows.actual.to_string());
+                    ui.end_row();
+                }
+            });
+
+        change
     }
 }
@@ -1,24 +1,23 @@
 use eframe::egui;
-use app::transaction;
+use app::{split, transaction};
 
 /// A VopeHist is a table that shows all previous transactions in the Vope
 pub struct VopeHist {
-    account: Vec<transaction::Transaction>,
+    account: Vec<split::Portion>,
 }
 
 impl VopeHist {
-    pub fn new(data: Vec<transaction::Transaction>) -> Self {
+    pub fn new(data: Vec<split::Portion>) -> Self {
         Self { account: data }
     }
-}
 
-impl egui::Widget for VopeHist {
-    fn ui(self, ui: &mut eframe::egui::Ui) -> eframe::egui::Response {
+    /// Draws the table, and returns the transaction the user wants to edit
+    pub fn show(self, ui: &mut egui::Ui) -> Option<transaction::TransactionId> {
+        let mut picked = None;
 
-        egui::Grid::new("vope_view")
-        .num_columns(3)
-        .min_col_width(ui.available_width() / 4.0 - 10.0)
-        .min_row_height(40.0)
+        egui::Grid::new("vope_hist")
+        .num_columns(5)
+        .min_col_width(ui.available_width() / 6.0 - 10.0)
         .striped(true)
         .show(ui, |ui| {
 
@@ -26,15 +25,41 @@ impl egui::Widget for VopeHist {
             ui.label("Date");
             ui.label("Description");
             ui.label("Transaction");
+            ui.label("Tags");
+            ui.label("");
             ui.end_row();
 
-            for v in self.account.iter() {
-                ui.label(v.date.to_string());
-                ui.label(&v.desc);
-                ui.label(v.charge.to_string());
+            for p in self.account.iter() {
+                let t = &p.transaction;
+                ui.label(t.date.to_string());
+
+                let desc = match &t.payee {
+                    Some(payee) => format!("{} ({})", t.desc, payee),
+                    None => t.desc.clone(),
+                };
+                let res = ui.label(desc);
+                let extra: Vec<&str> = t.memo.iter().map(String::as_str).chain(Some(t.notes.as_str())).filter(|s| !s.is_empty()).collect();
+                if !extra.is_empty() {
+                    res.on_hover_text(extra.join("\n"));
+                }
+
+                // A split transaction shows this vope's part of it
+                if p.amount == t.charge {
+                    ui.label(p.amount.to_string());
+                } else {
+                    ui.label(format!("{} of {}", p.amount, t.charge));
+                }
+
+                ui.label(t.tags.join(", "));
+
+                if ui.small_button("Notes...").clicked() {
+                    picked = Some(t.id.clone());
+                }
 
                 ui.end_row();
             }
-        }).response
+        });
+
+        picked
     }
-}
\ No newline at end of file
+}
@@ -1,10 +1,17 @@
 use super::Content;
 use crate::features::acc_table;
+use app::{dollar, export, journal, reconcile};
 use eframe::egui;
+use native_dialog::FileDialog;
 
 // submod definitions
 mod assign;
+mod budget;
+mod funding_editor;
 mod home;
+mod ledger_check;
+mod reconciler;
+mod reports;
 mod vope_mgr;
 
 // Another similar override to the egui::App
@@ -17,12 +24,20 @@ trait AccDisp {
     );
 
     fn disp_name(&self) -> String;
+
+    // Called when a change is undone or redone, so the display can follow it
+    fn history_moved(&mut self, _cmd: &journal::Command, _undone: bool, _acc: &app::Account) {}
 }
 
 pub struct AccMgmt {
     indx: usize,
     disp: Vec<Box<dyn AccDisp>>,
     acc: Box<app::Account>,
+    ledger_check: ledger_check::LedgerCheck,
+    funding_editor: funding_editor::FundingEditor,
+    reconciler: reconciler::Reconciler,
+    new_group: String,
+    group_status: Option<String>, // Why the last change to the groups failed
 }
 
 impl Content for AccMgmt {
@@ -31,6 +46,17 @@ impl Content for AccMgmt {
         ctx: &eframe::egui::Context,
         frame: &mut eframe::Frame,
     ) -> Option<Box<dyn Content>> {
+        let (undo, redo) = undo_shortcuts(ctx);
+        if undo {
+            self.undo();
+        }
+        if redo {
+            self.redo();
+        }
+
+        // File menu
+        self.menu(ctx);
+
         // Left side AccDisp menu
         self.sidepanel_left(ctx, frame);
 
@@ -40,6 +66,10 @@ impl Content for AccMgmt {
         // Main AccDisp to show
         self.disp[self.indx].update(ctx, frame, &mut self.acc);
 
+        self.ledger_check.show(ctx, &mut self.acc);
+        self.funding_editor.show(ctx, &mut self.acc);
+        self.reconciler.show(ctx, &mut self.acc);
+
         // For now, we will never leave this screen
         None
     }
@@ -56,9 +86,16 @@ impl AccMgmt {
             disp: vec![
                 Box::<home::Home>::default(),
                 Box::<assign::Assign>::default(),
+                Box::<budget::Budget>::default(),
+                Box::<reports::Reports>::default(),
                 Box::<vope_mgr::VopeMgr>::default(),
             ],
             acc,
+            ledger_check: ledger_check::LedgerCheck::default(),
+            funding_editor: funding_editor::FundingEditor::default(),
+            reconciler: reconciler::Reconciler::default(),
+            new_group: String::default(),
+            group_status: None,
         }
     }
 
@@ -83,7 +120,97 @@ impl AccMgmt {
                 self.indx = new_ind;
 
                 ui.separator();
+
+                let journal = self.acc.get_journal();
+                let undo_text = journal.next_undo().map(|c| format!("Undo {}", c));
+                let redo_text = journal.next_redo().map(|c| format!("Redo {}", c));
+
+                ui.horizontal(|ui| {
+                    let undo = ui.add_enabled(undo_text.is_some(), egui::Button::new("⟲ Undo"));
+                    if undo.on_hover_text(undo_text.unwrap_or_default()).clicked() {
+                        self.undo();
+                    }
+
+                    let redo = ui.add_enabled(redo_text.is_some(), egui::Button::new("⟳ Redo"));
+                    if redo.on_hover_text(redo_text.unwrap_or_default()).clicked() {
+                        self.redo();
+                    }
+                });
+
+                ui.separator();
+
+                if ui.button("Accounts").clicked() {
+                    self.funding_editor.open = true;
+                }
+
+                if ui.button("Reconcile").clicked() {
+                    self.reconciler.open = true;
+                }
+
+                if ui.button("Ledger").clicked() {
+                    self.ledger_check.open = true;
+                }
+            });
+    }
+
+    /// Takes back the last change, and saves
+    fn undo(&mut self) {
+        if let Some(cmd) = self.acc.undo() {
+            for d in self.disp.iter_mut() {
+                d.history_moved(&cmd, true, &self.acc);
+            }
+            self.save();
+        }
+    }
+
+    /// Makes the last undone change again, and saves
+    fn redo(&mut self) {
+        match self.acc.redo() {
+            Ok(Some(cmd)) => {
+                for d in self.disp.iter_mut() {
+                    d.history_moved(&cmd, false, &self.acc);
+                }
+                self.save();
+            }
+            Ok(None) => {}
+            Err(e) => log::error!("Failed to redo: {}", e),
+        }
+    }
+
+    fn save(&mut self) {
+        if let Err(e) = self.acc.save() {
+            log::error!("Failed to save: {}", e);
+        }
+    }
+
+    fn menu(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("acc_menu").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    ui.menu_button("Export", |ui| {
+                        for format in export::Format::ALL {
+                            if ui.button(format!("{}...", format)).clicked() {
+                                ui.close_menu();
+                                self.export(format);
+                            }
+                        }
+                    });
+                });
             });
+        });
+    }
+
+    /// Saves the whole portfolio in `format`, to a file the user picks
+    fn export(&mut self, format: export::Format) {
+        let path = FileDialog::new()
+            .add_filter(&format.to_string(), &[format.extension()])
+            .show_save_single_file();
+
+        if let Ok(Some(path)) = path {
+            if let Err(e) = export::export(self.acc.get_portfolio(), format, &path) {
+                log::error!("Failed to export {}: {}", format, e);
+            }
+        }
     }
 
     fn sidepanel_right(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
@@ -97,20 +224,117 @@ impl AccMgmt {
                 ui.separator();
 
                 let p = self.acc.get_portfolio();
+                let funding = self.acc.funding_balances();
 
-                egui::ScrollArea::new([false, true])
+                // What the cleared transactions say is in the bank today
+                let today = reconcile::Reconciliation {
+                    account: None,
+                    date: chrono::Local::now().date_naive(),
+                    balance: dollar::Dollar::ZERO,
+                };
+                let cleared = self.acc.reconcile_summary(&today).map(|s| s.cleared);
+
+                let reorder = egui::ScrollArea::new([false, true])
                     .show(ui, |scroll_ui| {
                         scroll_ui.vertical_centered(|ui| {
                             ui.label(format!("Net Worth: {}", p.view_holdings()));
+                            if let Ok(cleared) = cleared {
+                                ui.label(format!("Cleared: {}", cleared));
+                            }
+                            if let Some(r) = p.view_reconciliations().last() {
+                                ui.label(format!("Reconciled to {}", r.date));
+                            }
                             ui.separator();
                             ui.label(format!("Total Budget: {}", p.view_budgeted()))
                         });
                         scroll_ui.separator();
 
-                        scroll_ui.add(acc_table::VertAccDisp::new(p.view_vopes().clone()));
-                    });
+                        // Where the money actually is
+                        if !funding.is_empty() {
+                            egui::Grid::new("funding_balances")
+                                .striped(true)
+                                .show(scroll_ui, |ui| {
+                                    for (f, balance) in funding.iter() {
+                                        ui.label(&f.name).on_hover_text(format!("{}, {}", f.kind, f.institution));
+                                        ui.label(balance.to_string());
+                                        ui.end_row();
+                                    }
+                                });
+                            scroll_ui.separator();
+                        }
+
+                        acc_table::VertAccDisp::new(p).show(scroll_ui)
+                    })
+                    .inner;
 
                 ui.separator();
+
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut self.new_group).desired_width(100.0));
+                    if ui.button("+ Group").clicked() {
+                        let name = self.new_group.trim().to_string();
+                        self.group_status = self
+                            .acc
+                            .execute(journal::Command::AddGroup { name })
+                            .and_then(|_| self.acc.save())
+                            .err()
+                            .map(|e| e.to_string());
+                        if self.group_status.is_none() {
+                            self.new_group.clear();
+                        }
+                    }
+                    if ui.button("Sort").on_hover_text("List each group's vopes by name").clicked() {
+                        self.reorder(acc_table::Reorder::Sort);
+                    }
+                });
+                if let Some(e) = &self.group_status {
+                    ui.colored_label(egui::Color32::RED, e);
+                }
+
+                if let Some(r) = reorder {
+                    self.reorder(r);
+                }
             });
     }
+
+    /// Applies a drag, fold or move from the vope table, and saves it. Folding
+    /// is only how the table looks, everything else can be undone.
+    fn reorder(&mut self, r: acc_table::Reorder) {
+        let command = match r {
+            acc_table::Reorder::Move { name, group, index } => journal::Command::MoveVope { name, group, index },
+            acc_table::Reorder::Collapse { group, collapsed } => {
+                let res = self.acc.set_collapsed(&group, collapsed).and_then(|_| self.acc.save());
+                self.group_status = res.err().map(|e| e.to_string());
+                return;
+            }
+            acc_table::Reorder::MoveGroup { group, index } => journal::Command::MoveGroup { name: group, index },
+            acc_table::Reorder::RemoveGroup(group) => journal::Command::RemoveGroup { name: group },
+            acc_table::Reorder::Sort => journal::Command::SortVopes,
+        };
+        let res = self.acc.execute(command);
+
+        self.group_status = res.and_then(|_| self.acc.save()).err().map(|e| e.to_string());
+    }
+}
+
+/*
+ * Ctrl+Z to undo, Ctrl+Y or Ctrl+Shift+Z to redo, unless a text box wants them
+ */
+fn undo_shortcuts(ctx: &egui::Context) -> (bool, bool) {
+    if ctx.wants_keyboard_input() {
+        return (false, false);
+    }
+
+    let undo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+    let redo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y);
+    let redo_shift = egui::KeyboardShortcut::new(
+        egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+        egui::Key::Z,
+    );
+
+    ctx.input_mut(|i| {
+        // The longer shortcut first, or Ctrl+Z would take it
+        let redo = i.consume_shortcut(&redo_shift) || i.consume_shortcut(&redo);
+        (i.consume_shortcut(&undo), redo)
+    })
 }
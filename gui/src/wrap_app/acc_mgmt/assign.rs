@@ -1,14 +1,34 @@
-use std::collections;
+use std::{collections, path};
 
-use app::{misc, transaction};
+use app::{dollar, journal, misc, ofx, qif, rules, suggest, transaction};
 use eframe::{egui, epaint};
 use native_dialog::FileDialog;
 
+// submod definitions
+mod paycheck;
+mod profile_editor;
+mod rule_editor;
+mod schedule_editor;
+
 pub struct Assign {
     cator: Vec<transaction::Transaction>,
     act_t: Option<transaction::Transaction>,
     vope_list: collections::HashMap<String, (bool, f32)>, // I want all vopes, and if they are on or not
     even_weight: bool,
+    status: Option<String>, // The last error to show the user
+    profile: String,        // Name of the import profile to read CSVs with
+    source: Option<String>, // The funding account imported lines are tagged with
+    profile_editor: profile_editor::ProfileEditor,
+    statement_balance: Option<ofx::Balance>, // What the bank says the balance is
+    // Vopes a transaction was already filed under in the file it came from
+    suggested: collections::HashMap<transaction::TransactionId, Vec<(String, dollar::Dollar)>>,
+    file_split: Option<Vec<(String, dollar::Dollar)>>, // The active transaction's split, if it came with one
+    rule_editor: rule_editor::RuleEditor,
+    paycheck: paycheck::Paycheck,
+    schedule_editor: schedule_editor::ScheduleEditor,
+    matched: usize, // Lines of the last statement that were scheduled transactions
+    auto_assigned: Vec<rules::AutoAssigned>, // Filed by a rule on import, for the user to look over
+    ranking: Vec<suggest::Suggestion>,       // Where past assignments say the active transaction goes
 }
 
 impl Default for Assign {
@@ -24,6 +44,116 @@ impl Assign {
             act_t: None,
             vope_list: collections::HashMap::default(),
             even_weight: false,
+            status: None,
+            profile: "Default".to_string(),
+            source: None,
+            profile_editor: profile_editor::ProfileEditor::default(),
+            statement_balance: None,
+            suggested: collections::HashMap::default(),
+            file_split: None,
+            rule_editor: rule_editor::RuleEditor::default(),
+            paycheck: paycheck::Paycheck::default(),
+            schedule_editor: schedule_editor::ScheduleEditor::default(),
+            matched: 0,
+            auto_assigned: Vec::new(),
+            ranking: Vec::new(),
+        }
+    }
+
+    /// Moves on to the next transaction, selecting the vopes it was already
+    /// filed under if the statement said so, or else the vope past
+    /// assignments make most likely
+    fn next_transaction(&mut self, acc: &app::Account) {
+        self.act_t = self.cator.pop();
+        self.ranking.clear();
+        self.file_split = None;
+
+        let t = match &self.act_t {
+            Some(t) => t,
+            None => return,
+        };
+
+        let names = match self.suggested.remove(&t.id) {
+            Some(shares) if !shares.is_empty() => {
+                // Weighted by the split amounts, so the preselection reads the same as the file
+                let names = shares
+                    .iter()
+                    .map(|(n, a)| (n.clone(), dollar::Dollar::from_cents(a.cents().abs()).as_f32()))
+                    .collect();
+                self.file_split = Some(shares);
+                names
+            }
+            _ => {
+                self.ranking = suggest::Classifier::train(acc.get_portfolio()).rank(t);
+                match self.ranking.first() {
+                    Some(top) => vec![(top.vope.clone(), 1.0)],
+                    None => return,
+                }
+            }
+        };
+
+        for v in acc.get_portfolio().view_vopes() {
+            let weight = names
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(&v.name))
+                .map(|(_, w)| *w);
+
+            self.vope_list.insert(
+                v.name.clone(),
+                (weight.is_some(), weight.unwrap_or(v.budget.as_f32())),
+            );
+        }
+
+        let ignore = names.iter().any(|(n, _)| n.eq_ignore_ascii_case("Ignore"));
+        self.vope_list.insert("Ignore".to_string(), (ignore, 0.0));
+        self.even_weight = false;
+    }
+
+    /// Whether the selected vopes and weights are still the ones the file split into
+    fn matches_split(&self, shares: &[(String, dollar::Dollar)], picked: &[(String, f32)]) -> bool {
+        picked.len() == shares.len()
+            && shares.iter().all(|(n, a)| {
+                picked.iter().any(|(p, w)| {
+                    p.eq_ignore_ascii_case(n)
+                        && (n.eq_ignore_ascii_case("Ignore")
+                            || *w == dollar::Dollar::from_cents(a.cents().abs()).as_f32())
+                })
+            })
+    }
+
+    /// Reads OFX/QFX and QIF files directly, anything else with the selected CSV profile
+    fn read_statement(
+        &mut self,
+        path: &path::Path,
+        acc: &app::Account,
+    ) -> Result<Vec<transaction::Transaction>, app::Error> {
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+
+        if ext == "qif" {
+            self.statement_balance = None;
+            let list = qif::parse(path)?;
+            for q in list.iter() {
+                self.suggested.insert(q.transaction.id.clone(), q.assignment());
+            }
+            Ok(list.into_iter().map(|q| q.transaction).collect())
+        } else if ext == "ofx" || ext == "qfx" {
+            let statement = ofx::parse(path)?;
+            self.statement_balance = statement.ledger_balance;
+            // The statement says which account it is for, if we know it
+            if let Some(f) = statement
+                .account_id
+                .as_deref()
+                .and_then(|n| acc.funding_for_number(n))
+            {
+                self.source = Some(f.name.clone());
+            }
+            Ok(statement.transactions)
+        } else {
+            self.statement_balance = None;
+            acc.get_profile(&self.profile)?.parse(path)
         }
     }
 
@@ -36,6 +166,14 @@ impl Assign {
         egui::TopBottomPanel::top("assign").show(ctx, |ui| {
             ui.vertical_centered(|ui| {
                 ui.heading("Assign");
+
+                if let Some(bal) = &self.statement_balance {
+                    ui.label(format!("Statement balance: {} as of {}", bal.amount, bal.date));
+                }
+
+                if let Some(msg) = &self.status {
+                    ui.colored_label(egui::Color32::RED, msg);
+                }
             });
         });
     }
@@ -62,6 +200,16 @@ impl Assign {
 
                 ui.separator();
 
+                let income = self.act_t.as_ref().is_some_and(|t| t.charge.cents() > 0);
+                if ui
+                    .add_enabled(income, egui::Button::new("Cash paycheck"))
+                    .clicked()
+                {
+                    self.paycheck.open = true;
+                }
+
+                ui.separator();
+
                 let l = self
                     .vope_list
                     .iter()
@@ -84,16 +232,16 @@ impl Assign {
                     // If we have a transaction to process...
                     if let Some(transaction) = &self.act_t {
                         // Get all the selected vopes
-                        let t: Vec<(&str, f32)> = self
+                        let t: Vec<(String, f32)> = self
                             .vope_list
                             .iter()
                             .filter_map(|(k, v)| {
                                 if v.0 == true {
                                     if self.even_weight {
-                                        Some((k.as_str(), 1.0))
+                                        Some((k.clone(), 1.0))
 
                                     } else {
-                                        Some((k.as_str(), v.1))
+                                        Some((k.clone(), v.1))
                                     }
                                 } else {
                                     None
@@ -101,14 +249,28 @@ impl Assign {
                             })
                             .collect();
 
-                        // Send for processing
-                        match acc.get_portfolio_mut().assign_transaction(&t, transaction, self.even_weight) {
+                        // Send for processing, through the journal so it can be undone.
+                        // A split left as the file had it keeps its exact amounts
+                        let cmd = match &self.file_split {
+                            Some(shares) if !self.even_weight && self.matches_split(shares, &t) => {
+                                journal::Command::AssignShares {
+                                    shares: shares.clone(),
+                                    transaction: transaction.clone(),
+                                }
+                            }
+                            _ => journal::Command::Assign {
+                                names: t,
+                                transaction: transaction.clone(),
+                                even_weight: self.even_weight,
+                            },
+                        };
+                        match acc.execute(cmd) {
                             Ok(_) => {
                                 // and get the next one
-                                self.act_t = self.cator.pop();
-                                acc.save();
+                                self.next_transaction(acc);
+                                self.status = acc.save().err().map(|e| e.to_string());
                             },
-                            Err(e) => println!("AHHHHHHHHHH: {}", e),
+                            Err(e) => self.status = Some(e.to_string()),
                         }
                     }
                 }
@@ -127,7 +289,7 @@ impl Assign {
                 if ui.button("Start").clicked() && self.act_t.is_none() {
                     let path = FileDialog::new()
                         .set_location("~/Desktop")
-                        .add_filter("CSV File", &["csv"])
+                        .add_filter("Statement", &["csv", "ofx", "qfx", "qif"])
                         .show_open_single_file()
                         .unwrap();
 
@@ -136,14 +298,68 @@ impl Assign {
                         None => return,
                     };
 
-                    match transaction::parse_transactions(&path) {
+                    match self.read_statement(&path, acc) {
                         Ok(mut l) => {
+                            if let Some(source) = &self.source {
+                                for t in l.iter_mut() {
+                                    t.source = Some(source.clone());
+                                }
+                            }
                             acc.get_portfolio().clean_transaction_list(&mut l);
+
+                            // Lines that were already posted from a schedule replace it
+                            self.matched = acc.match_scheduled(&mut l).len();
+
+                            // Let the rules file what they can, the rest is up to the user
+                            self.status = match acc.auto_assign(&mut l) {
+                                Ok(done) => {
+                                    self.auto_assigned = done;
+                                    acc.save().err().map(|e| e.to_string())
+                                }
+                                Err(e) => Some(e.to_string()),
+                            };
+
                             self.cator.append(&mut l);
-                            self.act_t = self.cator.pop();
+                            self.next_transaction(acc);
                         }
-                        Err(_) => todo!(),
+                        Err(e) => self.status = Some(e.to_string()),
+                    }
+                }
+
+                egui::ComboBox::from_id_source("import_profile")
+                    .selected_text(&self.profile)
+                    .show_ui(ui, |ui| {
+                        for p in acc.get_profiles() {
+                            ui.selectable_value(&mut self.profile, p.name.clone(), &p.name);
+                        }
+                    });
+
+                if !acc.get_funding().is_empty() {
+                    egui::ComboBox::from_id_source("import_source")
+                        .selected_text(self.source.as_deref().unwrap_or("No account"))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.source, None, "No account");
+                            for f in acc.get_funding() {
+                                ui.selectable_value(&mut self.source, Some(f.name.clone()), &f.name);
+                            }
+                        })
+                        .response
+                        .on_hover_text("The account this statement is for");
+                }
+
+                if ui.button("Edit profile").clicked() {
+                    if let Ok(p) = acc.get_profile(&self.profile) {
+                        self.profile_editor.load(p);
                     }
+                    self.profile_editor.open = true;
+                }
+
+                if ui.button("Rules").clicked() {
+                    self.rule_editor.open = true;
+                }
+
+                if ui.button("Recurring").clicked() {
+                    self.schedule_editor.open = true;
                 }
                 ui.separator();
 
@@ -171,12 +387,54 @@ impl Assign {
 
             ui.separator();
 
-            let len = acc.get_portfolio().view_vopes().len();
+            if !self.ranking.is_empty() {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Suggested:");
+                    for s in self.ranking.iter().take(3) {
+                        ui.label(format!("{} ({:.0}%)", s.vope, s.confidence * 100.0));
+                    }
+                });
+                ui.separator();
+            }
+
+            if self.matched > 0 {
+                ui.label(format!("{} lines matched recurring transactions", self.matched));
+                ui.separator();
+            }
+
+            if !self.auto_assigned.is_empty() {
+                egui::CollapsingHeader::new(format!("Auto-assigned ({})", self.auto_assigned.len()))
+                    .show(ui, |ui| {
+                        ui.weak("Undo takes back the last one, and puts it in the queue to assign by hand");
+                        egui::Grid::new("auto_assigned").striped(true).show(ui, |ui| {
+                            for a in self.auto_assigned.iter() {
+                                let target = match &a.target {
+                                    rules::Target::Vopes(list) => list
+                                        .iter()
+                                        .map(|(n, _)| n.as_str())
+                                        .collect::<Vec<&str>>()
+                                        .join(", "),
+                                    rules::Target::Ignore => "Ignore".to_string(),
+                                };
+
+                                ui.label(a.transaction.date.to_string());
+                                ui.label(&a.transaction.desc);
+                                ui.label(a.transaction.charge.to_string());
+                                ui.label(target);
+                                ui.label(format!("by {}", a.rule));
+                                ui.end_row();
+                            }
+                        });
+                    });
+                ui.separator();
+            }
+
+            let len = acc.get_portfolio().view_vopes().iter().filter(|v| !v.archived).count();
 
             let min_width = ui.available_width() / (len as f32).sqrt().ceil();
 
             ui.horizontal_wrapped(|ui| {
-                for v in acc.get_portfolio().view_vopes().iter() {
+                for v in acc.get_portfolio().view_vopes().iter().filter(|v| !v.archived) {
                     let (b, _) = self.vope_list.get_mut(&v.name).unwrap();
                     let color = if *b {
                         egui::Color32::DARK_GREEN
@@ -207,6 +465,28 @@ impl super::AccDisp for Assign {
         "Assign".to_string()
     }
 
+    // An undone assignment goes back to the front of the queue, a redone one leaves it
+    fn history_moved(&mut self, cmd: &journal::Command, undone: bool, acc: &app::Account) {
+        let t = match cmd {
+            journal::Command::Assign { transaction, .. }
+            | journal::Command::AssignShares { transaction, .. }
+            | journal::Command::CashPaycheck { transaction, .. } => transaction,
+            _ => return,
+        };
+
+        if undone {
+            self.auto_assigned.retain(|a| &a.transaction != t);
+            if let Some(current) = self.act_t.take() {
+                self.cator.push(current);
+            }
+            self.act_t = Some(t.clone());
+        } else if self.act_t.as_ref() == Some(t) {
+            self.next_transaction(acc);
+        } else {
+            self.cator.retain(|c| c != t);
+        }
+    }
+
     fn update(
         &mut self,
         ctx: &egui::Context,
@@ -223,5 +503,15 @@ impl super::AccDisp for Assign {
         self.update_top_panel(ctx, frame, acc);
         self.update_bottom_panel(ctx, frame, acc);
         self.update_center_panel(ctx, frame, acc);
+
+        self.profile_editor.show(ctx, acc);
+        self.rule_editor.show(ctx, acc);
+        self.schedule_editor.show(ctx, acc);
+
+        if let Some(t) = self.act_t.clone() {
+            if self.paycheck.open && self.paycheck.show(ctx, acc, &t) {
+                self.next_transaction(acc);
+            }
+        }
     }
 }
@@ -0,0 +1,207 @@
+// Library imports
+use eframe::egui;
+use std::collections;
+// Local Library imports
+use app::{dollar, journal, paycheck, transaction};
+
+#[derive(PartialEq, Clone, Copy)]
+enum Mode {
+    BudgetShare,
+    Fixed,
+    Priority,
+    Goals,
+}
+
+/// A window that spreads an income transaction across the vopes
+pub struct Paycheck {
+    pub open: bool,
+    mode: Mode,
+    fixed: collections::HashMap<String, String>, // Vope name, and the amount typed for it
+    priority: Vec<(String, bool)>,                // Vopes in the order they are filled, and if they are on
+    status: Option<String>,
+}
+
+impl Default for Paycheck {
+    fn default() -> Self {
+        Self {
+            open: false,
+            mode: Mode::BudgetShare,
+            fixed: collections::HashMap::default(),
+            priority: Vec::new(),
+            status: None,
+        }
+    }
+}
+
+impl Paycheck {
+    /// Reads the form into an allocation
+    fn allocation(&self, t: &transaction::Transaction) -> Result<paycheck::Allocation, String> {
+        Ok(match self.mode {
+            Mode::BudgetShare => paycheck::Allocation::BudgetShare,
+            Mode::Fixed => {
+                let mut list = vec![];
+                for (name, amount) in self.fixed.iter() {
+                    if amount.trim().is_empty() {
+                        continue;
+                    }
+                    let a = amount
+                        .parse::<dollar::Dollar>()
+                        .map_err(|e| format!("{}: {}", name, e))?;
+                    list.push((name.clone(), a));
+                }
+                paycheck::Allocation::Fixed(list)
+            }
+            Mode::Priority => paycheck::Allocation::Priority(
+                self.priority
+                    .iter()
+                    .filter_map(|(n, on)| if *on { Some(n.clone()) } else { None })
+                    .collect(),
+            ),
+            Mode::Goals => paycheck::Allocation::Goals { date: t.date },
+        })
+    }
+
+    /// Shows the window for `t`, and returns true once it has been cashed
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        acc: &mut app::Account,
+        t: &transaction::Transaction,
+    ) -> bool {
+        let mut open = self.open;
+        let mut cashed = false;
+
+        // Keep the priority list in step with the vopes
+        let names: Vec<String> = acc
+            .get_portfolio()
+            .view_vopes()
+            .iter()
+            .filter(|v| !v.archived)
+            .map(|v| v.name.clone())
+            .collect();
+        self.priority.retain(|(n, _)| names.contains(n));
+        for n in names.iter() {
+            if !self.priority.iter().any(|(p, _)| p == n) {
+                self.priority.push((n.clone(), false));
+            }
+        }
+
+        egui::Window::new("Cash paycheck").open(&mut open).show(ctx, |ui| {
+            ui.label(t.to_string());
+            ui.separator();
+
+            let mut default = acc.get_portfolio().view_default_vope().map(str::to_string);
+            let before = default.clone();
+            ui.horizontal(|ui| {
+                ui.label("Leftover goes to:");
+                egui::ComboBox::from_id_source("default_vope")
+                    .selected_text(default.as_deref().unwrap_or("None"))
+                    .show_ui(ui, |ui| {
+                        for n in names.iter() {
+                            ui.selectable_value(&mut default, Some(n.clone()), n);
+                        }
+                    });
+            });
+            if default != before {
+                let res = acc.get_portfolio_mut().set_default_vope(default.as_deref());
+                self.status = res.and_then(|_| acc.save()).err().map(|e| e.to_string());
+            }
+
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut self.mode, Mode::BudgetShare, "By budget");
+                ui.radio_value(&mut self.mode, Mode::Fixed, "Fixed amounts");
+                ui.radio_value(&mut self.mode, Mode::Priority, "In order");
+                ui.radio_value(&mut self.mode, Mode::Goals, "By goals");
+            });
+            ui.separator();
+
+            match self.mode {
+                Mode::BudgetShare => {
+                    ui.label("Each vope gets its budget's share of the paycheck");
+                }
+                Mode::Fixed => {
+                    egui::Grid::new("paycheck_fixed").num_columns(2).show(ui, |ui| {
+                        for n in names.iter() {
+                            ui.label(n);
+                            ui.text_edit_singleline(self.fixed.entry(n.clone()).or_default());
+                            ui.end_row();
+                        }
+                    });
+                }
+                Mode::Priority => {
+                    ui.label("Checked vopes are filled up to their budget, top first");
+                    let mut swap = None;
+                    for (i, (n, on)) in self.priority.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            if ui.small_button("⏶").clicked() && i > 0 {
+                                swap = Some((i - 1, i));
+                            }
+                            if ui.small_button("⏷").clicked() {
+                                swap = Some((i, i + 1));
+                            }
+                            ui.checkbox(on, n.as_str());
+                        });
+                    }
+                    if let Some((a, b)) = swap {
+                        if b < self.priority.len() {
+                            self.priority.swap(a, b);
+                        }
+                    }
+                }
+                Mode::Goals => {
+                    ui.label("Vopes with a goal get what they still need this period, top first");
+                }
+            }
+            ui.separator();
+
+            // Show where the money would go before moving it
+            let plan = self.allocation(t).and_then(|how| {
+                acc.get_portfolio()
+                    .plan_paycheck(t.charge, &how)
+                    .map(|p| (how, p))
+                    .map_err(|e| e.to_string())
+            });
+
+            match &plan {
+                Ok((_, shares)) => {
+                    egui::Grid::new("paycheck_plan").striped(true).show(ui, |ui| {
+                        for (n, a) in shares.iter() {
+                            ui.label(n);
+                            ui.label(a.to_string());
+                            ui.end_row();
+                        }
+                    });
+                }
+                Err(msg) => {
+                    ui.colored_label(egui::Color32::RED, msg);
+                }
+            }
+
+            if ui
+                .add_enabled(plan.is_ok(), egui::Button::new("Cash"))
+                .clicked()
+            {
+                if let Ok((how, _)) = &plan {
+                    let cmd = journal::Command::CashPaycheck {
+                        transaction: t.clone(),
+                        allocation: how.clone(),
+                    };
+                    match acc.execute(cmd) {
+                        Ok(_) => {
+                            cashed = true;
+                            self.status = acc.save().err().map(|e| e.to_string());
+                        }
+                        Err(e) => self.status = Some(e.to_string()),
+                    }
+                }
+            }
+
+            if let Some(msg) = &self.status {
+                ui.colored_label(egui::Color32::RED, msg);
+            }
+        });
+
+        self.open = open && !cashed;
+        cashed
+    }
+}
@@ -0,0 +1,225 @@
+// Library imports
+use eframe::egui;
+// Local Library imports
+use app::import;
+
+/// A window for creating and changing CSV import profiles.
+///
+/// Every field is kept as text while editing, and only checked on save.
+pub struct ProfileEditor {
+    pub open: bool,
+    name: String,
+    delimiter: String,
+    skip_rows: String,
+    date_column: String,
+    date_format: String,
+    desc_column: String,
+    split: bool,
+    amount_column: String,
+    debit_column: String,
+    credit_column: String,
+    inverted: bool,
+    id_column: String,
+    status: Option<String>,
+}
+
+impl Default for ProfileEditor {
+    fn default() -> Self {
+        let mut s = Self {
+            open: false,
+            name: String::default(),
+            delimiter: String::default(),
+            skip_rows: String::default(),
+            date_column: String::default(),
+            date_format: String::default(),
+            desc_column: String::default(),
+            split: false,
+            amount_column: String::default(),
+            debit_column: String::default(),
+            credit_column: String::default(),
+            inverted: false,
+            id_column: String::default(),
+            status: None,
+        };
+        s.load(&import::ImportProfile::default());
+        s
+    }
+}
+
+impl ProfileEditor {
+    /// Fills the form with an existing profile
+    pub fn load(&mut self, p: &import::ImportProfile) {
+        self.name = p.name.clone();
+        self.delimiter = p.delimiter.to_string();
+        self.skip_rows = p.skip_rows.to_string();
+        self.date_column = p.date_column.to_string();
+        self.date_format = p.date_format.clone();
+        self.desc_column = p.desc_column.to_string();
+        match p.amount {
+            import::AmountColumns::Single(col) => {
+                self.split = false;
+                self.amount_column = col.to_string();
+            }
+            import::AmountColumns::Split { debit, credit } => {
+                self.split = true;
+                self.debit_column = debit.to_string();
+                self.credit_column = credit.to_string();
+            }
+        }
+        self.inverted = p.sign == import::SignConvention::Inverted;
+        self.id_column = p.id_column.map(|c| c.to_string()).unwrap_or_default();
+        self.status = None;
+    }
+
+    /// Checks the form, and turns it into a profile
+    fn build(&self) -> Result<import::ImportProfile, String> {
+        let num = |label: &str, s: &str| {
+            s.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("{} must be a whole number", label))
+        };
+
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("Give the profile a name".to_string());
+        }
+
+        let mut delimiter = self.delimiter.chars();
+        let delimiter = match (delimiter.next(), delimiter.next()) {
+            (Some(c), None) if c.is_ascii() => c,
+            _ => return Err("The delimiter must be a single ASCII character".to_string()),
+        };
+
+        let amount = if self.split {
+            import::AmountColumns::Split {
+                debit: num("Debit column", &self.debit_column)?,
+                credit: num("Credit column", &self.credit_column)?,
+            }
+        } else {
+            import::AmountColumns::Single(num("Amount column", &self.amount_column)?)
+        };
+
+        let id_column = if self.id_column.trim().is_empty() {
+            None
+        } else {
+            Some(num("ID column", &self.id_column)?)
+        };
+
+        Ok(import::ImportProfile {
+            name: name.to_string(),
+            delimiter,
+            skip_rows: num("Header rows", &self.skip_rows)?,
+            date_column: num("Date column", &self.date_column)?,
+            date_format: self.date_format.trim().to_string(),
+            desc_column: num("Description column", &self.desc_column)?,
+            amount,
+            sign: if self.inverted {
+                import::SignConvention::Inverted
+            } else {
+                import::SignConvention::Normal
+            },
+            id_column,
+        })
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, acc: &mut app::Account) {
+        let mut open = self.open;
+        let mut close = false;
+
+        egui::Window::new("Import Profile").open(&mut open).show(ctx, |ui| {
+            egui::Grid::new("profile_grid")
+                .num_columns(2)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Name:");
+                    ui.add(egui::TextEdit::singleline(&mut self.name));
+                    ui.end_row();
+
+                    ui.label("Delimiter:");
+                    ui.add(egui::TextEdit::singleline(&mut self.delimiter));
+                    ui.end_row();
+
+                    ui.label("Header rows:");
+                    ui.add(egui::TextEdit::singleline(&mut self.skip_rows));
+                    ui.end_row();
+
+                    ui.label("Date column:");
+                    ui.add(egui::TextEdit::singleline(&mut self.date_column));
+                    ui.end_row();
+
+                    ui.label("Date format:")
+                        .on_hover_text("For example %m/%d/%Y or %Y-%m-%d");
+                    ui.add(egui::TextEdit::singleline(&mut self.date_format));
+                    ui.end_row();
+
+                    ui.label("Description column:");
+                    ui.add(egui::TextEdit::singleline(&mut self.desc_column));
+                    ui.end_row();
+
+                    ui.label("Amount:");
+                    ui.checkbox(&mut self.split, "Separate debit and credit columns");
+                    ui.end_row();
+
+                    if self.split {
+                        ui.label("Debit column:");
+                        ui.add(egui::TextEdit::singleline(&mut self.debit_column));
+                        ui.end_row();
+
+                        ui.label("Credit column:");
+                        ui.add(egui::TextEdit::singleline(&mut self.credit_column));
+                        ui.end_row();
+                    } else {
+                        ui.label("Amount column:");
+                        ui.add(egui::TextEdit::singleline(&mut self.amount_column));
+                        ui.end_row();
+                    }
+
+                    ui.label("Sign:");
+                    ui.checkbox(&mut self.inverted, "Spending is positive");
+                    ui.end_row();
+
+                    ui.label("ID column (optional):");
+                    ui.add(egui::TextEdit::singleline(&mut self.id_column));
+                    ui.end_row();
+                });
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui.button("save").clicked() {
+                    let res = self
+                        .build()
+                        .and_then(|p| acc.add_profile(p, true).map_err(|e| e.to_string()))
+                        .and_then(|_| acc.save().map_err(|e| e.to_string()));
+
+                    match res {
+                        Ok(_) => close = true,
+                        Err(e) => self.status = Some(e),
+                    }
+                }
+
+                if ui.button("cancel").clicked() {
+                    close = true;
+                }
+
+                if ui.button("Delete").clicked() {
+                    let res = acc
+                        .remove_profile(&self.name)
+                        .and_then(|_| acc.save());
+
+                    match res {
+                        Ok(_) => close = true,
+                        Err(e) => self.status = Some(e.to_string()),
+                    }
+                }
+            });
+
+            if let Some(msg) = &self.status {
+                ui.colored_label(egui::Color32::RED, msg);
+            }
+        });
+
+        self.open = open && !close;
+    }
+}
@@ -0,0 +1,289 @@
+// Library imports
+use eframe::egui;
+use std::collections;
+// Local Library imports
+use app::{dollar, rules};
+
+const WEEKDAYS: [rules::Weekday; 7] = [
+    rules::Weekday::Mon,
+    rules::Weekday::Tue,
+    rules::Weekday::Wed,
+    rules::Weekday::Thu,
+    rules::Weekday::Fri,
+    rules::Weekday::Sat,
+    rules::Weekday::Sun,
+];
+
+#[derive(PartialEq, Clone, Copy)]
+enum DateKind {
+    Any,
+    DayOfMonth,
+    Weekdays,
+}
+
+/// A window listing the auto-assign rules, with a form to change them.
+pub struct RuleEditor {
+    pub open: bool,
+    name: String,
+    desc_pattern: String,
+    min_amount: String,
+    max_amount: String,
+    date_kind: DateKind,
+    first_day: u32,
+    last_day: u32,
+    weekdays: [bool; 7],
+    ignore: bool,
+    targets: collections::HashMap<String, (bool, f32)>, // Vope name, if it is on, and its weight
+    status: Option<String>,
+}
+
+impl Default for RuleEditor {
+    fn default() -> Self {
+        Self {
+            open: false,
+            name: String::default(),
+            desc_pattern: String::default(),
+            min_amount: String::default(),
+            max_amount: String::default(),
+            date_kind: DateKind::Any,
+            first_day: 1,
+            last_day: 31,
+            weekdays: [false; 7],
+            ignore: false,
+            targets: collections::HashMap::default(),
+            status: None,
+        }
+    }
+}
+
+impl RuleEditor {
+    /// Fills the form with an existing rule
+    fn load(&mut self, r: &rules::Rule) {
+        *self = Self {
+            open: self.open,
+            name: r.name.clone(),
+            desc_pattern: r.desc_pattern.clone().unwrap_or_default(),
+            min_amount: r.min_amount.map(|d| d.to_string()).unwrap_or_default(),
+            max_amount: r.max_amount.map(|d| d.to_string()).unwrap_or_default(),
+            ..Self::default()
+        };
+
+        match &r.date_pattern {
+            Some(rules::DatePattern::DayOfMonth { first, last }) => {
+                self.date_kind = DateKind::DayOfMonth;
+                self.first_day = *first;
+                self.last_day = *last;
+            }
+            Some(rules::DatePattern::Weekdays(days)) => {
+                self.date_kind = DateKind::Weekdays;
+                for (i, d) in WEEKDAYS.iter().enumerate() {
+                    self.weekdays[i] = days.contains(d);
+                }
+            }
+            None => {}
+        }
+
+        match &r.target {
+            rules::Target::Vopes(list) => {
+                for (n, w) in list {
+                    self.targets.insert(n.clone(), (true, *w));
+                }
+            }
+            rules::Target::Ignore => self.ignore = true,
+        }
+    }
+
+    /// Checks the form, and turns it into a rule
+    fn build(&self) -> Result<rules::Rule, String> {
+        let amount = |label: &str, s: &str| {
+            if s.trim().is_empty() {
+                Ok(None)
+            } else {
+                s.parse::<dollar::Dollar>()
+                    .map(Some)
+                    .map_err(|e| format!("{}: {}", label, e))
+            }
+        };
+
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("Give the rule a name".to_string());
+        }
+
+        let date_pattern = match self.date_kind {
+            DateKind::Any => None,
+            DateKind::DayOfMonth => Some(rules::DatePattern::DayOfMonth {
+                first: self.first_day,
+                last: self.last_day,
+            }),
+            DateKind::Weekdays => Some(rules::DatePattern::Weekdays(
+                WEEKDAYS
+                    .iter()
+                    .zip(self.weekdays)
+                    .filter_map(|(d, on)| if on { Some(*d) } else { None })
+                    .collect(),
+            )),
+        };
+
+        let target = if self.ignore {
+            rules::Target::Ignore
+        } else {
+            let list: Vec<(String, f32)> = self
+                .targets
+                .iter()
+                .filter_map(|(n, (on, w))| if *on { Some((n.clone(), *w)) } else { None })
+                .collect();
+
+            if list.is_empty() {
+                return Err("Pick at least one vope, or Ignore".to_string());
+            }
+            rules::Target::Vopes(list)
+        };
+
+        Ok(rules::Rule {
+            name: name.to_string(),
+            desc_pattern: Some(self.desc_pattern.trim())
+                .filter(|p| !p.is_empty())
+                .map(str::to_string),
+            min_amount: amount("Smallest amount", &self.min_amount)?,
+            max_amount: amount("Largest amount", &self.max_amount)?,
+            date_pattern,
+            target,
+        })
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, acc: &mut app::Account) {
+        let mut open = self.open;
+        let mut changed: Option<Result<(), app::Error>> = None;
+
+        egui::Window::new("Rules").open(&mut open).show(ctx, |ui| {
+            ui.columns(2, |cols| {
+                // The rules, in the order they are tried
+                cols[0].label("First matching rule wins");
+                cols[0].separator();
+
+                let names: Vec<String> = acc.get_rules().iter().map(|r| r.name.clone()).collect();
+                for (i, name) in names.iter().enumerate() {
+                    cols[0].horizontal(|ui| {
+                        if ui.small_button("⏶").clicked() {
+                            changed = Some(acc.move_rule(name, i.saturating_sub(1)));
+                        }
+                        if ui.small_button("⏷").clicked() {
+                            changed = Some(acc.move_rule(name, i + 1));
+                        }
+                        if ui
+                            .selectable_label(self.name.eq_ignore_ascii_case(name), name)
+                            .clicked()
+                        {
+                            if let Some(r) = acc.get_rules().get(i) {
+                                self.load(&r.clone());
+                            }
+                        }
+                    });
+                }
+
+                if cols[0].button("New rule").clicked() {
+                    *self = Self {
+                        open: true,
+                        ..Self::default()
+                    };
+                }
+
+                // The form for the selected rule
+                let ui = &mut cols[1];
+                egui::Grid::new("rule_grid")
+                    .num_columns(2)
+                    .spacing([20.0, 4.0])
+                    .show(ui, |ui| {
+                        ui.label("Name:");
+                        ui.text_edit_singleline(&mut self.name);
+                        ui.end_row();
+
+                        ui.label("Description matches:")
+                            .on_hover_text("A regular expression, case is ignored");
+                        ui.text_edit_singleline(&mut self.desc_pattern);
+                        ui.end_row();
+
+                        ui.label("Smallest amount:");
+                        ui.text_edit_singleline(&mut self.min_amount);
+                        ui.end_row();
+
+                        ui.label("Largest amount:");
+                        ui.text_edit_singleline(&mut self.max_amount);
+                        ui.end_row();
+
+                        ui.label("Date:");
+                        ui.horizontal(|ui| {
+                            ui.radio_value(&mut self.date_kind, DateKind::Any, "Any");
+                            ui.radio_value(&mut self.date_kind, DateKind::DayOfMonth, "Days of month");
+                            ui.radio_value(&mut self.date_kind, DateKind::Weekdays, "Weekdays");
+                        });
+                        ui.end_row();
+
+                        match self.date_kind {
+                            DateKind::DayOfMonth => {
+                                ui.label("");
+                                ui.horizontal(|ui| {
+                                    ui.add(egui::DragValue::new(&mut self.first_day).range(1..=31));
+                                    ui.label("to");
+                                    ui.add(egui::DragValue::new(&mut self.last_day).range(1..=31));
+                                });
+                                ui.end_row();
+                            }
+                            DateKind::Weekdays => {
+                                ui.label("");
+                                ui.horizontal(|ui| {
+                                    for (d, on) in WEEKDAYS.iter().zip(self.weekdays.iter_mut()) {
+                                        ui.checkbox(on, d.to_string());
+                                    }
+                                });
+                                ui.end_row();
+                            }
+                            DateKind::Any => {}
+                        }
+
+                        ui.label("Assign to:");
+                        ui.checkbox(&mut self.ignore, "Ignore");
+                        ui.end_row();
+
+                        if !self.ignore {
+                            for v in acc.get_portfolio().view_vopes().iter().filter(|v| !v.archived) {
+                                let (on, w) = self
+                                    .targets
+                                    .entry(v.name.clone())
+                                    .or_insert((false, 1.0));
+                                ui.checkbox(on, v.name.as_str());
+                                ui.add_enabled(*on, egui::DragValue::new(w).prefix("weight "));
+                                ui.end_row();
+                            }
+                        }
+                    });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui.button("save").clicked() {
+                        match self.build() {
+                            Ok(r) => changed = Some(acc.add_rule(r, true)),
+                            Err(e) => self.status = Some(e),
+                        }
+                    }
+
+                    if ui.button("Delete").clicked() {
+                        changed = Some(acc.remove_rule(&self.name));
+                    }
+                });
+
+                if let Some(msg) = &self.status {
+                    ui.colored_label(egui::Color32::RED, msg);
+                }
+            });
+        });
+
+        if let Some(res) = changed {
+            self.status = res.and_then(|_| acc.save()).err().map(|e| e.to_string());
+        }
+
+        self.open = open;
+    }
+}
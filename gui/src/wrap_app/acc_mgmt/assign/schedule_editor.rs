@@ -0,0 +1,240 @@
+// Library imports
+use eframe::egui;
+use std::collections;
+// Local Library imports
+use app::{dollar, recurring, rules};
+
+const FREQUENCIES: [(recurring::Frequency, &str); 5] = [
+    (recurring::Frequency::Daily, "Daily"),
+    (recurring::Frequency::Weekly, "Weekly"),
+    (recurring::Frequency::Biweekly, "Every two weeks"),
+    (recurring::Frequency::Monthly, "Monthly"),
+    (recurring::Frequency::Yearly, "Yearly"),
+];
+
+/// A window listing the recurring transactions, with a form to change them
+pub struct ScheduleEditor {
+    pub open: bool,
+    name: String,
+    desc: String,
+    amount: String,
+    frequency: recurring::Frequency,
+    start: String,
+    end: String,
+    ignore: bool,
+    targets: collections::HashMap<String, (bool, f32)>, // Vope name, if it is on, and its weight
+    last_posted: Option<chrono::NaiveDate>,
+    status: Option<String>,
+}
+
+impl Default for ScheduleEditor {
+    fn default() -> Self {
+        Self {
+            open: false,
+            name: String::default(),
+            desc: String::default(),
+            amount: String::default(),
+            frequency: recurring::Frequency::Monthly,
+            start: chrono::Local::now().date_naive().to_string(),
+            end: String::default(),
+            ignore: false,
+            targets: collections::HashMap::default(),
+            last_posted: None,
+            status: None,
+        }
+    }
+}
+
+impl ScheduleEditor {
+    /// Fills the form with an existing recurring transaction
+    fn load(&mut self, r: &recurring::Recurring) {
+        *self = Self {
+            open: self.open,
+            name: r.name.clone(),
+            desc: r.desc.clone(),
+            amount: r.amount.to_string(),
+            frequency: r.frequency,
+            start: r.start.to_string(),
+            end: r.end.map(|d| d.to_string()).unwrap_or_default(),
+            last_posted: r.last_posted,
+            ..Self::default()
+        };
+
+        match &r.target {
+            rules::Target::Vopes(list) => {
+                for (n, w) in list {
+                    self.targets.insert(n.clone(), (true, *w));
+                }
+            }
+            rules::Target::Ignore => self.ignore = true,
+        }
+    }
+
+    /// Checks the form, and turns it into a recurring transaction
+    fn build(&self) -> Result<recurring::Recurring, String> {
+        let date = |label: &str, s: &str| {
+            chrono::NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d")
+                .map_err(|_| format!("{} must look like 2024-01-31", label))
+        };
+
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("Give the recurring transaction a name".to_string());
+        }
+
+        let amount = self
+            .amount
+            .parse::<dollar::Dollar>()
+            .map_err(|e| format!("Amount: {}", e))?;
+
+        let start = date("Start", &self.start)?;
+        let end = if self.end.trim().is_empty() {
+            None
+        } else {
+            Some(date("End", &self.end)?)
+        };
+
+        let target = if self.ignore {
+            rules::Target::Ignore
+        } else {
+            let list: Vec<(String, f32)> = self
+                .targets
+                .iter()
+                .filter_map(|(n, (on, w))| if *on { Some((n.clone(), *w)) } else { None })
+                .collect();
+
+            if list.is_empty() {
+                return Err("Pick at least one vope, or Ignore".to_string());
+            }
+            rules::Target::Vopes(list)
+        };
+
+        Ok(recurring::Recurring {
+            name: name.to_string(),
+            desc: self.desc.trim().to_string(),
+            amount,
+            frequency: self.frequency,
+            start,
+            end,
+            target,
+            // Moving the start back does not post what was skipped
+            last_posted: self.last_posted,
+        })
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, acc: &mut app::Account) {
+        let mut open = self.open;
+        let mut changed: Option<Result<(), app::Error>> = None;
+
+        egui::Window::new("Recurring").open(&mut open).show(ctx, |ui| {
+            ui.columns(2, |cols| {
+                cols[0].label("Posted when they come due");
+                cols[0].separator();
+
+                for r in acc.get_recurring() {
+                    let label = format!("{} ({})", r.name, r.amount);
+                    if cols[0]
+                        .selectable_label(self.name.eq_ignore_ascii_case(&r.name), label)
+                        .clicked()
+                    {
+                        self.load(&r.clone());
+                    }
+                }
+
+                if cols[0].button("New").clicked() {
+                    *self = Self {
+                        open: true,
+                        ..Self::default()
+                    };
+                }
+
+                let ui = &mut cols[1];
+                egui::Grid::new("schedule_grid")
+                    .num_columns(2)
+                    .spacing([20.0, 4.0])
+                    .show(ui, |ui| {
+                        ui.label("Name:");
+                        ui.text_edit_singleline(&mut self.name);
+                        ui.end_row();
+
+                        ui.label("Description:");
+                        ui.text_edit_singleline(&mut self.desc);
+                        ui.end_row();
+
+                        ui.label("Amount:")
+                            .on_hover_text("Negative for money going out");
+                        ui.text_edit_singleline(&mut self.amount);
+                        ui.end_row();
+
+                        ui.label("Repeats:");
+                        egui::ComboBox::from_id_source("schedule_frequency")
+                            .selected_text(
+                                FREQUENCIES
+                                    .iter()
+                                    .find(|(f, _)| *f == self.frequency)
+                                    .map_or("", |(_, n)| n),
+                            )
+                            .show_ui(ui, |ui| {
+                                for (f, n) in FREQUENCIES {
+                                    ui.selectable_value(&mut self.frequency, f, n);
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("First date:");
+                        ui.text_edit_singleline(&mut self.start);
+                        ui.end_row();
+
+                        ui.label("Last date:").on_hover_text("Leave blank to repeat forever");
+                        ui.text_edit_singleline(&mut self.end);
+                        ui.end_row();
+
+                        ui.label("Assign to:");
+                        ui.checkbox(&mut self.ignore, "Ignore");
+                        ui.end_row();
+
+                        if !self.ignore {
+                            for v in acc.get_portfolio().view_vopes().iter().filter(|v| !v.archived) {
+                                let (on, w) = self
+                                    .targets
+                                    .entry(v.name.clone())
+                                    .or_insert((false, 1.0));
+                                ui.checkbox(on, v.name.as_str());
+                                ui.add_enabled(*on, egui::DragValue::new(w).prefix("weight "));
+                                ui.end_row();
+                            }
+                        }
+                    });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui.button("save").clicked() {
+                        match self.build() {
+                            Ok(r) => {
+                                changed = Some(acc.add_recurring(r, true).map(|_| {
+                                    acc.post_due(chrono::Local::now().date_naive());
+                                }))
+                            }
+                            Err(e) => self.status = Some(e),
+                        }
+                    }
+
+                    if ui.button("Delete").clicked() {
+                        changed = Some(acc.remove_recurring(&self.name));
+                    }
+                });
+
+                if let Some(msg) = &self.status {
+                    ui.colored_label(egui::Color32::RED, msg);
+                }
+            });
+        });
+
+        if let Some(res) = changed {
+            self.status = res.and_then(|_| acc.save()).err().map(|e| e.to_string());
+        }
+
+        self.open = open;
+    }
+}
@@ -0,0 +1,189 @@
+use app::{budget, goal};
+use eframe::egui;
+
+mod goal_editor;
+
+/// Budgeted, spent and available per vope, one period at a time
+pub struct Budget {
+    date: chrono::NaiveDate, // Any day in the period being shown
+    status: Option<String>,
+    goal_editor: goal_editor::GoalEditor,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self {
+            date: chrono::Local::now().date_naive(),
+            status: None,
+            goal_editor: goal_editor::GoalEditor::default(),
+        }
+    }
+}
+
+impl Budget {
+    fn period_settings(&mut self, ui: &mut egui::Ui, acc: &mut app::Account) {
+        let mut period = acc.get_portfolio().view_period();
+
+        ui.horizontal(|ui| {
+            ui.label("Periods:");
+
+            let monthly = matches!(period, budget::Period::Monthly { .. });
+            if ui.radio(monthly, "Monthly").clicked() && !monthly {
+                period = budget::Period::default();
+            }
+            if ui.radio(!monthly, "Weekly").clicked() && monthly {
+                period = budget::Period::Weeks {
+                    weeks: 1,
+                    anchor: self.date,
+                };
+            }
+
+            match &mut period {
+                budget::Period::Monthly { start_day } => {
+                    ui.label("starting on day");
+                    ui.add(egui::DragValue::new(start_day).range(1..=28));
+                }
+                budget::Period::Weeks { weeks, .. } => {
+                    ui.label("lasting");
+                    ui.add(egui::DragValue::new(weeks).range(1..=52).suffix(" weeks"));
+                }
+            }
+        });
+
+        if period != acc.get_portfolio().view_period() {
+            let res = acc.get_portfolio_mut().set_period(period);
+            self.status = res.and_then(|_| acc.save()).err().map(|e| e.to_string());
+        }
+    }
+}
+
+impl super::AccDisp for Budget {
+    fn disp_name(&self) -> String {
+        "Budget".to_string()
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame, acc: &mut Box<app::Account>) {
+        egui::TopBottomPanel::top("budget_header").show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.heading("Budget");
+            });
+
+            self.period_settings(ui, acc);
+
+            if let Some(msg) = &self.status {
+                ui.colored_label(egui::Color32::RED, msg);
+            }
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let period = acc.get_portfolio().view_period();
+            let start = period.start_of(self.date);
+            let end = period.next(start);
+
+            ui.horizontal(|ui| {
+                if ui.button("⏴").clicked() {
+                    self.date = period.prev(start);
+                }
+                ui.label(format!("{} to {}", start, end.pred_opt().unwrap_or(end)));
+                if ui.button("⏵").clicked() {
+                    self.date = end;
+                }
+                if ui.button("Today").clicked() {
+                    self.date = chrono::Local::now().date_naive();
+                }
+            });
+            ui.separator();
+
+            let report = acc.get_portfolio().period_report(self.date);
+            let mut changed = None;
+            let mut edit_goal = None;
+
+            egui::Grid::new("budget_view")
+                .num_columns(7)
+                .spacing([40.0, 8.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Vope");
+                    ui.label("At period end");
+                    ui.label("Carried");
+                    ui.label("Budgeted");
+                    ui.label("Spent");
+                    ui.label("Available");
+                    ui.label("Goal");
+                    ui.end_row();
+
+                    let listed = report.iter().zip(acc.get_portfolio().view_vopes()).filter(|(_, v)| !v.archived);
+                    for ((name, s), v) in listed {
+                        ui.label(name);
+
+                        let mut rollover = v.rollover;
+                        egui::ComboBox::from_id_source(("rollover", name))
+                            .selected_text(rollover_name(rollover))
+                            .show_ui(ui, |ui| {
+                                for r in [
+                                    budget::Rollover::Carry,
+                                    budget::Rollover::CarrySurplus,
+                                    budget::Rollover::CarryDeficit,
+                                    budget::Rollover::Reset,
+                                ] {
+                                    ui.selectable_value(&mut rollover, r, rollover_name(r));
+                                }
+                            });
+                        if rollover != v.rollover {
+                            changed = Some((name.clone(), rollover));
+                        }
+
+                        ui.label(s.carried.to_string());
+                        ui.label(s.budgeted.to_string());
+                        ui.label(s.spent.to_string());
+                        if s.available.is_negative() {
+                            ui.colored_label(egui::Color32::RED, s.available.to_string());
+                        } else {
+                            ui.label(s.available.to_string());
+                        }
+
+                        ui.horizontal(|ui| {
+                            if let (Some(g), Ok(Some(p))) = (&v.goal, acc.get_portfolio().goal_progress(name, self.date)) {
+                                ui.add(egui::ProgressBar::new(p.fraction).desired_width(100.0).show_percentage())
+                                    .on_hover_text(goal_text(g, &p));
+                            }
+                            if ui.small_button("Goal...").clicked() {
+                                edit_goal = Some(v.clone());
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+
+            if let Some((name, rollover)) = changed {
+                let res = acc.get_portfolio_mut().set_rollover(&name, rollover);
+                self.status = res.and_then(|_| acc.save()).err().map(|e| e.to_string());
+            }
+            if let Some(v) = edit_goal {
+                self.goal_editor.edit(&v);
+            }
+        });
+
+        self.goal_editor.show(ctx, acc);
+    }
+}
+
+/// Describes a goal, and what it needs this period
+pub fn goal_text(g: &goal::Goal, p: &goal::Progress) -> String {
+    match g {
+        goal::Goal::TargetByDate { target, date } => {
+            format!("{} by {}: {} a period, {} still needed this period", target, date, p.per_period, p.required)
+        }
+        goal::Goal::Contribution { amount } => format!("{} every period, {} still needed this period", amount, p.required),
+        goal::Goal::SpendDown { date } => format!("Spending down by {}: {} a period", date, p.per_period),
+    }
+}
+
+fn rollover_name(r: budget::Rollover) -> &'static str {
+    match r {
+        budget::Rollover::Carry => "Carry everything",
+        budget::Rollover::CarrySurplus => "Carry surplus",
+        budget::Rollover::CarryDeficit => "Carry deficit",
+        budget::Rollover::Reset => "Reset",
+    }
+}
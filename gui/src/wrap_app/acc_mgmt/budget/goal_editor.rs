@@ -0,0 +1,127 @@
+// Library imports
+use eframe::egui;
+// Local Library imports
+use app::{dollar, goal, vope};
+
+#[derive(PartialEq, Clone, Copy)]
+enum Kind {
+    None,
+    TargetByDate,
+    Contribution,
+    SpendDown,
+}
+
+/// A window that sets or clears one vope's savings goal
+pub struct GoalEditor {
+    pub open: bool,
+    vope: String,
+    kind: Kind,
+    amount: String,
+    date: String,
+    status: Option<String>,
+}
+
+impl Default for GoalEditor {
+    fn default() -> Self {
+        Self {
+            open: false,
+            vope: String::default(),
+            kind: Kind::None,
+            amount: String::default(),
+            date: String::default(),
+            status: None,
+        }
+    }
+}
+
+impl GoalEditor {
+    /// Opens the window on a vope, filled with its goal
+    pub fn edit(&mut self, v: &vope::Vope) {
+        let (kind, amount, date) = match v.goal {
+            None => (Kind::None, None, None),
+            Some(goal::Goal::TargetByDate { target, date }) => (Kind::TargetByDate, Some(target), Some(date)),
+            Some(goal::Goal::Contribution { amount }) => (Kind::Contribution, Some(amount), None),
+            Some(goal::Goal::SpendDown { date }) => (Kind::SpendDown, None, Some(date)),
+        };
+
+        *self = Self {
+            open: true,
+            vope: v.name.clone(),
+            kind,
+            amount: amount.map(|a| a.to_decimal_string()).unwrap_or_default(),
+            date: date.map(|d| d.to_string()).unwrap_or_default(),
+            status: None,
+        };
+    }
+
+    /// Checks the form, and turns it into a goal
+    fn build(&self) -> Result<Option<goal::Goal>, String> {
+        let amount = || {
+            self.amount
+                .parse::<dollar::Dollar>()
+                .map_err(|e| format!("Amount: {}", e))
+        };
+        let date = || {
+            chrono::NaiveDate::parse_from_str(self.date.trim(), "%Y-%m-%d")
+                .map_err(|_| "Dates look like 2024-01-31".to_string())
+        };
+
+        Ok(match self.kind {
+            Kind::None => None,
+            Kind::TargetByDate => Some(goal::Goal::TargetByDate {
+                target: amount()?,
+                date: date()?,
+            }),
+            Kind::Contribution => Some(goal::Goal::Contribution { amount: amount()? }),
+            Kind::SpendDown => Some(goal::Goal::SpendDown { date: date()? }),
+        })
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, acc: &mut app::Account) {
+        let mut open = self.open;
+        let mut saved = false;
+
+        egui::Window::new(format!("Goal for {}", self.vope))
+            .id(egui::Id::new("goal_editor"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.radio_value(&mut self.kind, Kind::None, "No goal");
+                ui.radio_value(&mut self.kind, Kind::TargetByDate, "Save up an amount by a date");
+                ui.radio_value(&mut self.kind, Kind::Contribution, "Put in an amount every period");
+                ui.radio_value(&mut self.kind, Kind::SpendDown, "Spend it evenly until a date");
+                ui.separator();
+
+                egui::Grid::new("goal_form").num_columns(2).show(ui, |ui| {
+                    if matches!(self.kind, Kind::TargetByDate | Kind::Contribution) {
+                        ui.label("Amount:");
+                        ui.text_edit_singleline(&mut self.amount);
+                        ui.end_row();
+                    }
+                    if matches!(self.kind, Kind::TargetByDate | Kind::SpendDown) {
+                        ui.label("By (YYYY-MM-DD):");
+                        ui.text_edit_singleline(&mut self.date);
+                        ui.end_row();
+                    }
+                });
+
+                if ui.button("Save").clicked() {
+                    let res = self
+                        .build()
+                        .and_then(|g| {
+                            acc.get_portfolio_mut()
+                                .set_goal(&self.vope, g)
+                                .and_then(|_| acc.save())
+                                .map_err(|e| e.to_string())
+                        });
+                    saved = res.is_ok();
+                    self.status = res.err();
+                }
+
+                if let Some(msg) = &self.status {
+                    ui.colored_label(egui::Color32::RED, msg);
+                }
+            });
+
+        self.open = open && !saved;
+    }
+}
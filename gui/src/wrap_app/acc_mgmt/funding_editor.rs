@@ -0,0 +1,155 @@
+// Library imports
+use eframe::egui;
+// Local Library imports
+use app::{dollar, funding};
+
+/// A window listing the real accounts the money is kept in, with a form to change them.
+pub struct FundingEditor {
+    pub open: bool,
+    name: String,
+    kind: funding::Kind,
+    institution: String,
+    number: String,
+    opening_balance: String,
+    status: Option<String>,
+}
+
+impl Default for FundingEditor {
+    fn default() -> Self {
+        Self {
+            open: false,
+            name: String::default(),
+            kind: funding::Kind::Checking,
+            institution: String::default(),
+            number: String::default(),
+            opening_balance: String::default(),
+            status: None,
+        }
+    }
+}
+
+impl FundingEditor {
+    /// Fills the form with an existing account
+    fn load(&mut self, f: &funding::FundingAccount) {
+        *self = Self {
+            open: self.open,
+            name: f.name.clone(),
+            kind: f.kind,
+            institution: f.institution.clone(),
+            number: f.number.clone().unwrap_or_default(),
+            opening_balance: f.opening_balance.to_string(),
+            status: None,
+        };
+    }
+
+    /// Checks the form, and turns it into an account
+    fn build(&self) -> Result<funding::FundingAccount, String> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("Give the account a name".to_string());
+        }
+
+        let opening_balance = if self.opening_balance.trim().is_empty() {
+            dollar::Dollar::ZERO
+        } else {
+            self.opening_balance
+                .parse::<dollar::Dollar>()
+                .map_err(|e| format!("Opening balance: {}", e))?
+        };
+
+        Ok(funding::FundingAccount {
+            name: name.to_string(),
+            kind: self.kind,
+            institution: self.institution.trim().to_string(),
+            number: Some(self.number.trim())
+                .filter(|n| !n.is_empty())
+                .map(str::to_string),
+            opening_balance,
+        })
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, acc: &mut app::Account) {
+        let mut open = self.open;
+        let mut changed: Option<Result<(), app::Error>> = None;
+
+        egui::Window::new("Accounts").open(&mut open).show(ctx, |ui| {
+            ui.columns(2, |cols| {
+                for (f, balance) in acc.funding_balances() {
+                    let label = format!("{} ({})", f.name, balance);
+                    if cols[0]
+                        .selectable_label(self.name.eq_ignore_ascii_case(&f.name), label)
+                        .clicked()
+                    {
+                        self.load(f);
+                    }
+                }
+
+                if cols[0].button("New account").clicked() {
+                    *self = Self {
+                        open: true,
+                        ..Self::default()
+                    };
+                }
+
+                // The form for the selected account
+                let ui = &mut cols[1];
+                egui::Grid::new("funding_grid")
+                    .num_columns(2)
+                    .spacing([20.0, 4.0])
+                    .show(ui, |ui| {
+                        ui.label("Name:");
+                        ui.text_edit_singleline(&mut self.name);
+                        ui.end_row();
+
+                        ui.label("Type:");
+                        egui::ComboBox::from_id_source("funding_kind")
+                            .selected_text(self.kind.to_string())
+                            .show_ui(ui, |ui| {
+                                for k in funding::Kind::ALL {
+                                    ui.selectable_value(&mut self.kind, k, k.to_string());
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Institution:");
+                        ui.text_edit_singleline(&mut self.institution);
+                        ui.end_row();
+
+                        ui.label("Account number:")
+                            .on_hover_text("OFX statements with this number are tagged with the account");
+                        ui.text_edit_singleline(&mut self.number);
+                        ui.end_row();
+
+                        ui.label("Opening balance:");
+                        ui.text_edit_singleline(&mut self.opening_balance);
+                        ui.end_row();
+                    });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui.button("save").clicked() {
+                        match self.build() {
+                            Ok(f) => changed = Some(acc.add_funding(f, true)),
+                            Err(e) => self.status = Some(e),
+                        }
+                    }
+
+                    if ui.button("Delete").clicked() {
+                        changed = Some(acc.remove_funding(&self.name));
+                    }
+                });
+
+                if let Some(msg) = &self.status {
+                    ui.colored_label(egui::Color32::RED, msg);
+                }
+            });
+        });
+
+        if let Some(res) = changed {
+            self.status = res.and_then(|_| acc.save()).err().map(|e| e.to_string());
+        }
+
+        self.open = open;
+    }
+}
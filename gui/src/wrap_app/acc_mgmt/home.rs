@@ -23,6 +23,7 @@ impl super::AccDisp for Home {
 
                 let t = acc.get_portfolio().view_vopes()
                     .iter()
+                    .filter(|d| !d.archived)
                     .map(|d| (d.name.as_str(), d.budget.as_f64()))
                     .collect::<Vec<(&str, f64)>>();
 
@@ -0,0 +1,95 @@
+// Library imports
+use eframe::egui;
+// Local Library imports
+use app::ledger;
+
+/// A window showing balances as of a date, and checking them against the ledger
+pub struct LedgerCheck {
+    pub open: bool,
+    date: String,
+    mismatches: Option<Vec<ledger::Mismatch>>, // The result of the last check
+    status: Option<String>,
+}
+
+impl Default for LedgerCheck {
+    fn default() -> Self {
+        Self {
+            open: false,
+            date: chrono::Local::now().date_naive().to_string(),
+            mismatches: None,
+            status: None,
+        }
+    }
+}
+
+impl LedgerCheck {
+    pub fn show(&mut self, ctx: &egui::Context, acc: &mut app::Account) {
+        let mut open = self.open;
+
+        egui::Window::new("Ledger").open(&mut open).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Balances as of:");
+                ui.text_edit_singleline(&mut self.date);
+            });
+
+            match chrono::NaiveDate::parse_from_str(self.date.trim(), "%Y-%m-%d") {
+                Ok(date) => {
+                    egui::Grid::new("ledger_balances").striped(true).show(ui, |ui| {
+                        for b in acc.get_portfolio().balances_as_of(date) {
+                            ui.label(b.name);
+                            ui.label(b.amount.to_string());
+                            ui.end_row();
+                        }
+                    });
+                }
+                Err(_) => {
+                    ui.colored_label(egui::Color32::RED, "Dates look like 2024-01-31");
+                }
+            }
+            ui.label(format!("{} events", acc.get_portfolio().view_ledger().events().len()));
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui.button("Verify").clicked() {
+                    self.mismatches = Some(acc.get_portfolio().verify());
+                }
+
+                let broken = self.mismatches.as_ref().is_some_and(|m| !m.is_empty());
+                if ui.add_enabled(broken, egui::Button::new("Rebuild")).clicked() {
+                    acc.get_portfolio_mut().rebuild();
+                    self.mismatches = Some(acc.get_portfolio().verify());
+                    self.status = acc.save().err().map(|e| e.to_string());
+                }
+            });
+
+            match &self.mismatches {
+                Some(list) if list.is_empty() => {
+                    ui.label("Every balance matches the ledger");
+                }
+                Some(list) => {
+                    egui::Grid::new("ledger_mismatches").striped(true).show(ui, |ui| {
+                        ui.label("Vope");
+                        ui.label("Stored");
+                        ui.label("Ledger");
+                        ui.end_row();
+
+                        let show = |d: Option<app::dollar::Dollar>| d.map_or("-".to_string(), |d| d.to_string());
+                        for m in list {
+                            ui.label(&m.name);
+                            ui.label(show(m.stored));
+                            ui.label(show(m.derived));
+                            ui.end_row();
+                        }
+                    });
+                }
+                None => {}
+            }
+
+            if let Some(msg) = &self.status {
+                ui.colored_label(egui::Color32::RED, msg);
+            }
+        });
+
+        self.open = open;
+    }
+}
@@ -0,0 +1,160 @@
+// Library imports
+use eframe::egui;
+// Local Library imports
+use app::{dollar, reconcile, transaction};
+
+/// A window for ticking transactions off against a bank statement
+pub struct Reconciler {
+    pub open: bool,
+    account: Option<String>, // The funding account the statement is for, or all of them
+    date: String,
+    balance: String,
+    status: Option<String>,
+}
+
+impl Default for Reconciler {
+    fn default() -> Self {
+        Self {
+            open: false,
+            account: None,
+            date: chrono::Local::now().date_naive().to_string(),
+            balance: String::default(),
+            status: None,
+        }
+    }
+}
+
+impl Reconciler {
+    /// Reads the form into the statement being reconciled
+    fn statement(&self) -> Result<reconcile::Reconciliation, String> {
+        let date = chrono::NaiveDate::parse_from_str(self.date.trim(), "%Y-%m-%d")
+            .map_err(|_| "Dates look like 2024-01-31".to_string())?;
+        let balance = self
+            .balance
+            .parse::<dollar::Dollar>()
+            .map_err(|e| format!("Statement balance: {}", e))?;
+
+        Ok(reconcile::Reconciliation {
+            account: self.account.clone(),
+            date,
+            balance,
+        })
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, acc: &mut app::Account) {
+        let mut open = self.open;
+
+        egui::Window::new("Reconcile").open(&mut open).show(ctx, |ui| {
+            egui::Grid::new("reconcile_form").num_columns(2).show(ui, |ui| {
+                ui.label("Account:");
+                egui::ComboBox::from_id_source("reconcile_account")
+                    .selected_text(self.account.as_deref().unwrap_or("All accounts"))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.account, None, "All accounts");
+                        for f in acc.get_funding() {
+                            ui.selectable_value(&mut self.account, Some(f.name.clone()), &f.name);
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Statement date:");
+                ui.text_edit_singleline(&mut self.date);
+                ui.end_row();
+
+                ui.label("Statement balance:");
+                ui.text_edit_singleline(&mut self.balance);
+                ui.end_row();
+            });
+
+            let last = acc
+                .get_portfolio()
+                .view_reconciliations()
+                .iter()
+                .rev()
+                .find(|r| r.account == self.account);
+            if let Some(r) = last {
+                ui.label(format!("Last reconciled {} at {}", r.date, r.balance));
+            }
+            ui.separator();
+
+            let rec = match self.statement() {
+                Ok(rec) => rec,
+                Err(msg) => {
+                    ui.colored_label(egui::Color32::RED, msg);
+                    return;
+                }
+            };
+
+            // Everything on the statement that is not locked yet
+            let list: Vec<transaction::Transaction> = acc
+                .get_portfolio()
+                .transactions()
+                .iter()
+                .filter(|t| {
+                    t.status != transaction::Status::Reconciled
+                        && reconcile::in_scope(t, rec.account.as_deref(), rec.date)
+                })
+                .cloned()
+                .collect();
+
+            let mut toggled = None;
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                egui::Grid::new("reconcile_list").striped(true).show(ui, |ui| {
+                    for t in list.iter() {
+                        let mut cleared = t.status == transaction::Status::Cleared;
+                        if ui.checkbox(&mut cleared, "").changed() {
+                            toggled = Some((t.id.clone(), cleared));
+                        }
+                        ui.label(t.date.to_string());
+                        ui.label(&t.desc);
+                        ui.label(t.charge.to_string());
+                        ui.end_row();
+                    }
+                });
+            });
+
+            if let Some((id, cleared)) = toggled {
+                let res = acc.get_portfolio_mut().set_cleared(&id, cleared);
+                self.status = res.and_then(|_| acc.save()).err().map(|e| e.to_string());
+            }
+            ui.separator();
+
+            match acc.reconcile_summary(&rec) {
+                Ok(summary) => {
+                    egui::Grid::new("reconcile_summary").num_columns(2).show(ui, |ui| {
+                        ui.label("Cleared balance:");
+                        ui.label(summary.cleared.to_string());
+                        ui.end_row();
+
+                        ui.label("Statement balance:");
+                        ui.label(summary.statement.to_string());
+                        ui.end_row();
+
+                        ui.label("Difference:");
+                        if summary.difference.is_zero() {
+                            ui.label(summary.difference.to_string());
+                        } else {
+                            ui.colored_label(egui::Color32::RED, summary.difference.to_string());
+                        }
+                        ui.end_row();
+                    });
+
+                    let balanced = summary.difference.is_zero();
+                    if ui.add_enabled(balanced, egui::Button::new("Finish")).clicked() {
+                        let res = acc.finish_reconciliation(rec);
+                        self.status = res.and_then(|_| acc.save()).err().map(|e| e.to_string());
+                    }
+                }
+                Err(e) => {
+                    ui.colored_label(egui::Color32::RED, e.to_string());
+                }
+            }
+
+            if let Some(msg) = &self.status {
+                ui.colored_label(egui::Color32::RED, msg);
+            }
+        });
+
+        self.open = open;
+    }
+}
@@ -0,0 +1,186 @@
+use app::{budget, report};
+use eframe::egui;
+
+/// How many months are shown when the screen is first opened
+const MONTHS: usize = 6;
+
+/// Spending per vope per month, income against expenses, and budget variance
+pub struct Reports {
+    from: String,
+    to: String,
+    date: chrono::NaiveDate, // Any day in the budget period the variance is for
+}
+
+impl Default for Reports {
+    fn default() -> Self {
+        let today = chrono::Local::now().date_naive();
+        let months = budget::Period::default();
+        let from = (1..MONTHS).fold(months.start_of(today), |d, _| months.prev(d));
+
+        Self {
+            from: from.to_string(),
+            to: today.to_string(),
+            date: today,
+        }
+    }
+}
+
+impl Reports {
+    /// Income and expense bars side by side, one pair per month
+    fn cash_flow(&self, ui: &mut egui::Ui, months: &[report::PeriodTotals]) {
+        let bars = |pick: fn(&report::PeriodTotals) -> app::dollar::Dollar, offset: f64| {
+            months
+                .iter()
+                .enumerate()
+                .map(|(i, m)| {
+                    egui_plot::Bar::new(i as f64 + offset, pick(m).as_f64())
+                        .width(0.4)
+                        .name(m.start.format("%b %Y"))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let income = egui_plot::BarChart::new(bars(|m| m.income, -0.2))
+            .name("Income")
+            .color(egui::Color32::from_rgb(90, 170, 90));
+        let expense = egui_plot::BarChart::new(bars(|m| m.expense, 0.2))
+            .name("Expenses")
+            .color(egui::Color32::from_rgb(200, 90, 90));
+
+        let labels: Vec<String> = months.iter().map(|m| m.start.format("%b %y").to_string()).collect();
+        egui_plot::Plot::new("cash_flow")
+            .legend(egui_plot::Legend::default())
+            .height(220.0)
+            .allow_zoom(false)
+            .allow_drag(false)
+            .allow_scroll(false)
+            .x_axis_formatter(move |mark, _range| {
+                let i = mark.value.round();
+                if (mark.value - i).abs() < 0.01 && i >= 0.0 {
+                    labels.get(i as usize).cloned().unwrap_or_default()
+                } else {
+                    String::new()
+                }
+            })
+            .show(ui, |plot_ui| {
+                plot_ui.bar_chart(income);
+                plot_ui.bar_chart(expense);
+            });
+    }
+
+    /// Rows of vopes, columns of months
+    fn spending_table(&self, ui: &mut egui::Ui, months: &[report::PeriodTotals]) {
+        egui::Grid::new("spending_table").striped(true).show(ui, |ui| {
+            ui.label("Vope");
+            for m in months {
+                ui.label(m.start.format("%b %Y").to_string());
+            }
+            ui.end_row();
+
+            let vopes = months.first().map_or(0, |m| m.spent.len());
+            for v in 0..vopes {
+                ui.label(&months[0].spent[v].0);
+                for m in months {
+                    ui.label(m.spent[v].1.to_string());
+                }
+                ui.end_row();
+            }
+
+            ui.label("Net");
+            for m in months {
+                let net = m.net();
+                if net.is_negative() {
+                    ui.colored_label(egui::Color32::RED, net.to_string());
+                } else {
+                    ui.label(net.to_string());
+                }
+            }
+            ui.end_row();
+        });
+    }
+
+    /// Budgeted against spent for each vope, in one budget period
+    fn variance_table(&mut self, ui: &mut egui::Ui, acc: &app::Account) {
+        let period = acc.get_portfolio().view_period();
+        let start = period.start_of(self.date);
+
+        ui.horizontal(|ui| {
+            if ui.button("⏴").clicked() {
+                self.date = period.prev(start);
+            }
+            ui.label(format!("{} to {}", start, period.next(start).pred_opt().unwrap_or(start)));
+            if ui.button("⏵").clicked() {
+                self.date = period.next(start);
+            }
+        });
+
+        egui::Grid::new("variance_table").striped(true).show(ui, |ui| {
+            ui.label("Vope");
+            ui.label("Budgeted");
+            ui.label("Spent");
+            ui.label("Variance");
+            ui.end_row();
+
+            for v in report::variance(acc.get_portfolio(), self.date) {
+                ui.label(&v.vope);
+                ui.label(v.budgeted.to_string());
+                ui.label(v.spent.to_string());
+                if v.variance.is_negative() {
+                    ui.colored_label(egui::Color32::RED, v.variance.to_string());
+                } else {
+                    ui.label(v.variance.to_string());
+                }
+                ui.end_row();
+            }
+        });
+    }
+}
+
+impl super::AccDisp for Reports {
+    fn disp_name(&self) -> String {
+        "Reports".to_string()
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame, acc: &mut Box<app::Account>) {
+        egui::TopBottomPanel::top("reports_header").show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.heading("Reports");
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("From:");
+                ui.text_edit_singleline(&mut self.from);
+                ui.label("To:");
+                ui.text_edit_singleline(&mut self.to);
+            });
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let parse = |s: &str| chrono::NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d");
+            let (from, to) = match (parse(&self.from), parse(&self.to)) {
+                (Ok(from), Ok(to)) if from <= to => (from, to),
+                _ => {
+                    ui.colored_label(egui::Color32::RED, "Dates look like 2024-01-31, and From comes first");
+                    return;
+                }
+            };
+
+            let months = report::by_period(acc.get_portfolio(), &budget::Period::default(), from, to);
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.heading("Income and expenses");
+                self.cash_flow(ui, &months);
+                ui.separator();
+
+                ui.heading("Spending per vope");
+                egui::ScrollArea::horizontal().show(ui, |ui| {
+                    self.spending_table(ui, &months);
+                });
+                ui.separator();
+
+                ui.heading("Budget against actual");
+                self.variance_table(ui, acc);
+            });
+        });
+    }
+}
@@ -1,13 +1,15 @@
 // Library imports
 use eframe::egui;
 // Local Library imports
-use app::dollar;
+use app::{dollar, journal, transaction};
 // Local imports
 use crate::features::vope_hist;
 
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct VopeMgr {
     selected: String,
+    show_archived: bool,
+    status: Option<String>,
 
     add_open: bool,
     add_name: String,
@@ -21,7 +23,13 @@ pub struct VopeMgr {
     edit_open: bool,
     edit_name: String,
     edit_budget: String,
-    edit_amount: String,
+    merge_into: String,
+
+    note_id: Option<transaction::TransactionId>,
+    note_payee: String,
+    note_memo: String,
+    note_notes: String,
+    note_tags: String,
 }
 
 impl Default for VopeMgr {
@@ -34,6 +42,8 @@ impl VopeMgr {
     fn new() -> Self {
         Self {
             selected: "Safety".to_owned(),
+            show_archived: false,
+            status: None,
             add_open: false,
             trans_open: false,
             edit_open: false,
@@ -44,7 +54,301 @@ impl VopeMgr {
             trans_amount: String::default(),
             edit_name: String::default(),
             edit_budget: String::default(),
-            edit_amount: String::default(),
+            merge_into: String::default(),
+            note_id: None,
+            note_payee: String::default(),
+            note_memo: String::default(),
+            note_notes: String::default(),
+            note_tags: String::default(),
+        }
+    }
+
+    /// Fills the edit window with the selected vope
+    fn open_edit(&mut self, acc: &app::Account) {
+        if let Some(v) = acc
+            .get_portfolio()
+            .view_vopes()
+            .iter()
+            .find(|v| v.name == self.selected)
+        {
+            self.edit_name = v.name.clone();
+            self.edit_budget = v.budget.to_decimal_string();
+            self.merge_into = String::default();
+            self.edit_open = true;
+        }
+    }
+
+    /// Fills the notes window with a transaction from the history
+    fn open_notes(&mut self, acc: &app::Account, id: transaction::TransactionId) {
+        if let Some(t) = acc.get_portfolio().find_transaction(&id) {
+            self.note_payee = t.payee.clone().unwrap_or_default();
+            self.note_memo = t.memo.clone().unwrap_or_default();
+            self.note_notes = t.notes.clone();
+            self.note_tags = t.tags.join(", ");
+            self.note_id = Some(id);
+        }
+    }
+
+    fn add_window(&mut self, ctx: &egui::Context, acc: &mut app::Account) {
+        let mut open = self.add_open;
+        let mut done = false;
+
+        egui::Window::new("Add").open(&mut open).show(ctx, |ui| {
+            egui::Grid::new("add_grid")
+                .num_columns(2)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Vope Name:");
+                    ui.text_edit_singleline(&mut self.add_name);
+                    ui.end_row();
+
+                    ui.label("Budgeted:");
+                    ui.text_edit_singleline(&mut self.add_budget);
+                    ui.end_row();
+                });
+
+            ui.separator();
+
+            if ui.button("Save").clicked() {
+                let name = self.add_name.trim().to_string();
+                let res = self
+                    .add_budget
+                    .parse::<dollar::Dollar>()
+                    .map_err(|e| format!("Budget: {}", e))
+                    .and_then(|budget| {
+                        acc.execute(journal::Command::AddVope { name: name.clone(), budget })
+                            .and_then(|_| acc.save())
+                            .map_err(|e| e.to_string())
+                    });
+
+                done = res.is_ok();
+                self.status = res.err();
+                if done {
+                    self.selected = name;
+                    self.add_name.clear();
+                    self.add_budget.clear();
+                }
+            }
+        });
+
+        self.add_open = open && !done;
+    }
+
+    fn transfer_window(&mut self, ctx: &egui::Context, acc: &mut app::Account) {
+        let mut open = self.trans_open;
+        let mut done = false;
+        let names = vope_names(acc, false);
+
+        egui::Window::new("Transfer").open(&mut open).show(ctx, |ui| {
+            egui::Grid::new("trans_grid")
+                .num_columns(2)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("From:");
+                    vope_combo(ui, "trans_from", &mut self.trans_from, &names);
+                    ui.end_row();
+
+                    ui.label("To:");
+                    vope_combo(ui, "trans_to", &mut self.trans_to, &names);
+                    ui.end_row();
+
+                    ui.label("Amount:");
+                    ui.text_edit_singleline(&mut self.trans_amount);
+                    ui.end_row();
+                });
+
+            ui.separator();
+
+            if ui.button("Save").clicked() {
+                let res = self
+                    .trans_amount
+                    .parse::<dollar::Dollar>()
+                    .map_err(|e| format!("Amount: {}", e))
+                    .and_then(|amount| {
+                        let cmd = journal::Command::Transfer {
+                            from: self.trans_from.clone(),
+                            to: self.trans_to.clone(),
+                            amount,
+                        };
+                        acc.execute(cmd)
+                            .and_then(|_| acc.save())
+                            .map_err(|e| e.to_string())
+                    });
+
+                done = res.is_ok();
+                self.status = res.err();
+                if done {
+                    self.trans_amount.clear();
+                }
+            }
+        });
+
+        self.trans_open = open && !done;
+    }
+
+    /// Rename, re-budget, merge, archive or delete the selected vope
+    fn edit_window(&mut self, ctx: &egui::Context, acc: &mut app::Account) {
+        let mut open = self.edit_open;
+        let mut done = false;
+        let selected = self.selected.clone();
+        let archived = acc
+            .get_portfolio()
+            .view_vopes()
+            .iter()
+            .any(|v| v.name == selected && v.archived);
+        let others: Vec<String> = vope_names(acc, true)
+            .into_iter()
+            .filter(|n| *n != selected)
+            .collect();
+
+        egui::Window::new(format!("Edit {}", selected))
+            .id(egui::Id::new("vope_edit"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let mut res: Option<Result<(), String>> = None;
+
+                egui::Grid::new("edit_grid")
+                    .num_columns(3)
+                    .spacing([20.0, 4.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Name:");
+                        ui.text_edit_singleline(&mut self.edit_name);
+                        if ui.button("Rename").clicked() {
+                            let name = self.edit_name.trim().to_string();
+                            res = Some(acc.rename_vope(&selected, &name).map_err(|e| e.to_string()));
+                            if let Some(Ok(_)) = res {
+                                self.selected = name;
+                            }
+                        }
+                        ui.end_row();
+
+                        ui.label("Budget:");
+                        ui.text_edit_singleline(&mut self.edit_budget);
+                        if ui.button("Set").clicked() {
+                            res = Some(
+                                self.edit_budget
+                                    .parse::<dollar::Dollar>()
+                                    .map_err(|e| format!("Budget: {}", e))
+                                    .and_then(|b| {
+                                        acc.get_portfolio_mut()
+                                            .set_budget(&selected, b)
+                                            .map_err(|e| e.to_string())
+                                    }),
+                            );
+                        }
+                        ui.end_row();
+
+                        ui.label("Merge into:");
+                        vope_combo(ui, "merge_into", &mut self.merge_into, &others);
+                        if ui
+                            .add_enabled(!self.merge_into.is_empty(), egui::Button::new("Merge"))
+                            .on_hover_text("Moves the balance and every transaction, then removes this vope")
+                            .clicked()
+                        {
+                            res = Some(acc.merge_vope(&selected, &self.merge_into).map_err(|e| e.to_string()));
+                            if let Some(Ok(_)) = res {
+                                self.selected = self.merge_into.clone();
+                                done = true;
+                            }
+                        }
+                        ui.end_row();
+                    });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    let label = if archived { "Unarchive" } else { "Archive" };
+                    if ui
+                        .button(label)
+                        .on_hover_text("Archived vopes keep their money and history, but are hidden")
+                        .clicked()
+                    {
+                        res = Some(
+                            acc.get_portfolio_mut()
+                                .set_archived(&selected, !archived)
+                                .map_err(|e| e.to_string()),
+                        );
+                    }
+
+                    if ui.button("Delete").clicked() {
+                        let cmd = journal::Command::RemoveVope { name: selected.clone() };
+                        res = Some(acc.execute(cmd).map_err(|e| e.to_string()));
+                        done = matches!(res, Some(Ok(_)));
+                    }
+                });
+
+                if let Some(r) = res {
+                    self.status = r.and_then(|_| acc.save().map_err(|e| e.to_string())).err();
+                }
+            });
+
+        self.edit_open = open && !done;
+    }
+
+    /// Payee, memo, notes and tags of one transaction
+    fn notes_window(&mut self, ctx: &egui::Context, acc: &mut app::Account) {
+        let Some(id) = self.note_id.clone() else {
+            return;
+        };
+        let Some(t) = acc.get_portfolio().find_transaction(&id).cloned() else {
+            self.note_id = None;
+            return;
+        };
+        let mut open = true;
+        let mut done = false;
+
+        egui::Window::new(format!("{} | {}", t.date, t.desc))
+            .id(egui::Id::new("vope_notes"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid::new("notes_grid")
+                    .num_columns(2)
+                    .spacing([40.0, 4.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Payee:");
+                        ui.text_edit_singleline(&mut self.note_payee);
+                        ui.end_row();
+
+                        ui.label("Memo:");
+                        ui.text_edit_singleline(&mut self.note_memo);
+                        ui.end_row();
+
+                        ui.label("Tags:");
+                        ui.text_edit_singleline(&mut self.note_tags)
+                            .on_hover_text("Separated by commas, such as reimbursable, gift");
+                        ui.end_row();
+
+                        ui.label("Notes:");
+                        ui.text_edit_multiline(&mut self.note_notes);
+                        ui.end_row();
+                    });
+
+                ui.separator();
+
+                if ui.button("Save").clicked() {
+                    let edit = transaction::TransactionEdit {
+                        payee: Some(self.note_payee.clone()),
+                        memo: Some(self.note_memo.clone()),
+                        notes: Some(self.note_notes.clone()),
+                        tags: Some(transaction::parse_tags(&self.note_tags)),
+                        ..Default::default()
+                    };
+                    let res = acc
+                        .get_portfolio_mut()
+                        .edit_transaction(&id, &edit)
+                        .and_then(|_| acc.save());
+
+                    done = res.is_ok();
+                    self.status = res.err().map(|e| e.to_string());
+                }
+            });
+
+        if !open || done {
+            self.note_id = None;
         }
     }
 }
@@ -55,213 +359,102 @@ impl super::AccDisp for VopeMgr {
     }
 
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame, acc: &mut Box<app::Account>) {
-        // let Self {
-        //     selected,
-        //     add_open,
-        //     trans_open,
-        //     edit_open,
-        //     add_name,
-        //     add_budget,
-        //     trans_from,
-        //     trans_to,
-        //     trans_amount,
-        //     edit_name,
-        //     edit_budget,
-        //     edit_amount,
-        // } = self;
-
-        // egui::TopBottomPanel::top("vope_mgr_header").show(ctx, |ui| {
-        //     ui.vertical_centered(|ui| {
-        //         ui.heading("Vope Mgr");
-        //     });
-        // });
-
-        // egui::TopBottomPanel::bottom("acc_history")
-        //     .resizable(true)
-        //     .show(ctx, |ui| {
-        //         ui.vertical_centered(|ui| {
-        //             ui.heading("Vope History");
-        //             ui.separator();
-        //         });
-
-        //         egui::ScrollArea::new([false, true]).show(ui, |scroll_ui| {
-        //             scroll_ui.add(vope_hist::VopeHist::new(acc.get_vope_history(selected)))
-        //         });
-        //     });
-
-        // egui::CentralPanel::default().show(ctx, |ui| {
-        //     ui.horizontal_wrapped(|ui| {
-        //         ui.label("Select a vope:");
-
-        //         if ui.button("+").clicked() {
-        //             *add_open = true;
-        //         }
-        //         ui.add_space(10.0);
-
-        //         if ui.button("Transfer").clicked() {
-        //             *trans_open = true;
-        //         }
-        //         ui.add_space(10.0);
-
-        //         if ui.button("Edit").clicked() {
-        //             *edit_open = true;
-        //         }
-        //     });
-
-        //     let mut add_temp = *add_open;
-
-        //     egui::Window::new("Add").open(add_open).show(ctx, |ui| {
-        //         egui::Grid::new("add_grid")
-        //             .num_columns(2)
-        //             .spacing([40.0, 4.0])
-        //             .striped(true)
-        //             .show(ui, |ui| {
-        //                 ui.label("Vope Name:");
-        //                 ui.add(egui::Separator::default().vertical());
-        //                 ui.add(egui::TextEdit::singleline(add_name));
-        //                 ui.end_row();
-
-        //                 ui.label("Budgeted:");
-        //                 ui.add(egui::Separator::default().vertical());
-        //                 ui.add(egui::TextEdit::singleline(add_budget));
-        //                 ui.end_row();
-        //             });
-
-        //         ui.separator();
-
-        //         ui.horizontal(|ui| {
-        //             if ui.button("save").clicked()
-        //                 && acc
-        //                     .add_vope(add_name.as_str(), dollar::Dollar::from(add_budget.as_str()))
-        //                     .is_ok()
-        //             {
-        //                 add_temp = false;
-        //             }
-
-        //             if ui.button("cancel").clicked() {
-        //                 add_temp = false;
-        //             }
-        //         });
-        //     });
-
-        //     *add_open &= add_temp;
-
-        //     let mut trans_temp = *trans_open;
-
-        //     egui::Window::new("Transfer")
-        //         .open(trans_open)
-        //         .show(ctx, |ui| {
-        //             egui::Grid::new("trans_grid")
-        //                 .num_columns(2)
-        //                 .spacing([40.0, 4.0])
-        //                 .striped(true)
-        //                 .show(ui, |ui| {
-        //                     ui.label("From:");
-        //                     ui.add(egui::Separator::default().vertical());
-        //                     ui.add(egui::TextEdit::singleline(trans_from));
-        //                     ui.end_row();
-
-        //                     ui.label("To:");
-        //                     ui.add(egui::Separator::default().vertical());
-        //                     ui.add(egui::TextEdit::singleline(trans_to));
-        //                     ui.end_row();
-
-        //                     ui.label("Amount:");
-        //                     ui.add(egui::Separator::default().vertical());
-        //                     ui.add(egui::TextEdit::singleline(trans_amount));
-        //                     ui.end_row();
-        //                 });
-
-        //             ui.separator();
-
-        //             ui.horizontal(|ui| {
-        //                 if ui.button("save").clicked()
-        //                     && acc
-        //                         .transfer(&trans_from, &trans_to, dollar::Dollar::from(trans_amount.as_str()))
-        //                         .is_ok()
-        //                 {
-        //                     // Save state here....
-        //                     trans_temp = false;
-        //                 }
-
-        //                 if ui.button("cancel").clicked() {
-        //                     trans_temp = false;
-        //                 }
-        //             });
-        //         });
-
-        //     *trans_open &= trans_temp;
-
-        //     let mut edit_temp = *edit_open;
-
-        //     egui::Window::new("Edit").open(edit_open).show(ctx, |ui| {
-        //         egui::Grid::new("edit_grid")
-        //             .num_columns(2)
-        //             .spacing([40.0, 4.0])
-        //             .striped(true)
-        //             .show(ui, |ui| {
-        //                 ui.label("Name:");
-        //                 ui.add(egui::Separator::default().vertical());
-        //                 ui.add(egui::TextEdit::singleline(edit_name));
-        //                 ui.end_row();
-
-        //                 ui.label("New Budget:");
-        //                 ui.add(egui::Separator::default().vertical());
-        //                 ui.add(egui::TextEdit::singleline(edit_budget));
-        //                 ui.end_row();
-
-        //                 ui.label("Set new amount:");
-        //                 ui.add(egui::Separator::default().vertical());
-        //                 ui.add(egui::TextEdit::singleline(edit_amount));
-        //                 ui.end_row();
-        //             });
-
-        //         ui.separator();
-
-        //         ui.horizontal(|ui| {
-        //             if ui.button("save").clicked() {
-        //                 let r1 = acc.rm_vope(&edit_name);
-        //                 let r2 = acc.add_vope(edit_name, dollar::Dollar::from(edit_budget.as_str()));
-
-        //                 if r1.is_ok() && r2.is_ok() {
-        //                     // Save state here....
-        //                     edit_temp = false;
-        //                 }
-        //             }
-
-        //             if ui.button("cancel").clicked() {
-        //                 edit_temp = false;
-        //             }
-
-        //             if ui.button("Delete").clicked() && acc.rm_vope(&edit_name).is_ok() {
-        //                 // Delete the vope here
-        //                 edit_temp = false;
-        //             }
-        //         });
-        //     });
-
-        //     *edit_open &= edit_temp;
-
-        //     ui.separator();
-
-        //     let len = acc.get_portfolio().accounts.len();
-
-        //     let min_width = ui.available_width() / (len as f32).sqrt().ceil();
-
-        //     ui.horizontal_wrapped(|ui| {
-        //         for v in acc.get_portfolio().accounts.iter() {
-        //             if ui
-        //                 .add(
-        //                     egui::Button::new(v.name.as_str())
-        //                         .min_size(egui::Vec2::new(min_width - 10.0, min_width / 2.0)),
-        //                 )
-        //                 .clicked()
-        //             {
-        //                 self.selected = v.name.clone();
-        //             }
-        //         }
-        //     });
-        // });
+        egui::TopBottomPanel::top("vope_mgr_header").show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.heading("Vope Mgr");
+            });
+        });
+
+        egui::TopBottomPanel::bottom("acc_history")
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.heading("Vope History");
+                    ui.separator();
+                });
+
+                let picked = egui::ScrollArea::new([false, true])
+                    .show(ui, |scroll_ui| {
+                        let history = acc.get_portfolio().get_vope_history(&self.selected).unwrap_or_default();
+                        vope_hist::VopeHist::new(history).show(scroll_ui)
+                    })
+                    .inner;
+                if let Some(id) = picked {
+                    self.open_notes(acc, id);
+                }
+            });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Select a vope:");
+
+                if ui.button("+").clicked() {
+                    self.add_open = true;
+                }
+                ui.add_space(10.0);
+
+                if ui.button("Transfer").clicked() {
+                    self.trans_open = true;
+                }
+                ui.add_space(10.0);
+
+                if ui.button("Edit").clicked() {
+                    self.open_edit(acc);
+                }
+                ui.add_space(10.0);
+
+                ui.checkbox(&mut self.show_archived, "Show archived");
+            });
+
+            if let Some(msg) = &self.status {
+                ui.colored_label(egui::Color32::RED, msg);
+            }
+
+            ui.separator();
+
+            let names = vope_names(acc, self.show_archived);
+            let min_width = ui.available_width() / (names.len().max(1) as f32).sqrt().ceil();
+
+            ui.horizontal_wrapped(|ui| {
+                for n in names.iter() {
+                    let button = egui::Button::new(n.as_str())
+                        .selected(*n == self.selected)
+                        .min_size(egui::Vec2::new(min_width - 10.0, min_width / 2.0));
+                    if ui.add(button).clicked() {
+                        self.selected = n.clone();
+                    }
+                }
+            });
+        });
+
+        self.add_window(ctx, acc);
+        self.transfer_window(ctx, acc);
+        self.edit_window(ctx, acc);
+        self.notes_window(ctx, acc);
     }
-    
+}
+
+/*
+ * The vopes by name, with or without the archived ones
+ */
+fn vope_names(acc: &app::Account, archived: bool) -> Vec<String> {
+    acc.get_portfolio()
+        .view_vopes()
+        .iter()
+        .filter(|v| archived || !v.archived)
+        .map(|v| v.name.clone())
+        .collect()
+}
+
+/*
+ * Picks one of `names` into `value`
+ */
+fn vope_combo(ui: &mut egui::Ui, id: &str, value: &mut String, names: &[String]) {
+    egui::ComboBox::from_id_source(id)
+        .selected_text(value.as_str())
+        .show_ui(ui, |ui| {
+            for n in names {
+                ui.selectable_value(value, n.clone(), n);
+            }
+        });
 }
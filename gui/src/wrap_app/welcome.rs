@@ -1,17 +1,22 @@
-use directories;
 use eframe::egui;
-use std::path;
+use native_dialog::FileDialog;
 
 use app;
 
 use super::{acc_mgmt, Content};
 
 /// Wraps many demo/test apps into one.
-pub struct Welcome {}
+pub struct Welcome {
+    status: Option<String>, // Why the last open failed
+    confirm_replace: bool,  // "New" found an account already there
+}
 
 impl Welcome {
     pub fn new() -> Welcome {
-        Welcome {}
+        Welcome {
+            status: None,
+            confirm_replace: false,
+        }
     }
 }
 
@@ -31,26 +36,45 @@ impl Content for Welcome {
                 
                 // Return this result
                 let op_acc = if ui.button("Open").clicked() {
-                    // Default account location is
-                    // %USERPROFILE%\AppData\Roaming\ButzIndustries\MoneyMan\data\acc.json
-                    let binding =
-                        directories::ProjectDirs::from("io", "ButzIndustries", "MoneyMan").unwrap();
-                    let path = path::Path::new(binding.data_dir()).join("acc.json");
-                                   
-                    Some(Box::new(app::Account::open(path).unwrap()))
+                    Some(app::Account::open(app::Account::default_path()))
                 } else if ui.button("Open from...").clicked() {
-                    // Do a browser.....
-                    todo!()
+                    FileDialog::new()
+                        .add_filter("Account File", &["json"])
+                        .show_open_single_file()
+                        .ok()
+                        .flatten()
+                        .map(app::Account::open)
                 } else if ui.button("New").clicked() {
-                    Some(Box::new(app::Account::new().unwrap()))
+                    Some(app::Account::new())
                 } else {
                     None
                 };
 
                 // If an account was opened, open the 
-                if let Some(acc) = op_acc {
-                    res = Some(Box::new(acc_mgmt::AccMgmt::new(acc)));
-                };
+                match op_acc {
+                    Some(Ok(acc)) => res = Some(Box::new(acc_mgmt::AccMgmt::new(Box::new(acc)))),
+                    Some(Err(e)) => {
+                        self.confirm_replace = matches!(e, app::Error::AccountExists(_));
+                        self.status = Some(e.to_string());
+                    }
+                    None => {}
+                }
+
+                if let Some(msg) = &self.status {
+                    ui.colored_label(egui::Color32::RED, msg);
+                }
+
+                if self.confirm_replace {
+                    let path = app::Account::default_path();
+                    let hint = format!("The old account is kept at {}", app::Account::replaced_path(&path).display());
+                    if ui.button("Replace it with a new account").on_hover_text(hint).clicked() {
+                        self.confirm_replace = false;
+                        match app::Account::replace_at(path) {
+                            Ok(acc) => res = Some(Box::new(acc_mgmt::AccMgmt::new(Box::new(acc)))),
+                            Err(e) => self.status = Some(e.to_string()),
+                        }
+                    }
+                }
 
             })
         });